@@ -72,6 +72,9 @@ pub const PF_TEXT_ALIGN_RIGHT:  u8 = 2;
 pub const PF_ARC_DIRECTION_CW:  u8 = 0;
 pub const PF_ARC_DIRECTION_CCW: u8 = 1;
 
+pub const PF_FILL_RULE_WINDING:  u8 = 0;
+pub const PF_FILL_RULE_EVEN_ODD: u8 = 1;
+
 // `gl`
 
 pub const PF_GL_VERSION_GL3:    u8 = 0;
@@ -99,6 +102,7 @@ pub type PFFillStyleRef = *mut FillStyle;
 pub type PFLineCap = u8;
 pub type PFLineJoin = u8;
 pub type PFArcDirection = u8;
+pub type PFFillRule = u8;
 pub type PFTextAlign = u8;
 #[repr(C)]
 pub struct PFTextMetrics {
@@ -415,6 +419,30 @@ pub unsafe extern "C" fn PFCanvasStrokePath(canvas: PFCanvasRef, path: PFPathRef
     (*canvas).stroke_path(*Box::from_raw(path))
 }
 
+/// Unlike `PFCanvasFillPath()`, this function does not destroy the path.
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasIsPointInPath(canvas: PFCanvasRef,
+                                               path: PFPathRef,
+                                               point: *const PFVector2F,
+                                               fill_rule: PFFillRule)
+                                               -> bool {
+    let fill_rule = match fill_rule {
+        PF_FILL_RULE_WINDING  => FillRule::Winding,
+        PF_FILL_RULE_EVEN_ODD => FillRule::EvenOdd,
+        _                     => panic!("Invalid Pathfinder fill rule!"),
+    };
+    (*canvas).is_point_in_path(&*path, (*point).to_rust(), fill_rule)
+}
+
+/// Unlike `PFCanvasStrokePath()`, this function does not destroy the path.
+#[no_mangle]
+pub unsafe extern "C" fn PFCanvasIsPointInStroke(canvas: PFCanvasRef,
+                                                 path: PFPathRef,
+                                                 point: *const PFVector2F)
+                                                 -> bool {
+    (*canvas).is_point_in_stroke(&*path, (*point).to_rust())
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn PFPathCreate() -> PFPathRef {
     Box::into_raw(Box::new(Path2D::new()))
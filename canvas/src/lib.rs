@@ -27,6 +27,7 @@ use pathfinder_content::pattern::Pattern;
 use pathfinder_content::render_target::RenderTargetId;
 use pathfinder_content::stroke::{LineJoin as StrokeLineJoin};
 use pathfinder_content::stroke::{OutlineStrokeToFill, StrokeStyle};
+use pathfinder_content::svg_path;
 use pathfinder_geometry::line_segment::LineSegment2F;
 use pathfinder_renderer::paint::{Paint, PaintCompositeOp};
 use pathfinder_renderer::scene::{ClipPath, ClipPathId, DrawPath, RenderTarget, Scene};
@@ -324,6 +325,12 @@ impl CanvasRenderingContext2D {
 
     #[inline]
     pub fn stroke_path(&mut self, path: Path2D) {
+        let outline = self.stroke_outline(path);
+        self.push_path(outline, PathOp::Stroke, FillRule::Winding);
+    }
+
+    // Converts the path to a fill, applying the current line styles and dash pattern.
+    fn stroke_outline(&self, path: Path2D) -> Outline {
         let mut stroke_style = self.current_state.resolve_stroke_style();
 
         // The smaller scale is relevant here, as we multiply by it and want to ensure it is always
@@ -347,9 +354,7 @@ impl CanvasRenderingContext2D {
 
         let mut stroke_to_fill = OutlineStrokeToFill::new(&outline, stroke_style);
         stroke_to_fill.offset();
-        outline = stroke_to_fill.into_outline();
-
-        self.push_path(outline, PathOp::Stroke, FillRule::Winding);
+        stroke_to_fill.into_outline()
     }
 
     pub fn clip_path(&mut self, path: Path2D, fill_rule: FillRule) {
@@ -481,6 +486,24 @@ impl CanvasRenderingContext2D {
 
     }
 
+    // Hit testing
+
+    /// Returns true if the given point, in canvas coordinates, lies inside the area that
+    /// `fill_path()` would fill for the given path and fill rule.
+    pub fn is_point_in_path(&self, path: &Path2D, point: Vector2F, fill_rule: FillRule) -> bool {
+        let mut outline = path.clone().into_outline();
+        outline.transform(&self.current_state.transform);
+        outline.contains_point(point, fill_rule)
+    }
+
+    /// Returns true if the given point, in canvas coordinates, lies inside the area that
+    /// `stroke_path()` would fill for the given path with the current line styles.
+    pub fn is_point_in_stroke(&self, path: &Path2D, point: Vector2F) -> bool {
+        let mut outline = self.stroke_outline(path.clone());
+        outline.transform(&self.current_state.transform);
+        outline.contains_point(point, FillRule::Winding)
+    }
+
     // Transformations
 
     #[inline]
@@ -595,6 +618,15 @@ impl CanvasRenderingContext2D {
         }
     }
 
+    // Gradients
+
+    /// Creates a conic gradient that sweeps clockwise around `center`, starting at `start_angle`
+    /// radians from the positive X axis.
+    #[inline]
+    pub fn create_conic_gradient(&self, start_angle: f32, center: Vector2F) -> Gradient {
+        Gradient::conic(center, start_angle)
+    }
+
     // Extensions
 
     pub fn create_pattern_from_canvas(&mut self, canvas: Canvas, transform: Transform2F)
@@ -714,6 +746,15 @@ impl Path2D {
         Path2D { outline: Outline::new(), current_contour: Contour::new() }
     }
 
+    /// Creates a path from SVG path data, like the HTML `new Path2D("M 0 0 L ...")` constructor.
+    ///
+    /// As in browsers, the path data is parsed up to the first error, and the rest is ignored.
+    pub fn from_svg(data: &str) -> Path2D {
+        let (mut outline, _) = svg_path::parse_prefix(data);
+        let current_contour = outline.pop_contour().unwrap_or_else(Contour::new);
+        Path2D { outline, current_contour }
+    }
+
     #[inline]
     pub fn close_path(&mut self) {
         self.current_contour.close();
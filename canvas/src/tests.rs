@@ -3,8 +3,10 @@
 // For this file only, any copyright is dedicated to the Public Domain.
 // https://creativecommons.org/publicdomain/zero/1.0/
 
+use pathfinder_content::fill::FillRule;
+use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::vector::{Vector2F, vec2f};
-use super::Path2D;
+use super::{Canvas, CanvasFontContext, Path2D};
 
 #[test]
 pub fn test_path2d_formatting() {
@@ -17,3 +19,30 @@ pub fn test_path2d_formatting() {
     path.close_path();
     assert_eq!(format!("{:?}", path), "M 0 1 L 2 3 L 4 5 z");
 }
+
+#[test]
+pub fn test_hit_testing() {
+    let mut canvas = Canvas::new(vec2f(100.0, 100.0))
+        .get_context_2d(CanvasFontContext::from_system_source());
+    canvas.translate(vec2f(10.0, 10.0));
+    canvas.set_line_width(4.0);
+
+    let mut path = Path2D::new();
+    path.rect(RectF::new(Vector2F::zero(), vec2f(20.0, 20.0)));
+    assert!(canvas.is_point_in_path(&path, vec2f(15.0, 15.0), FillRule::Winding));
+    assert!(!canvas.is_point_in_path(&path, vec2f(5.0, 5.0), FillRule::Winding));
+
+    assert!(canvas.is_point_in_stroke(&path, vec2f(9.0, 15.0)));
+    assert!(!canvas.is_point_in_stroke(&path, vec2f(15.0, 15.0)));
+    assert!(!canvas.is_point_in_stroke(&path, vec2f(7.0, 15.0)));
+}
+
+#[test]
+pub fn test_path2d_from_svg() {
+    let path = Path2D::from_svg("M0,1 l2,2 H4 z");
+    assert_eq!(format!("{:?}", path), "M 0 1 L 2 3 L 4 3 z");
+
+    // Everything after the first error is ignored.
+    let path = Path2D::from_svg("M 0 1 L 2 3 L 4 X 6 7");
+    assert_eq!(format!("{:?}", path), "M 0 1 L 2 3");
+}
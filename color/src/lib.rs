@@ -209,6 +209,74 @@ impl ColorF {
     pub fn set_a(&mut self, a: f32) {
         self.0[3] = a;
     }
+
+    // Color space conversion
+
+    /// Converts a color with gamma-encoded sRGB channels to linear sRGB. Alpha is unchanged.
+    #[inline]
+    pub fn srgb_to_linear(&self) -> ColorF {
+        ColorF::new(srgb_channel_to_linear(self.r()),
+                    srgb_channel_to_linear(self.g()),
+                    srgb_channel_to_linear(self.b()),
+                    self.a())
+    }
+
+    /// Converts a color with linear sRGB channels to gamma-encoded sRGB. Alpha is unchanged.
+    #[inline]
+    pub fn linear_to_srgb(&self) -> ColorF {
+        ColorF::new(linear_channel_to_srgb(self.r()),
+                    linear_channel_to_srgb(self.g()),
+                    linear_channel_to_srgb(self.b()),
+                    self.a())
+    }
+
+    /// Converts a gamma-encoded sRGB color to OKLab, with the L, a, and b components stored in
+    /// the red, green, and blue channels respectively. Alpha is unchanged.
+    ///
+    /// See https://bottosson.github.io/posts/oklab/.
+    pub fn srgb_to_oklab(&self) -> ColorF {
+        let linear = self.srgb_to_linear();
+        let (r, g, b) = (linear.r(), linear.g(), linear.b());
+        let l = f32::cbrt(0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b);
+        let m = f32::cbrt(0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b);
+        let s = f32::cbrt(0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b);
+        ColorF::new(0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+                    1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+                    0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+                    self.a())
+    }
+
+    /// Converts an OKLab color, laid out as `srgb_to_oklab()` produces, back to gamma-encoded
+    /// sRGB. Colors outside the sRGB gamut are not clamped.
+    pub fn oklab_to_srgb(&self) -> ColorF {
+        let (l, a, b) = (self.r(), self.g(), self.b());
+        let l_ = l + 0.396_337_78 * a + 0.215_803_76 * b;
+        let m_ = l - 0.105_561_346 * a - 0.063_854_17 * b;
+        let s_ = l - 0.089_484_18 * a - 1.291_485_5 * b;
+        let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+        ColorF::new(4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+                    -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+                    -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+                    self.a()).linear_to_srgb()
+    }
+}
+
+#[inline]
+fn srgb_channel_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        f32::powf((value + 0.055) / 1.055, 2.4)
+    }
+}
+
+#[inline]
+fn linear_channel_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * f32::powf(value, 1.0 / 2.4) - 0.055
+    }
 }
 
 impl Debug for ColorF {
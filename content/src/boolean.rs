@@ -0,0 +1,613 @@
+// pathfinder/content/src/boolean.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Boolean operations on outlines: union, intersection, difference, and exclusive or.
+//!
+//! Curves are flattened to line segments first, so results consist only of straight lines. All
+//! contours are treated as closed, as they are when filled.
+//!
+//! The algorithm splits every edge at every point where it meets another edge, classifies each
+//! resulting piece by the winding numbers on either side of it, and keeps the pieces that separate
+//! the inside of the result from the outside. Vertices are snapped to a fixed grid so that the
+//! geometric predicates can be computed exactly.
+
+use crate::fill::FillRule;
+use crate::outline::{Contour, ContourIterFlags, Outline};
+use crate::segment::Segment;
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+const FLATTENING_TOLERANCE: f32 = 0.1;
+
+// The number of grid cells per unit that vertices are snapped to.
+const GRID_SCALE: f32 = 256.0;
+
+/// A boolean operation on two outlines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BooleanOp {
+    /// The area covered by either outline.
+    Union,
+    /// The area covered by both outlines.
+    Intersection,
+    /// The area covered by the first outline but not the second.
+    Difference,
+    /// The area covered by exactly one of the outlines.
+    Xor,
+}
+
+impl BooleanOp {
+    #[inline]
+    fn apply(self, a: bool, b: bool) -> bool {
+        match self {
+            BooleanOp::Union => a || b,
+            BooleanOp::Intersection => a && b,
+            BooleanOp::Difference => a && !b,
+            BooleanOp::Xor => a != b,
+        }
+    }
+}
+
+/// Computes the given boolean operation on the areas filled by `a` and `b` under `fill_rule`.
+///
+/// The contours of the result don't intersect one another or themselves, and each is oriented
+/// so that its inside lies to its left, so the result fills the same area under either fill rule.
+pub fn boolean_op(a: &Outline, b: &Outline, op: BooleanOp, fill_rule: FillRule) -> Outline {
+    let mut edges = vec![];
+    flatten_outline(a, 0, &mut edges);
+    flatten_outline(b, 1, &mut edges);
+    let edges = split_edges(&edges);
+    let edges = classify_edges(&edges, |winding| {
+        op.apply(is_inside(winding[0], fill_rule), is_inside(winding[1], fill_rule))
+    });
+    link_edges(&edges)
+}
+
+/// Returns an outline filling the same area as `outline` does under `fill_rule`, but without any
+/// self-intersections.
+pub fn simplify(outline: &Outline, fill_rule: FillRule) -> Outline {
+    boolean_op(outline, &Outline::new(), BooleanOp::Union, fill_rule)
+}
+
+#[inline]
+fn is_inside(winding: i32, fill_rule: FillRule) -> bool {
+    match fill_rule {
+        FillRule::Winding => winding != 0,
+        FillRule::EvenOdd => winding % 2 != 0,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct GridPoint {
+    x: i64,
+    y: i64,
+}
+
+impl GridPoint {
+    #[inline]
+    fn snap(point: Vector2F) -> GridPoint {
+        GridPoint {
+            x: f32::round(point.x() * GRID_SCALE) as i64,
+            y: f32::round(point.y() * GRID_SCALE) as i64,
+        }
+    }
+
+    #[inline]
+    fn to_f32(self) -> Vector2F {
+        vec2f(self.x as f32, self.y as f32) * (1.0 / GRID_SCALE)
+    }
+
+    #[inline]
+    fn sub(self, other: GridPoint) -> (i64, i64) {
+        (self.x - other.x, self.y - other.y)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Edge {
+    from: GridPoint,
+    to: GridPoint,
+    operand: usize,
+}
+
+impl Edge {
+    #[inline]
+    fn min_x(&self) -> i64 { self.from.x.min(self.to.x) }
+    #[inline]
+    fn max_x(&self) -> i64 { self.from.x.max(self.to.x) }
+    #[inline]
+    fn min_y(&self) -> i64 { self.from.y.min(self.to.y) }
+    #[inline]
+    fn max_y(&self) -> i64 { self.from.y.max(self.to.y) }
+}
+
+// Twice the signed area of the triangle `a`, `b`, `c`.
+#[inline]
+fn orient(a: GridPoint, b: GridPoint, c: GridPoint) -> i64 {
+    let (ab, ac) = (b.sub(a), c.sub(a));
+    ab.0 * ac.1 - ab.1 * ac.0
+}
+
+#[inline]
+fn dot(a: (i64, i64), b: (i64, i64)) -> i64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+// Returns true if `point`, which must be collinear with the edge, lies strictly between its
+// endpoints.
+#[inline]
+fn lies_within(edge: &Edge, point: GridPoint) -> bool {
+    dot(point.sub(edge.from), edge.to.sub(edge.from)) > 0 &&
+        dot(point.sub(edge.to), edge.from.sub(edge.to)) > 0
+}
+
+fn flatten_outline(outline: &Outline, operand: usize, edges: &mut Vec<Edge>) {
+    let mut points = vec![];
+    for contour in outline.contours() {
+        points.clear();
+        match contour.first_position() {
+            None => continue,
+            Some(first_position) => points.push(GridPoint::snap(first_position)),
+        }
+        for segment in contour.iter(ContourIterFlags::IGNORE_CLOSE_SEGMENT) {
+            flatten_segment(&segment, &mut points);
+        }
+
+        // Contours are implicitly closed when filled.
+        points.push(points[0]);
+        for pair in points.windows(2) {
+            if pair[0] != pair[1] {
+                edges.push(Edge { from: pair[0], to: pair[1], operand });
+            }
+        }
+    }
+}
+
+fn flatten_segment(segment: &Segment, points: &mut Vec<GridPoint>) {
+    if segment.is_none() {
+        return;
+    }
+    if segment.is_quadratic() {
+        return flatten_segment(&segment.to_cubic(), points);
+    }
+    if segment.is_line() ||
+            (segment.is_cubic() && segment.as_cubic_segment().is_flat(FLATTENING_TOLERANCE)) {
+        points.push(GridPoint::snap(segment.baseline.to()));
+        return;
+    }
+
+    let (prev, next) = segment.split(0.5);
+    flatten_segment(&prev, points);
+    flatten_segment(&next, points);
+}
+
+// Splits edges wherever they cross or touch, so that afterward edges meet only at their endpoints.
+fn split_edges(edges: &[Edge]) -> Vec<Edge> {
+    let mut split_points = vec![vec![]; edges.len()];
+    let mut order: Vec<usize> = (0..edges.len()).collect();
+    order.sort_by_key(|&index| edges[index].min_x());
+
+    for (order_index, &index_a) in order.iter().enumerate() {
+        let a = &edges[index_a];
+        for &index_b in &order[(order_index + 1)..] {
+            let b = &edges[index_b];
+            if b.min_x() > a.max_x() {
+                break;
+            }
+            if b.min_y() > a.max_y() || b.max_y() < a.min_y() {
+                continue;
+            }
+
+            let (points_on_a, points_on_b) = intersect(a, b);
+            split_points[index_a].extend(points_on_a.iter().filter_map(|point| *point));
+            split_points[index_b].extend(points_on_b.iter().filter_map(|point| *point));
+        }
+    }
+
+    let mut split_edges = vec![];
+    for (edge, points) in edges.iter().zip(split_points.iter_mut()) {
+        let vector = edge.to.sub(edge.from);
+        points.sort_by_key(|point| dot(point.sub(edge.from), vector));
+        points.dedup();
+
+        let mut from = edge.from;
+        for &to in points.iter().chain(Some(edge.to).iter()) {
+            if to != from {
+                split_edges.push(Edge { from, to, operand: edge.operand });
+                from = to;
+            }
+        }
+    }
+    split_edges
+}
+
+// Returns the points at which each edge must be split where it meets the other.
+fn intersect(a: &Edge, b: &Edge) -> ([Option<GridPoint>; 2], [Option<GridPoint>; 2]) {
+    let (mut points_on_a, mut points_on_b) = ([None; 2], [None; 2]);
+    let (b_from_side, b_to_side) = (orient(a.from, a.to, b.from), orient(a.from, a.to, b.to));
+    let (a_from_side, a_to_side) = (orient(b.from, b.to, a.from), orient(b.from, b.to, a.to));
+
+    if b_from_side == 0 && b_to_side == 0 {
+        // The edges are collinear. Split each at the endpoints of the other that overlap it.
+        for (slot, &point) in points_on_a.iter_mut().zip([b.from, b.to].iter()) {
+            if lies_within(a, point) {
+                *slot = Some(point);
+            }
+        }
+        for (slot, &point) in points_on_b.iter_mut().zip([a.from, a.to].iter()) {
+            if lies_within(b, point) {
+                *slot = Some(point);
+            }
+        }
+        return (points_on_a, points_on_b);
+    }
+
+    if b_from_side.signum() * b_to_side.signum() > 0 ||
+            a_from_side.signum() * a_to_side.signum() > 0 {
+        return (points_on_a, points_on_b);
+    }
+
+    if b_from_side != 0 && b_to_side != 0 && a_from_side != 0 && a_to_side != 0 {
+        // The edges cross properly.
+        let t = a_from_side as f64 / (a_from_side - a_to_side) as f64;
+        let vector = a.to.sub(a.from);
+        let point = GridPoint {
+            x: a.from.x + f64::round(vector.0 as f64 * t) as i64,
+            y: a.from.y + f64::round(vector.1 as f64 * t) as i64,
+        };
+        if point != a.from && point != a.to {
+            points_on_a[0] = Some(point);
+        }
+        if point != b.from && point != b.to {
+            points_on_b[0] = Some(point);
+        }
+        return (points_on_a, points_on_b);
+    }
+
+    // An endpoint of one edge touches the other.
+    if b_from_side == 0 && lies_within(a, b.from) {
+        points_on_a[0] = Some(b.from);
+    }
+    if b_to_side == 0 && lies_within(a, b.to) {
+        points_on_a[1] = Some(b.to);
+    }
+    if a_from_side == 0 && lies_within(b, a.from) {
+        points_on_b[0] = Some(a.from);
+    }
+    if a_to_side == 0 && lies_within(b, a.to) {
+        points_on_b[1] = Some(a.to);
+    }
+    (points_on_a, points_on_b)
+}
+
+// Keeps the edges that have the inside of the result on exactly one side, oriented so that the
+// inside lies to their left.
+fn classify_edges<F>(edges: &[Edge], is_inside: F) -> Vec<Edge> where F: Fn([i32; 2]) -> bool {
+    // Merge coincident edges, tracking the net number of times each operand crosses them.
+    let mut groups: BTreeMap<(GridPoint, GridPoint), [i32; 2]> = BTreeMap::new();
+    for edge in edges {
+        let (key, direction) = match edge.from.cmp(&edge.to) {
+            Ordering::Less => ((edge.from, edge.to), 1),
+            _ => ((edge.to, edge.from), -1),
+        };
+        groups.entry(key).or_insert([0; 2])[edge.operand] += direction;
+    }
+    let groups: Vec<_> = groups.into_iter().collect();
+
+    // Find the winding numbers just beside the midpoint of each edge, casting rays along the x
+    // axis from edges that aren't horizontal and along the y axis from those that are.
+    let mut windings = vec![[0; 2]; groups.len()];
+    sweep_windings(&groups, SweepAxis::Y, &mut windings);
+    sweep_windings(&groups, SweepAxis::X, &mut windings);
+
+    let mut result = vec![];
+    for (&((from, to), crossings), &winding) in groups.iter().zip(windings.iter()) {
+        // `winding` lies on the side of the edge toward negative cross coordinates. Crossing the
+        // edge toward positive ones adds its crossings in the direction it sweeps.
+        let axis = SweepAxis::for_edge(from, to);
+        let (from_coords, to_coords) = (axis.coords(from), axis.coords(to));
+        let direction = if from_coords.0 < to_coords.0 { 1 } else { -1 };
+        let other_winding = [winding[0] + direction * crossings[0],
+                             winding[1] + direction * crossings[1]];
+        let (left_winding, right_winding) = if axis.negative_side_is_left(from, to) {
+            (winding, other_winding)
+        } else {
+            (other_winding, winding)
+        };
+
+        match (is_inside(left_winding), is_inside(right_winding)) {
+            (true, false) => result.push(Edge { from, to, operand: 0 }),
+            (false, true) => result.push(Edge { from: to, to: from, operand: 0 }),
+            _ => {}
+        }
+    }
+    result
+}
+
+// The axis that a sweep moves along. Rays are cast perpendicular to it, toward negative
+// coordinates.
+#[derive(Clone, Copy, PartialEq)]
+enum SweepAxis {
+    X,
+    Y,
+}
+
+impl SweepAxis {
+    // Returns the axis to sweep along to find the winding numbers beside the given edge.
+    #[inline]
+    fn for_edge(from: GridPoint, to: GridPoint) -> SweepAxis {
+        if from.y == to.y { SweepAxis::X } else { SweepAxis::Y }
+    }
+
+    // Returns the sweep and cross coordinates of the given point, doubled so that the midpoints
+    // of edges lie on the grid.
+    #[inline]
+    fn coords(self, point: GridPoint) -> (i128, i128) {
+        match self {
+            SweepAxis::X => (2 * point.x as i128, 2 * point.y as i128),
+            SweepAxis::Y => (2 * point.y as i128, 2 * point.x as i128),
+        }
+    }
+
+    // Returns true if the side of the edge toward negative cross coordinates is on its left.
+    #[inline]
+    fn negative_side_is_left(self, from: GridPoint, to: GridPoint) -> bool {
+        match self {
+            SweepAxis::X => to.x < from.x,
+            SweepAxis::Y => to.y > from.y,
+        }
+    }
+}
+
+// Sweeps along `axis`, finding the winding numbers just beside the midpoints of the edges that
+// are swept along it. Each ray is tested only against the edges that span its sweep coordinate.
+//
+// An edge spans the coordinates from its lower end up to, but not including, its upper end, so
+// that a ray passing through a vertex counts the edges meeting there correctly.
+fn sweep_windings(groups: &[((GridPoint, GridPoint), [i32; 2])],
+                  axis: SweepAxis,
+                  windings: &mut [[i32; 2]]) {
+    let span = |index: usize| {
+        let ((from, to), _) = groups[index];
+        let (from, to) = (axis.coords(from).0, axis.coords(to).0);
+        (from.min(to), from.max(to))
+    };
+
+    let mut edges: Vec<usize> = (0..groups.len()).filter(|&index| {
+        let (min, max) = span(index);
+        min < max
+    }).collect();
+    edges.sort_by_key(|&index| span(index).0);
+
+    let mut rays: Vec<(i128, i128, usize)> = edges.iter().filter(|&&index| {
+        let ((from, to), _) = groups[index];
+        SweepAxis::for_edge(from, to) == axis
+    }).map(|&index| {
+        let ((from, to), _) = groups[index];
+        let (from, to) = (axis.coords(from), axis.coords(to));
+        ((from.0 + to.0) / 2, (from.1 + to.1) / 2, index)
+    }).collect();
+    rays.sort();
+
+    let (mut active, mut next_edge) = (vec![], 0);
+    for &(ray_sweep, ray_cross, ray_index) in &rays {
+        while next_edge < edges.len() && span(edges[next_edge]).0 <= ray_sweep {
+            active.push(edges[next_edge]);
+            next_edge += 1;
+        }
+        active.retain(|&index| span(index).1 > ray_sweep);
+
+        for &index in &active {
+            if index == ray_index {
+                continue;
+            }
+
+            // Check whether the edge lies toward negative cross coordinates where it meets the
+            // sweep coordinate of the ray.
+            let ((from, to), crossings) = groups[index];
+            let (from, to) = (axis.coords(from), axis.coords(to));
+            let (direction, (lower, upper)) =
+                if from.0 < to.0 { (1, (from, to)) } else { (-1, (to, from)) };
+            let (extent, slope) = (upper.0 - lower.0, upper.1 - lower.1);
+            if (lower.1 - ray_cross) * extent + (ray_sweep - lower.0) * slope < 0 {
+                for operand in 0..2 {
+                    windings[ray_index][operand] += direction * crossings[operand];
+                }
+            }
+        }
+    }
+}
+
+// Links directed edges into closed contours.
+fn link_edges(edges: &[Edge]) -> Outline {
+    let mut outgoing: BTreeMap<GridPoint, Vec<usize>> = BTreeMap::new();
+    for (edge_index, edge) in edges.iter().enumerate() {
+        outgoing.entry(edge.from).or_default().push(edge_index);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut outline = Outline::new();
+    for first_index in 0..edges.len() {
+        if used[first_index] {
+            continue;
+        }
+
+        let mut points = vec![edges[first_index].from];
+        let mut edge_index = first_index;
+        loop {
+            used[edge_index] = true;
+            let edge = edges[edge_index];
+            if edge.to == edges[first_index].from {
+                break;
+            }
+            points.push(edge.to);
+
+            // Where several edges leave the same vertex, take the one that turns furthest to the
+            // left, so that regions touching at a single point become separate contours.
+            let direction = edge.to.sub(edge.from);
+            let turn = |index: usize| turn_angle(direction, edges[index].to.sub(edges[index].from));
+            let next_index = outgoing[&edge.to]
+                .iter()
+                .cloned()
+                .filter(|&index| !used[index])
+                .max_by(|&a, &b| turn(a).partial_cmp(&turn(b)).unwrap_or(Ordering::Equal));
+            match next_index {
+                Some(next_index) => edge_index = next_index,
+                None => break,
+            }
+        }
+
+        let contour = contour_from_points(&points);
+        if !contour.is_empty() {
+            outline.push_contour(contour);
+        }
+    }
+    outline
+}
+
+#[inline]
+fn turn_angle(from: (i64, i64), to: (i64, i64)) -> f64 {
+    let cross = from.0 as f64 * to.1 as f64 - from.1 as f64 * to.0 as f64;
+    f64::atan2(cross, dot(from, to) as f64)
+}
+
+// Builds a closed contour from the given points, dropping points that lie on a straight line
+// between their neighbors.
+fn contour_from_points(points: &[GridPoint]) -> Contour {
+    let mut kept: Vec<GridPoint> = vec![];
+    for &point in points {
+        while kept.len() >= 2 && is_redundant(kept[kept.len() - 2], kept[kept.len() - 1], point) {
+            kept.pop();
+        }
+        kept.push(point);
+    }
+    while kept.len() >= 3 && is_redundant(kept[kept.len() - 2], kept[kept.len() - 1], kept[0]) {
+        kept.pop();
+    }
+    while kept.len() >= 3 && is_redundant(kept[kept.len() - 1], kept[0], kept[1]) {
+        kept.remove(0);
+    }
+
+    let mut contour = Contour::with_capacity(kept.len());
+    if kept.len() < 3 {
+        return contour;
+    }
+    for point in kept {
+        contour.push_endpoint(point.to_f32());
+    }
+    contour.close();
+    contour
+}
+
+#[inline]
+fn is_redundant(prev: GridPoint, point: GridPoint, next: GridPoint) -> bool {
+    orient(prev, point, next) == 0 && dot(point.sub(prev), next.sub(point)) > 0
+}
+
+#[cfg(test)]
+mod test {
+    use crate::boolean::{self, BooleanOp};
+    use crate::fill::FillRule;
+    use crate::outline::{Contour, ContourIterFlags, Outline};
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::{Vector2F, vec2f};
+
+    fn area(outline: &Outline) -> f32 {
+        let mut area = 0.0;
+        for contour in outline.contours() {
+            for segment in contour.iter(ContourIterFlags::empty()) {
+                let (from, to) = (segment.baseline.from(), segment.baseline.to());
+                area += from.x() * to.y() - to.x() * from.y();
+            }
+        }
+        area * 0.5
+    }
+
+    fn square(origin: Vector2F, size: f32) -> Outline {
+        Outline::from_rect(RectF::new(origin, Vector2F::splat(size)))
+    }
+
+    #[test]
+    fn overlapping_squares() {
+        let a = square(Vector2F::zero(), 10.0);
+        let b = square(vec2f(5.0, 5.0), 10.0);
+        let expected = [
+            (BooleanOp::Union, 175.0, 1),
+            (BooleanOp::Intersection, 25.0, 1),
+            (BooleanOp::Difference, 75.0, 1),
+            (BooleanOp::Xor, 150.0, 2),
+        ];
+        for &(op, expected_area, expected_contours) in &expected {
+            let result = boolean::boolean_op(&a, &b, op, FillRule::Winding);
+            assert_eq!(result.contours().len(), expected_contours, "{:?}", op);
+            assert!(f32::abs(f32::abs(area(&result)) - expected_area) < 0.01, "{:?}", op);
+        }
+
+        let union = boolean::boolean_op(&a, &b, BooleanOp::Union, FillRule::Winding);
+        assert_eq!(union.contours()[0].len(), 8);
+    }
+
+    #[test]
+    fn disjoint_and_nested() {
+        let outer = square(Vector2F::zero(), 10.0);
+        let inner = square(vec2f(2.0, 2.0), 4.0);
+        let far = square(vec2f(20.0, 0.0), 5.0);
+
+        let hole = boolean::boolean_op(&outer, &inner, BooleanOp::Difference, FillRule::Winding);
+        assert_eq!(hole.contours().len(), 2);
+        assert!(f32::abs(f32::abs(area(&hole)) - 84.0) < 0.01);
+
+        let both = boolean::boolean_op(&outer, &far, BooleanOp::Union, FillRule::Winding);
+        assert_eq!(both.contours().len(), 2);
+        assert!(f32::abs(f32::abs(area(&both)) - 125.0) < 0.01);
+
+        let none = boolean::boolean_op(&outer, &far, BooleanOp::Intersection, FillRule::Winding);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn many_overlapping_shapes() {
+        // A diagonal chain of squares, each overlapping the last by a quarter.
+        let mut squares = Outline::new();
+        for index in 0..20 {
+            let origin = Vector2F::splat(index as f32 * 5.0);
+            squares.push_contour(Contour::from_rect(RectF::new(origin, Vector2F::splat(10.0))));
+        }
+        let union = boolean::simplify(&squares, FillRule::Winding);
+        assert_eq!(union.contours().len(), 1);
+        assert!(f32::abs(f32::abs(area(&union)) - (100.0 + 19.0 * 75.0)) < 0.01);
+
+        // Under the even-odd rule, the overlaps are left out. That leaves an L shape at each end
+        // and two squares, touching at a corner, in each of the others.
+        let even_odd = boolean::simplify(&squares, FillRule::EvenOdd);
+        assert_eq!(even_odd.contours().len(), 2 + 18 * 2);
+        assert!(f32::abs(f32::abs(area(&even_odd)) - (2.0 * 75.0 + 18.0 * 50.0)) < 0.01);
+    }
+
+    #[test]
+    fn simplify_respects_fill_rule() {
+        // A pentagram, whose center is covered twice.
+        let mut contour = Contour::new();
+        for index in 0..5 {
+            let angle = (index * 2) as f32 * std::f32::consts::PI * 2.0 / 5.0;
+            contour.push_endpoint(vec2f(f32::sin(angle), -f32::cos(angle)) * 100.0);
+        }
+        contour.close();
+        let mut star = Outline::new();
+        star.push_contour(contour);
+
+        let winding = boolean::simplify(&star, FillRule::Winding);
+        let even_odd = boolean::simplify(&star, FillRule::EvenOdd);
+        assert_eq!(winding.contours().len(), 1);
+        assert_eq!(winding.contours()[0].len(), 10);
+        assert_eq!(even_odd.contours().len(), 5);
+        assert!(f32::abs(area(&winding)) > f32::abs(area(&even_odd)));
+    }
+}
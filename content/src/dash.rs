@@ -85,13 +85,14 @@ impl<'a, 'b, 'c> ContourDash<'a, 'b, 'c> {
             let mut current_segment = queued_segment.take().unwrap();
             let mut distance = self.state.distance_left;
 
-            let t = current_segment.time_for_distance(distance);
-            if t < 1.0 {
+            let length = current_segment.arc_length();
+            if distance < length {
+                let t = current_segment.time_for_distance_with_length(distance, length);
                 let (prev_segment, next_segment) = current_segment.split(t);
                 current_segment = prev_segment;
                 queued_segment = Some(next_segment);
             } else {
-                distance = current_segment.arc_length();
+                distance = length;
             }
 
             if self.state.is_on() {
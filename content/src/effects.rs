@@ -55,6 +55,16 @@ pub enum Filter {
         uv_origin: Vector2F,
     },
 
+    /// Converts a linear gradient to a conic one.
+    ConicGradient {
+        /// The point that the gradient sweeps around.
+        center: Vector2F,
+        /// The angle at which the gradient starts, in radians.
+        start_angle: f32,
+        /// The origin of the linearized gradient in the texture.
+        uv_origin: Vector2F,
+    },
+
     PatternFilter(PatternFilter),
 }
 
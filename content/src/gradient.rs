@@ -11,12 +11,12 @@
 //! Gradient effects that paths can be filled with.
 
 use crate::util;
-use pathfinder_color::ColorU;
+use pathfinder_color::{ColorF, ColorU};
 use pathfinder_geometry::line_segment::LineSegment2F;
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::Vector2F;
 use pathfinder_geometry::util as geometry_util;
-use pathfinder_simd::default::F32x2;
+use pathfinder_simd::default::{F32x2, F32x4};
 use std::cmp::Ordering;
 use std::convert;
 use std::hash::{Hash, Hasher};
@@ -27,6 +27,7 @@ pub struct Gradient {
     pub geometry: GradientGeometry,
     stops: Vec<ColorStop>,
     pub wrap: GradientWrap,
+    pub interpolation: GradientInterpolation,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -49,13 +50,50 @@ pub enum GradientGeometry {
         /// Like `gradientTransform` in SVG. Note that this is the inverse of Cairo's gradient
         /// transform.
         transform: Transform2F,
-    }
+    },
+    /// A gradient that sweeps around a center point, also known as a sweep gradient.
+    Conic {
+        /// The point that the gradient sweeps around.
+        center: Vector2F,
+        /// The angle at which the gradient starts, in radians, measured clockwise from the
+        /// positive X axis (with Y pointing down).
+        start_angle: f32,
+        /// Transform from conic gradient space into screen space.
+        transform: Transform2F,
+    },
 }
 
+/// What happens outside the gradient's range of 0 to 1.
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum GradientWrap {
+    /// The colors at the ends of the gradient extend outward.
     Clamp,
+    /// The gradient repeats.
     Repeat,
+    /// The gradient repeats, mirrored on every other repetition.
+    Reflect,
+}
+
+/// How colors are blended between adjacent color stops.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GradientInterpolation {
+    /// The color space that the stops are interpolated in.
+    pub color_space: GradientColorSpace,
+    /// Whether the colors are premultiplied by their alpha before interpolating.
+    ///
+    /// Premultiplying prevents transparent stops from tinting their neighbors, as in CSS.
+    pub premultiplied: bool,
+}
+
+/// A color space that gradients can be interpolated in.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GradientColorSpace {
+    /// Gamma-encoded sRGB. This is what SVG and HTML canvas use.
+    SRGB,
+    /// sRGB with the transfer function removed.
+    LinearSRGB,
+    /// The perceptually-uniform OKLab color space, like CSS `in oklab`.
+    OKLab,
 }
 
 impl Eq for Gradient {}
@@ -79,8 +117,21 @@ impl Hash for Gradient {
                 util::hash_f32(transform.m22(), state);
                 util::hash_f32(transform.m23(), state);
             }
+            GradientGeometry::Conic { center, start_angle, transform } => {
+                (2).hash(state);
+                util::hash_f32(center.x(), state);
+                util::hash_f32(center.y(), state);
+                util::hash_f32(start_angle, state);
+                util::hash_f32(transform.m11(), state);
+                util::hash_f32(transform.m12(), state);
+                util::hash_f32(transform.m13(), state);
+                util::hash_f32(transform.m21(), state);
+                util::hash_f32(transform.m22(), state);
+                util::hash_f32(transform.m23(), state);
+            }
         }
         self.stops.hash(state);
+        self.interpolation.hash(state);
     }
 }
 
@@ -103,6 +154,7 @@ impl Gradient {
             geometry: GradientGeometry::Linear(line),
             stops: Vec::new(),
             wrap: GradientWrap::Clamp,
+            interpolation: GradientInterpolation::default(),
         }
     }
 
@@ -118,6 +170,20 @@ impl Gradient {
             geometry: GradientGeometry::Radial { line: line.to_line(), radii, transform },
             stops: Vec::new(),
             wrap: GradientWrap::Clamp,
+            interpolation: GradientInterpolation::default(),
+        }
+    }
+
+    /// Creates a conic gradient that sweeps clockwise around `center`, starting at `start_angle`
+    /// radians from the positive X axis. This matches `createConicGradient()` in HTML canvas.
+    #[inline]
+    pub fn conic(center: Vector2F, start_angle: f32) -> Gradient {
+        let transform = Transform2F::default();
+        Gradient {
+            geometry: GradientGeometry::Conic { center, start_angle, transform },
+            stops: Vec::new(),
+            wrap: GradientWrap::Clamp,
+            interpolation: GradientInterpolation::default(),
         }
     }
 
@@ -145,12 +211,18 @@ impl Gradient {
         &mut self.stops
     }
 
+    /// Returns the color of the gradient at `t`, applying the wrap mode if `t` is outside the
+    /// range 0 to 1.
     pub fn sample(&self, mut t: f32) -> ColorU {
         if self.stops.is_empty() {
             return ColorU::transparent_black();
         }
 
-        t = geometry_util::clamp(t, 0.0, 1.0);
+        t = match self.wrap {
+            GradientWrap::Clamp => geometry_util::clamp(t, 0.0, 1.0),
+            GradientWrap::Repeat => t - f32::floor(t),
+            GradientWrap::Reflect => 1.0 - f32::abs(t.rem_euclid(2.0) - 1.0),
+        };
         let last_index = self.stops.len() - 1;
 
         let upper_index = self.stops.binary_search_by(|stop| {
@@ -167,7 +239,7 @@ impl Gradient {
         }
 
         let ratio = ((t - lower_stop.offset) / denom).min(1.0);
-        lower_stop.color.to_f32().lerp(upper_stop.color.to_f32(), ratio).to_u8()
+        self.interpolation.lerp(lower_stop.color, upper_stop.color, ratio)
     }
 
     #[inline]
@@ -186,14 +258,83 @@ impl Gradient {
         }
 
         match self.geometry {
-            GradientGeometry::Linear(ref mut line) => *line = new_transform * *line,
-            GradientGeometry::Radial { ref mut transform, .. } => {
+            GradientGeometry::Linear(ref mut line) => {
+                *line = transform_linear_gradient_line(*line, new_transform)
+            }
+            GradientGeometry::Radial { ref mut transform, .. } |
+            GradientGeometry::Conic { ref mut transform, .. } => {
                 *transform = new_transform * *transform
             }
         }
     }
 }
 
+// Transforms the line of a linear gradient.
+//
+// Lines of constant color are perpendicular to the gradient line. Transforms that don't preserve
+// angles, like nonuniform scales and skews, keep them parallel but not perpendicular, so rather
+// than transforming the end point, this finds the line that is perpendicular to the transformed
+// lines of constant color and produces the same colors.
+fn transform_linear_gradient_line(line: LineSegment2F, transform: Transform2F) -> LineSegment2F {
+    let vector = line.vector();
+    let length_squared = vector.square_length();
+    let det = transform.matrix.det();
+    if length_squared == 0.0 || det == 0.0 {
+        return transform * line;
+    }
+
+    // The gradient of the color parameter with respect to the transformed space, which is the
+    // inverse transpose of the transform's matrix applied to the line's vector.
+    let (m11, m12, m21, m22) = (transform.m11(), transform.m12(), transform.m21(), transform.m22());
+    let gradient = Vector2F::new(m22 * vector.x() - m21 * vector.y(),
+                                 m11 * vector.y() - m12 * vector.x()) / (det * length_squared);
+
+    let from = transform * line.from();
+    LineSegment2F::new(from, from + gradient / gradient.square_length())
+}
+
+impl Default for GradientInterpolation {
+    #[inline]
+    fn default() -> GradientInterpolation {
+        GradientInterpolation { color_space: GradientColorSpace::SRGB, premultiplied: false }
+    }
+}
+
+impl GradientInterpolation {
+    /// Blends two colors in this interpolation space.
+    pub fn lerp(&self, from: ColorU, to: ColorU, t: f32) -> ColorU {
+        if *self == GradientInterpolation::default() {
+            return from.to_f32().lerp(to.to_f32(), t).to_u8();
+        }
+
+        let (from, to) = (self.convert_stop_color(from), self.convert_stop_color(to));
+        let mut color = from.lerp(to, t);
+        if self.premultiplied && color.a() > 0.0 {
+            let alpha = color.a();
+            color.0 *= F32x4::new(1.0 / alpha, 1.0 / alpha, 1.0 / alpha, 1.0);
+        }
+        let color = match self.color_space {
+            GradientColorSpace::SRGB => color,
+            GradientColorSpace::LinearSRGB => color.linear_to_srgb(),
+            GradientColorSpace::OKLab => color.oklab_to_srgb(),
+        };
+        ColorF(color.0.clamp(F32x4::default(), F32x4::splat(1.0))).to_u8()
+    }
+
+    fn convert_stop_color(&self, color: ColorU) -> ColorF {
+        let color = color.to_f32();
+        let mut color = match self.color_space {
+            GradientColorSpace::SRGB => color,
+            GradientColorSpace::LinearSRGB => color.srgb_to_linear(),
+            GradientColorSpace::OKLab => color.srgb_to_oklab(),
+        };
+        if self.premultiplied {
+            color.0 *= F32x4::new(color.a(), color.a(), color.a(), 1.0);
+        }
+        color
+    }
+}
+
 impl ColorStop {
     #[inline]
     pub fn new(color: ColorU, offset: f32) -> ColorStop {
@@ -221,9 +362,13 @@ impl RadialGradientLine for Vector2F {
 
 #[cfg(test)]
 mod test {
-    use crate::gradient::Gradient;
+    use crate::gradient::{Gradient, GradientColorSpace, GradientGeometry, GradientInterpolation};
+    use crate::gradient::GradientWrap;
     use pathfinder_color::ColorU;
-    use pathfinder_geometry::vector::Vector2F;
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, vec2f};
+    use std::f32::consts::FRAC_PI_2;
 
     #[test]
     fn stable_order() {
@@ -251,4 +396,73 @@ mod test {
             assert!(sample.r == 0, "{} {}", i, sample.r);
         }
     }
+
+    #[test]
+    fn sample_wrap_modes() {
+        let mut grad = Gradient::linear_from_points(Vector2F::default(), Vector2F::default());
+        grad.add_color_stop(ColorU::new(0, 0, 0, 255), 0.0);
+        grad.add_color_stop(ColorU::new(200, 0, 0, 255), 1.0);
+
+        assert_eq!(grad.sample(1.25).r, 200);
+        grad.wrap = GradientWrap::Repeat;
+        assert_eq!(grad.sample(1.25).r, 50);
+        grad.wrap = GradientWrap::Reflect;
+        assert_eq!(grad.sample(1.25).r, 150);
+        assert_eq!(grad.sample(-0.25).r, 50);
+    }
+
+    #[test]
+    fn interpolation_spaces() {
+        let mut grad = Gradient::linear_from_points(Vector2F::default(), Vector2F::default());
+        grad.add_color_stop(ColorU::new(255, 0, 0, 255), 0.0);
+        grad.add_color_stop(ColorU::new(0, 0, 255, 0), 1.0);
+
+        // Unpremultiplied, the transparent blue stop pulls the color toward blue.
+        let middle = grad.sample(0.5);
+        assert_eq!((middle.r, middle.b, middle.a), (128, 128, 128));
+
+        // Premultiplied, the color stays red as it fades out.
+        grad.interpolation.premultiplied = true;
+        let middle = grad.sample(0.5);
+        assert_eq!((middle.r, middle.b), (255, 0));
+
+        // Linear sRGB is brighter in the middle than gamma-encoded sRGB.
+        let mut grad = Gradient::linear_from_points(Vector2F::default(), Vector2F::default());
+        grad.add_color_stop(ColorU::black(), 0.0);
+        grad.add_color_stop(ColorU::white(), 1.0);
+        grad.interpolation = GradientInterpolation {
+            color_space: GradientColorSpace::LinearSRGB,
+            premultiplied: false,
+        };
+        assert!(grad.sample(0.5).r > 180);
+
+        // OKLab round-trips the stop colors.
+        grad.interpolation.color_space = GradientColorSpace::OKLab;
+        assert!(grad.sample(0.0).r <= 1 && grad.sample(1.0).r >= 254);
+    }
+
+    fn linear_line(grad: &Gradient) -> LineSegment2F {
+        match grad.geometry {
+            GradientGeometry::Linear(line) => line,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn transform_linear_keeps_stops_perpendicular() {
+        // Similarities transform the end points.
+        let mut grad = Gradient::linear_from_points(vec2f(1.0, 0.0), vec2f(1.0, 2.0));
+        grad.apply_transform(Transform2F::from_rotation(FRAC_PI_2).translate(vec2f(3.0, 0.0)));
+        let line = linear_line(&grad);
+        assert!((line.from() - vec2f(3.0, 1.0)).length() < 1e-5, "{:?}", line);
+        assert!((line.to() - vec2f(1.0, 1.0)).length() < 1e-5, "{:?}", line);
+
+        // Under a nonuniform scale, the diagonal lines of constant color `x + y = c` become
+        // `x / 2 + y = c`, so the gradient line must stay perpendicular to those.
+        let mut grad = Gradient::linear_from_points(Vector2F::zero(), vec2f(1.0, 1.0));
+        grad.apply_transform(Transform2F::from_scale(vec2f(2.0, 1.0)));
+        let line = linear_line(&grad);
+        assert_eq!(line.from(), Vector2F::zero());
+        assert!((line.to() - vec2f(0.8, 1.6)).length() < 1e-5, "{:?}", line);
+    }
 }
@@ -15,17 +15,20 @@ extern crate bitflags;
 #[macro_use]
 extern crate log;
 
+pub mod boolean;
 pub mod clip;
 pub mod dash;
 pub mod effects;
 pub mod fill;
 pub mod gradient;
+pub mod measure;
 pub mod orientation;
 pub mod outline;
 pub mod pattern;
 pub mod render_target;
 pub mod segment;
 pub mod stroke;
+pub mod svg_path;
 pub mod transform;
 
 mod dilation;
@@ -0,0 +1,312 @@
+// pathfinder/content/src/measure.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Measures the lengths of paths and extracts pieces of them by distance.
+//!
+//! This is useful for animating strokes that draw themselves on, for placing markers at regular
+//! intervals along a path, and for trimming paths.
+
+use crate::outline::{Contour, ContourIterFlags, Outline};
+use crate::segment::{Segment, SegmentKind};
+use pathfinder_geometry::vector::Vector2F;
+
+const EPSILON: f32 = 0.0001;
+
+/// Measures every contour of an outline.
+#[derive(Clone, Debug)]
+pub struct OutlineMeasure {
+    contours: Vec<ContourMeasure>,
+    length: f32,
+}
+
+/// Measures a single contour, allowing positions and pieces of it to be looked up by distance.
+#[derive(Clone, Debug)]
+pub struct ContourMeasure {
+    segments: Vec<MeasuredSegment>,
+    length: f32,
+    closed: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct MeasuredSegment {
+    segment: Segment,
+    // The distance along the contour at which this segment starts.
+    start: f32,
+    length: f32,
+}
+
+impl OutlineMeasure {
+    /// Measures all the contours in the given outline.
+    pub fn new(outline: &Outline) -> OutlineMeasure {
+        let contours: Vec<_> = outline.contours().iter().map(ContourMeasure::new).collect();
+        let length = contours.iter().map(|contour| contour.length).sum();
+        OutlineMeasure { contours, length }
+    }
+
+    /// Returns the measurements of each contour, in the order they appear in the outline.
+    #[inline]
+    pub fn contours(&self) -> &[ContourMeasure] {
+        &self.contours
+    }
+
+    /// Returns the sum of the lengths of all contours.
+    #[inline]
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    /// Returns the part of the outline between the two distances as a new outline.
+    ///
+    /// Distances are measured along all contours in order, as though they were joined end to end.
+    /// Each contour that the range touches contributes a separate, open contour to the result.
+    /// If `start` is not less than `end`, the result is empty.
+    pub fn extract(&self, start: f32, end: f32) -> Outline {
+        let mut outline = Outline::new();
+        let mut contour_start = 0.0;
+        for contour in &self.contours {
+            let contour_end = contour_start + contour.length;
+            if start < contour_end && end > contour_start {
+                let piece = contour.extract(start - contour_start, end - contour_start);
+                if !piece.is_empty() {
+                    outline.push_contour(piece);
+                }
+            }
+            contour_start = contour_end;
+        }
+        outline
+    }
+}
+
+impl ContourMeasure {
+    /// Measures the given contour, including its closing segment if it is closed.
+    pub fn new(contour: &Contour) -> ContourMeasure {
+        let mut segments = vec![];
+        let mut length = 0.0;
+        for segment in contour.iter(ContourIterFlags::empty()) {
+            let segment_length = segment.arc_length();
+            if segment.is_none() || segment_length < EPSILON {
+                continue;
+            }
+            segments.push(MeasuredSegment { segment, start: length, length: segment_length });
+            length += segment_length;
+        }
+        ContourMeasure { segments, length, closed: contour.is_closed() }
+    }
+
+    /// Returns the length of this contour.
+    #[inline]
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    /// Returns true if the measured contour was closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Returns the position and the unit tangent vector at the given distance along this contour.
+    ///
+    /// The distance is clamped to the length of the contour. Returns `None` if the contour has
+    /// zero length.
+    pub fn position_and_tangent_at(&self, distance: f32) -> Option<(Vector2F, Vector2F)> {
+        let (segment, t) = self.segment_and_time_at(distance)?;
+        Some((segment.sample(t), tangent_of(&segment, t)))
+    }
+
+    /// Returns the position at the given distance along this contour.
+    ///
+    /// The distance is clamped to the length of the contour. Returns `None` if the contour has
+    /// zero length.
+    #[inline]
+    pub fn position_at(&self, distance: f32) -> Option<Vector2F> {
+        self.position_and_tangent_at(distance).map(|(position, _)| position)
+    }
+
+    /// Returns the unit tangent vector at the given distance along this contour.
+    ///
+    /// The distance is clamped to the length of the contour. Returns `None` if the contour has
+    /// zero length.
+    #[inline]
+    pub fn tangent_at(&self, distance: f32) -> Option<Vector2F> {
+        self.position_and_tangent_at(distance).map(|(_, tangent)| tangent)
+    }
+
+    /// Returns the part of this contour between the two distances as a new, open contour.
+    ///
+    /// The distances are clamped to the length of the contour. If `start` is not less than `end`
+    /// after clamping, the result is empty.
+    pub fn extract(&self, start: f32, end: f32) -> Contour {
+        let start = start.max(0.0);
+        let end = end.min(self.length);
+        let mut contour = Contour::new();
+        if start >= end {
+            return contour;
+        }
+
+        let first_index = self.segment_index_at(start);
+        for measured in &self.segments[first_index..] {
+            if measured.start >= end {
+                break;
+            }
+
+            let mut segment = measured.segment;
+            let (local_start, local_end) = (start - measured.start, end - measured.start);
+            let mut t_end = 1.0;
+            if local_end < measured.length {
+                t_end = segment.time_for_distance_with_length(local_end, measured.length);
+                segment = segment.split(t_end).0;
+            }
+            if local_start > 0.0 {
+                // Splitting a Bézier curve reparameterizes it linearly, so times measured on the
+                // original segment can be rescaled to the piece that was split off above.
+                let t_start = measured.segment
+                                      .time_for_distance_with_length(local_start, measured.length);
+                segment = segment.split(t_start / t_end).1;
+            }
+
+            if contour.is_empty() {
+                contour.push_endpoint(segment.baseline.from());
+            }
+            push_segment(&mut contour, &segment);
+        }
+        contour
+    }
+
+    fn segment_and_time_at(&self, distance: f32) -> Option<(Segment, f32)> {
+        if self.segments.is_empty() {
+            return None;
+        }
+        let distance = distance.clamp(0.0, self.length);
+        let measured = &self.segments[self.segment_index_at(distance)];
+        let t = measured.segment.time_for_distance_with_length(distance - measured.start,
+                                                               measured.length);
+        Some((measured.segment, t))
+    }
+
+    // Returns the index of the segment containing the given distance, which must be within the
+    // contour.
+    fn segment_index_at(&self, distance: f32) -> usize {
+        let (mut lo, mut hi) = (0, self.segments.len());
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            if self.segments[mid].start <= distance {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+}
+
+fn tangent_of(segment: &Segment, t: f32) -> Vector2F {
+    let tangent = match segment.kind {
+        SegmentKind::Line | SegmentKind::None => segment.baseline.vector(),
+        SegmentKind::Quadratic | SegmentKind::Cubic => {
+            segment.to_cubic().as_cubic_segment().derivative(t)
+        }
+    };
+
+    // The derivative vanishes where a control point coincides with an endpoint; fall back to the
+    // chord in that case.
+    if tangent.square_length() < EPSILON {
+        segment.baseline.vector().normalize()
+    } else {
+        tangent.normalize()
+    }
+}
+
+fn push_segment(contour: &mut Contour, segment: &Segment) {
+    match segment.kind {
+        SegmentKind::None => {}
+        SegmentKind::Line => contour.push_endpoint(segment.baseline.to()),
+        SegmentKind::Quadratic => {
+            contour.push_quadratic(segment.ctrl.from(), segment.baseline.to())
+        }
+        SegmentKind::Cubic => {
+            contour.push_cubic(segment.ctrl.from(), segment.ctrl.to(), segment.baseline.to())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::measure::OutlineMeasure;
+    use crate::outline::{Contour, Outline};
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, vec2f};
+    use std::f32::consts::PI;
+
+    fn assert_near(a: Vector2F, b: Vector2F) {
+        assert!((a - b).length() < 0.01, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn measure_rect() {
+        let outline = Outline::from_rect(RectF::new(Vector2F::zero(), vec2f(10.0, 20.0)));
+        let measure = OutlineMeasure::new(&outline);
+        assert_eq!(measure.length(), 60.0);
+
+        let contour = &measure.contours()[0];
+        assert!(contour.is_closed());
+        let (position, tangent) = contour.position_and_tangent_at(15.0).unwrap();
+        assert_near(position, vec2f(10.0, 5.0));
+        assert_near(tangent, vec2f(0.0, 1.0));
+        assert_near(contour.position_at(100.0).unwrap(), Vector2F::zero());
+    }
+
+    #[test]
+    fn measure_circle() {
+        let mut contour = Contour::new();
+        contour.push_ellipse(&Transform2F::from_scale(50.0));
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+
+        let measure = OutlineMeasure::new(&outline);
+        assert!(f32::abs(measure.length() - 100.0 * PI) < 0.1);
+        let contour = &measure.contours()[0];
+        let position = contour.position_at(contour.length() * 0.125).unwrap();
+        assert!(f32::abs(position.length() - 50.0) < 0.05);
+    }
+
+    #[test]
+    fn extract() {
+        let mut first = Contour::new();
+        first.push_endpoint(Vector2F::zero());
+        first.push_endpoint(vec2f(10.0, 0.0));
+        first.push_endpoint(vec2f(10.0, 10.0));
+        let mut second = Contour::new();
+        second.push_endpoint(vec2f(0.0, 20.0));
+        second.push_quadratic(vec2f(10.0, 20.0), vec2f(20.0, 20.0));
+        let mut outline = Outline::new();
+        outline.push_contour(first);
+        outline.push_contour(second);
+
+        let measure = OutlineMeasure::new(&outline);
+        assert!(f32::abs(measure.length() - 40.0) < 0.01);
+
+        let piece = measure.extract(5.0, 35.0);
+        assert_eq!(piece.contours().len(), 2);
+        let first = &piece.contours()[0];
+        assert_near(first.first_position().unwrap(), vec2f(5.0, 0.0));
+        assert_near(first.last_position().unwrap(), vec2f(10.0, 10.0));
+        let second = &piece.contours()[1];
+        assert_near(second.first_position().unwrap(), vec2f(0.0, 20.0));
+        assert_near(second.last_position().unwrap(), vec2f(15.0, 20.0));
+
+        let middle = measure.contours()[1].extract(5.0, 15.0);
+        assert_near(middle.first_position().unwrap(), vec2f(5.0, 20.0));
+        assert_near(middle.last_position().unwrap(), vec2f(15.0, 20.0));
+
+        assert!(measure.extract(20.0, 20.0).is_empty());
+    }
+}
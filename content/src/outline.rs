@@ -10,10 +10,14 @@
 
 //! A compressed in-memory representation of a vector path.
 
+use crate::boolean::{self, BooleanOp};
 use crate::clip::{self, ContourPolygonClipper};
 use crate::dilation::ContourDilator;
+use crate::fill::FillRule;
 use crate::orientation::Orientation;
 use crate::segment::{Segment, SegmentFlags, SegmentKind};
+use crate::stroke::{LineJoin, OutlineStrokeToFill, StrokeStyle};
+use crate::svg_path::{self, SVGPathDataError};
 use crate::util::safe_sqrt;
 use pathfinder_geometry::line_segment::LineSegment2F;
 use pathfinder_geometry::rect::RectF;
@@ -21,7 +25,7 @@ use pathfinder_geometry::transform2d::{Transform2F, Matrix2x2F};
 use pathfinder_geometry::transform3d::Perspective;
 use pathfinder_geometry::unit_vector::UnitVector;
 use pathfinder_geometry::vector::{Vector2F, vec2f};
-use std::f32::consts::PI;
+use std::f32::consts::{PI, SQRT_2};
 use std::fmt::{self, Debug, Formatter};
 use std::mem;
 
@@ -151,6 +155,29 @@ impl Outline {
         outline
     }
 
+    /// Parses SVG path data, as found in the `d` attribute of an SVG `<path>` element.
+    ///
+    /// Use `svg_path::parse_prefix()` to instead keep everything before the first error, as
+    /// browsers do.
+    #[inline]
+    pub fn from_svg_path_data(data: &str) -> Result<Outline, SVGPathDataError> {
+        svg_path::parse(data)
+    }
+
+    /// Writes this outline as SVG path data, with as many digits as are needed to read the
+    /// coordinates back exactly.
+    #[inline]
+    pub fn to_svg_path_data(&self) -> String {
+        svg_path::write(self, None)
+    }
+
+    /// Writes this outline as SVG path data, rounding coordinates to the given number of digits
+    /// after the decimal point.
+    #[inline]
+    pub fn to_svg_path_data_with_precision(&self, decimal_places: usize) -> String {
+        svg_path::write(self, Some(decimal_places))
+    }
+
     /// Returns the dimensions of an axis-aligned box that encloses the entire outline.
     #[inline]
     pub fn bounds(&self) -> RectF {
@@ -274,6 +301,86 @@ impl Outline {
         }
     }
 
+    /// Combines the areas filled by this outline and `other` under `fill_rule` with the given
+    /// boolean operation, returning a new outline.
+    ///
+    /// Curves are flattened, so the result consists only of line segments.
+    #[inline]
+    pub fn boolean_op(&self, other: &Outline, op: BooleanOp, fill_rule: FillRule) -> Outline {
+        boolean::boolean_op(self, other, op, fill_rule)
+    }
+
+    /// Returns the area filled by either this outline or `other`.
+    #[inline]
+    pub fn union(&self, other: &Outline, fill_rule: FillRule) -> Outline {
+        self.boolean_op(other, BooleanOp::Union, fill_rule)
+    }
+
+    /// Returns the area filled by both this outline and `other`.
+    #[inline]
+    pub fn intersection(&self, other: &Outline, fill_rule: FillRule) -> Outline {
+        self.boolean_op(other, BooleanOp::Intersection, fill_rule)
+    }
+
+    /// Returns the area filled by this outline but not by `other`.
+    #[inline]
+    pub fn difference(&self, other: &Outline, fill_rule: FillRule) -> Outline {
+        self.boolean_op(other, BooleanOp::Difference, fill_rule)
+    }
+
+    /// Returns the area filled by exactly one of this outline and `other`.
+    #[inline]
+    pub fn xor(&self, other: &Outline, fill_rule: FillRule) -> Outline {
+        self.boolean_op(other, BooleanOp::Xor, fill_rule)
+    }
+
+    /// Returns an outline that fills the same area as this one does under `fill_rule`, but whose
+    /// contours don't intersect themselves or each other.
+    #[inline]
+    pub fn simplify(&self, fill_rule: FillRule) -> Outline {
+        boolean::simplify(self, fill_rule)
+    }
+
+    /// Returns the winding number of this outline around the given point: the sum of the winding
+    /// numbers of all its contours.
+    pub fn winding_number(&self, point: Vector2F) -> i32 {
+        if !self.bounds.contains_point(point) {
+            return 0;
+        }
+        self.contours.iter().map(|contour| contour.winding_number(point)).sum()
+    }
+
+    /// Returns true if the given point lies inside the area that this outline fills under the
+    /// given fill rule.
+    pub fn contains_point(&self, point: Vector2F, fill_rule: FillRule) -> bool {
+        let winding_number = self.winding_number(point);
+        match fill_rule {
+            FillRule::Winding => winding_number != 0,
+            FillRule::EvenOdd => winding_number % 2 != 0,
+        }
+    }
+
+    /// Returns true if the given point lies inside the stroke of this outline with the given
+    /// style.
+    ///
+    /// The stroke is converted to a fill with `OutlineStrokeToFill` and tested with the nonzero
+    /// fill rule, just as it is when rendered.
+    pub fn stroke_contains_point(&self, point: Vector2F, style: StrokeStyle) -> bool {
+        // Joins and caps can extend no further than this from the path.
+        let extent_scale = match style.line_join {
+            LineJoin::Miter(miter_limit) => f32::max(miter_limit, SQRT_2),
+            LineJoin::Bevel | LineJoin::Round => SQRT_2,
+        };
+        let extent = style.line_width * 0.5 * extent_scale;
+        if !self.bounds.dilate(extent).contains_point(point) {
+            return false;
+        }
+
+        let mut stroke_to_fill = OutlineStrokeToFill::new(self, style);
+        stroke_to_fill.offset();
+        stroke_to_fill.into_outline().contains_point(point, FillRule::Winding)
+    }
+
     /// Marks all contours as closed.
     #[inline]
     pub fn close_all_contours(&mut self) {
@@ -874,6 +981,24 @@ impl Contour {
         }
     }
 
+    /// Returns the winding number of this contour around the given point.
+    ///
+    /// The contour is treated as closed, as it is when filled. Curves are handled exactly.
+    pub fn winding_number(&self, point: Vector2F) -> i32 {
+        let (first_position, last_position) = match (self.first_position(), self.last_position()) {
+            (Some(first_position), Some(last_position)) => (first_position, last_position),
+            _ => return 0,
+        };
+        if !self.bounds.contains_point(point) {
+            return 0;
+        }
+
+        let closing_segment = Segment::line(LineSegment2F::new(last_position, first_position));
+        self.iter(ContourIterFlags::IGNORE_CLOSE_SEGMENT)
+            .map(|segment| segment.ray_crossings(point))
+            .sum::<i32>() + closing_segment.ray_crossings(point)
+    }
+
     /// Applies the given affine transform to this subpath.
     pub fn transform(&mut self, transform: &Transform2F) {
         if transform.is_identity() {
@@ -1084,3 +1209,67 @@ pub(crate) fn union_rect(bounds: &mut RectF, new_point: Vector2F, first: bool) {
         *bounds = bounds.union_point(new_point)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::fill::FillRule;
+    use crate::outline::{Contour, Outline};
+    use crate::stroke::{LineCap, LineJoin, StrokeStyle};
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, vec2f};
+
+    #[test]
+    fn contains_point() {
+        let mut circle = Contour::new();
+        circle.push_ellipse(&Transform2F::from_scale(10.0));
+        let mut outline = Outline::new();
+        outline.push_contour(circle);
+        outline.push_contour(Contour::from_rect(RectF::new(vec2f(-2.0, -2.0), vec2f(4.0, 4.0))));
+
+        // Just inside and just outside the curve, where a flattened circle would be inaccurate.
+        assert!(outline.contains_point(vec2f(9.99, 0.0), FillRule::EvenOdd));
+        assert!(!outline.contains_point(vec2f(7.08, 7.08), FillRule::EvenOdd));
+        assert!(outline.contains_point(vec2f(7.06, 7.06), FillRule::EvenOdd));
+
+        // The square overlaps the circle, so it is a hole only under the even-odd rule.
+        assert_eq!(f32::abs(outline.winding_number(Vector2F::zero()) as f32), 2.0);
+        assert!(outline.contains_point(Vector2F::zero(), FillRule::Winding));
+        assert!(!outline.contains_point(Vector2F::zero(), FillRule::EvenOdd));
+        assert!(!outline.contains_point(vec2f(20.0, 0.0), FillRule::Winding));
+    }
+
+    #[test]
+    fn open_contours_are_implicitly_closed() {
+        let mut contour = Contour::new();
+        contour.push_endpoint(vec2f(0.0, 0.0));
+        contour.push_endpoint(vec2f(10.0, 0.0));
+        contour.push_quadratic(vec2f(10.0, 10.0), vec2f(0.0, 10.0));
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+
+        assert!(outline.contains_point(vec2f(5.0, 5.0), FillRule::Winding));
+        assert!(!outline.contains_point(vec2f(-1.0, 5.0), FillRule::Winding));
+    }
+
+    #[test]
+    fn stroke_contains_point() {
+        let mut contour = Contour::new();
+        contour.push_endpoint(vec2f(0.0, 0.0));
+        contour.push_endpoint(vec2f(100.0, 0.0));
+        let mut outline = Outline::new();
+        outline.push_contour(contour);
+
+        let style = StrokeStyle {
+            line_width: 10.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter(10.0),
+        };
+        assert!(outline.stroke_contains_point(vec2f(50.0, 4.0), style));
+        assert!(!outline.stroke_contains_point(vec2f(50.0, 6.0), style));
+        assert!(!outline.stroke_contains_point(vec2f(-3.0, 0.0), style));
+
+        let square_cap = StrokeStyle { line_cap: LineCap::Square, ..style };
+        assert!(outline.stroke_contains_point(vec2f(-3.0, 0.0), square_cap));
+    }
+}
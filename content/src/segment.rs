@@ -17,6 +17,21 @@ use pathfinder_geometry::vector::{Vector2F, vec2f};
 use pathfinder_simd::default::F32x4;
 use std::f32::consts::SQRT_2;
 
+/// The default maximum error, in pixels, of arc length computations.
+pub const ARC_LENGTH_TOLERANCE: f32 = 0.01;
+
+const MAX_ARC_LENGTH_SUBDIVISION_DEPTH: u32 = 12;
+const MAX_TIME_FOR_DISTANCE_ITERATIONS: u32 = 16;
+const RAY_CROSSING_BISECTION_ITERATIONS: u32 = 24;
+
+// Abscissae and weights for five-point Gauss-Legendre quadrature over [-1, 1].
+const GAUSS_LEGENDRE_ABSCISSAE: [f32; 5] = [
+    -0.906_179_8, -0.538_469_3, 0.0, 0.538_469_3, 0.906_179_8,
+];
+const GAUSS_LEGENDRE_WEIGHTS: [f32; 5] = [
+    0.236_926_88, 0.478_628_67, 0.568_888_9, 0.478_628_67, 0.236_926_88,
+];
+
 /// A single line or Bézier curve segment, with explicit start and end points.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Segment {
@@ -227,14 +242,121 @@ impl Segment {
         }
     }
 
+    /// Returns the length of this segment, accurate to within `ARC_LENGTH_TOLERANCE`.
+    #[inline]
     pub fn arc_length(&self) -> f32 {
-        // FIXME(pcwalton)
-        self.baseline.vector().length()
+        self.arc_length_with_tolerance(ARC_LENGTH_TOLERANCE)
+    }
+
+    /// Returns the length of this segment, accurate to within `tolerance`.
+    ///
+    /// Curves are measured with adaptive Gauss-Legendre quadrature.
+    pub fn arc_length_with_tolerance(&self, tolerance: f32) -> f32 {
+        match self.kind {
+            SegmentKind::None => 0.0,
+            SegmentKind::Line => self.baseline.vector().length(),
+            SegmentKind::Quadratic | SegmentKind::Cubic => {
+                self.to_cubic().as_cubic_segment().arc_length_between(0.0, 1.0, tolerance)
+            }
+        }
     }
 
+    /// Returns the time at which the given distance along this segment is reached, accurate to
+    /// within `ARC_LENGTH_TOLERANCE`.
+    ///
+    /// The result is clamped to the range [0, 1]. A segment with no length always returns 0.0.
+    #[inline]
     pub fn time_for_distance(&self, distance: f32) -> f32 {
-        // FIXME(pcwalton)
-        distance / self.arc_length()
+        self.time_for_distance_with_tolerance(distance, ARC_LENGTH_TOLERANCE)
+    }
+
+    /// Like `time_for_distance()`, but takes the length of this segment, as returned by
+    /// `arc_length()`, so that callers that already know it don't measure the segment again.
+    #[inline]
+    pub fn time_for_distance_with_length(&self, distance: f32, length: f32) -> f32 {
+        self.time_for_distance_in(distance, length, ARC_LENGTH_TOLERANCE)
+    }
+
+    /// Returns the time at which the given distance along this segment is reached, such that the
+    /// length of the curve up to that time is within `tolerance` of `distance`.
+    pub fn time_for_distance_with_tolerance(&self, distance: f32, tolerance: f32) -> f32 {
+        self.time_for_distance_in(distance, self.arc_length_with_tolerance(tolerance), tolerance)
+    }
+
+    fn time_for_distance_in(&self, distance: f32, length: f32, tolerance: f32) -> f32 {
+        if length <= 0.0 || distance <= 0.0 {
+            return 0.0;
+        }
+        if distance >= length {
+            return 1.0;
+        }
+        if self.is_line() {
+            return distance / length;
+        }
+
+        // Invert the arc length function with Newton's method, falling back to bisection
+        // whenever a step would leave the interval known to contain the answer.
+        let segment = self.to_cubic();
+        let cubic = segment.as_cubic_segment();
+        let (mut min_t, mut max_t) = (0.0, 1.0);
+        let mut t = distance / length;
+        for _ in 0..MAX_TIME_FOR_DISTANCE_ITERATIONS {
+            let error = cubic.arc_length_between(0.0, t, tolerance) - distance;
+            if f32::abs(error) <= tolerance {
+                break;
+            }
+            if error > 0.0 {
+                max_t = t;
+            } else {
+                min_t = t;
+            }
+
+            let speed = cubic.derivative(t).length();
+            let next_t = t - error / speed;
+            t = if speed > EPSILON && next_t > min_t && next_t < max_t {
+                next_t
+            } else {
+                (min_t + max_t) * 0.5
+            };
+        }
+        t
+    }
+
+    /// Returns the signed number of times this segment crosses the ray that extends from `point`
+    /// in the positive x direction. Summing this over a closed contour yields its winding number.
+    ///
+    /// Crossings in the direction of increasing y count as 1, and the others count as -1. Each
+    /// segment includes its starting point but not its ending point, so that a crossing at a point
+    /// shared by two segments is counted once. Curves are handled exactly, without flattening.
+    pub fn ray_crossings(&self, point: Vector2F) -> i32 {
+        match self.kind {
+            SegmentKind::None => 0,
+            SegmentKind::Line => {
+                let (from, to) = (self.baseline.from(), self.baseline.to());
+                let direction = ray_crossing_direction(from.y(), to.y(), point.y());
+                if direction == 0 {
+                    return 0;
+                }
+                let vector = to - from;
+                let x = from.x() + (point.y() - from.y()) * vector.x() / vector.y();
+                if x > point.x() { direction } else { 0 }
+            }
+            SegmentKind::Quadratic | SegmentKind::Cubic => {
+                self.to_cubic().as_cubic_segment().ray_crossings(point)
+            }
+        }
+    }
+}
+
+// Returns 1 if `y` lies in `[from_y, to_y)`, -1 if it lies in `[to_y, from_y)`, and 0 otherwise.
+#[inline]
+fn ray_crossing_direction(from_y: f32, to_y: f32, y: f32) -> i32 {
+    if from_y <= y && y < to_y {
+        1
+    } else if to_y <= y && y < from_y {
+        -1
+    } else {
+        0
     }
 }
 
@@ -346,6 +468,94 @@ impl<'s> CubicSegment<'s> {
         self.split(t).0.baseline.to()
     }
 
+    /// Returns the derivative of this curve with respect to time at `t`.
+    #[inline]
+    pub fn derivative(self, t: f32) -> Vector2F {
+        let (p0, p3) = (self.0.baseline.from(), self.0.baseline.to());
+        let (p1, p2) = (self.0.ctrl.from(), self.0.ctrl.to());
+        let u = 1.0 - t;
+        (p1 - p0) * (3.0 * u * u) + (p2 - p1) * (6.0 * u * t) + (p3 - p2) * (3.0 * t * t)
+    }
+
+    /// Returns the signed number of times this curve crosses the ray that extends from `point` in
+    /// the positive x direction. See `Segment::ray_crossings()`.
+    pub fn ray_crossings(self, point: Vector2F) -> i32 {
+        let (p0, p3) = (self.0.baseline.from(), self.0.baseline.to());
+        let (p1, p2) = (self.0.ctrl.from(), self.0.ctrl.to());
+        if point.y() < self.min_y() || point.y() >= self.max_y() || point.x() >= self.max_x() {
+            return 0;
+        }
+
+        // Split the curve at its extrema in y, so that each piece crosses the ray at most once.
+        let a = -p0.y() + 3.0 * (p1.y() - p2.y()) + p3.y();
+        let b = 2.0 * (p0.y() - 2.0 * p1.y() + p2.y());
+        let c = p1.y() - p0.y();
+        let mut times = [0.0, 1.0, 1.0, 1.0];
+        let mut time_count = 1;
+        for &t in solve_quadratic(a, b, c).iter().flatten() {
+            if t > 0.0 && t < 1.0 {
+                times[time_count] = t;
+                time_count += 1;
+            }
+        }
+        times[time_count] = 1.0;
+        times[..(time_count + 1)].sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        // Use the exact endpoints so that crossings at shared points are counted consistently.
+        let sample = |t: f32| if t == 0.0 { p0 } else if t == 1.0 { p3 } else { self.sample(t) };
+
+        let mut crossings = 0;
+        for pair in times[..(time_count + 1)].windows(2) {
+            let (from_y, to_y) = (sample(pair[0]).y(), sample(pair[1]).y());
+            let direction = ray_crossing_direction(from_y, to_y, point.y());
+            if direction == 0 {
+                continue;
+            }
+
+            let (mut min_t, mut max_t) = (pair[0], pair[1]);
+            for _ in 0..RAY_CROSSING_BISECTION_ITERATIONS {
+                let mid_t = (min_t + max_t) * 0.5;
+                if (sample(mid_t).y() <= point.y()) == (direction > 0) {
+                    min_t = mid_t;
+                } else {
+                    max_t = mid_t;
+                }
+            }
+            if sample((min_t + max_t) * 0.5).x() > point.x() {
+                crossings += direction;
+            }
+        }
+        crossings
+    }
+
+    fn arc_length_between(self, t0: f32, t1: f32, tolerance: f32) -> f32 {
+        let estimate = self.gauss_legendre_arc_length(t0, t1);
+        self.adaptive_arc_length(t0, t1, estimate, tolerance, MAX_ARC_LENGTH_SUBDIVISION_DEPTH)
+    }
+
+    // Refines the estimate of the length of the curve between `t0` and `t1` by splitting the
+    // interval in half until the halves agree with the whole.
+    fn adaptive_arc_length(self, t0: f32, t1: f32, estimate: f32, tolerance: f32, depth: u32)
+                           -> f32 {
+        let t_mid = (t0 + t1) * 0.5;
+        let left = self.gauss_legendre_arc_length(t0, t_mid);
+        let right = self.gauss_legendre_arc_length(t_mid, t1);
+        if depth == 0 || f32::abs(left + right - estimate) <= tolerance {
+            return left + right;
+        }
+        self.adaptive_arc_length(t0, t_mid, left, tolerance * 0.5, depth - 1) +
+            self.adaptive_arc_length(t_mid, t1, right, tolerance * 0.5, depth - 1)
+    }
+
+    fn gauss_legendre_arc_length(self, t0: f32, t1: f32) -> f32 {
+        let (half_width, center) = ((t1 - t0) * 0.5, (t0 + t1) * 0.5);
+        let mut length = 0.0;
+        for (&x, &weight) in GAUSS_LEGENDRE_ABSCISSAE.iter().zip(GAUSS_LEGENDRE_WEIGHTS.iter()) {
+            length += weight * self.derivative(center + half_width * x).length();
+        }
+        length * half_width
+    }
+
     #[inline]
     pub fn min_x(&self) -> f32 {
         f32::min(self.0.baseline.min_x(), self.0.ctrl.min_x())
@@ -363,3 +573,70 @@ impl<'s> CubicSegment<'s> {
         f32::max(self.0.baseline.max_y(), self.0.ctrl.max_y())
     }
 }
+
+// Returns the real roots of `a*t^2 + b*t + c`.
+fn solve_quadratic(a: f32, b: f32, c: f32) -> [Option<f32>; 2] {
+    if f32::abs(a) < EPSILON {
+        if f32::abs(b) < EPSILON {
+            return [None, None];
+        }
+        return [Some(-c / b), None];
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return [None, None];
+    }
+    let sqrt_discriminant = discriminant.sqrt();
+    [Some((-b - sqrt_discriminant) / (2.0 * a)), Some((-b + sqrt_discriminant) / (2.0 * a))]
+}
+
+#[cfg(test)]
+mod test {
+    use crate::segment::Segment;
+    use pathfinder_geometry::line_segment::LineSegment2F;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::vec2f;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn arc_length() {
+        let line = Segment::line(LineSegment2F::new(vec2f(0.0, 0.0), vec2f(30.0, 40.0)));
+        assert_eq!(line.arc_length(), 50.0);
+
+        let arc = Segment::quarter_circle_arc().transform(&Transform2F::from_scale(100.0));
+        assert!(f32::abs(arc.arc_length() - 100.0 * FRAC_PI_2) < 0.05);
+
+        // A quadratic with its control point on the chord is a straight line.
+        let quadratic = Segment::quadratic(LineSegment2F::new(vec2f(0.0, 0.0), vec2f(10.0, 0.0)),
+                                           vec2f(5.0, 0.0));
+        assert!(f32::abs(quadratic.arc_length() - 10.0) < 0.01);
+    }
+
+    #[test]
+    fn time_for_distance() {
+        let arc = Segment::quarter_circle_arc().transform(&Transform2F::from_scale(100.0));
+        let length = arc.arc_length();
+        for &distance in &[1.0, 25.0, 50.0, 100.0, 150.0] {
+            let t = arc.time_for_distance(distance);
+            assert!(t > 0.0 && t < 1.0);
+            let (before, after) = arc.split(t);
+            assert!(f32::abs(before.arc_length() - distance) < 0.05);
+            assert!(f32::abs(after.arc_length() - (length - distance)) < 0.05);
+        }
+
+        assert_eq!(arc.time_for_distance(0.0), 0.0);
+        assert_eq!(arc.time_for_distance(length * 2.0), 1.0);
+        assert_eq!(arc.time_for_distance_with_length(50.0, length), arc.time_for_distance(50.0));
+
+        let line = Segment::line(LineSegment2F::new(vec2f(0.0, 0.0), vec2f(30.0, 40.0)));
+        assert_eq!(line.time_for_distance(10.0), 0.2);
+        assert_eq!(line.time_for_distance(60.0), 1.0);
+        assert_eq!(line.time_for_distance(-10.0), 0.0);
+
+        // Segments with no length don't divide by zero.
+        let point = Segment::line(LineSegment2F::new(vec2f(5.0, 5.0), vec2f(5.0, 5.0)));
+        assert_eq!(point.time_for_distance(1.0), 0.0);
+        assert_eq!(Segment::none().time_for_distance(1.0), 0.0);
+    }
+}
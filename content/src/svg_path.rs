@@ -0,0 +1,510 @@
+// pathfinder/content/src/svg_path.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reading and writing SVG path data: the syntax of the `d` attribute of the SVG `<path>`
+//! element.
+//!
+//! See https://www.w3.org/TR/SVG2/paths.html#PathData.
+
+use crate::outline::{ArcDirection, Contour, ContourIterFlags, Outline};
+use crate::segment::SegmentKind;
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter, Write};
+use std::mem;
+
+/// An error encountered while parsing SVG path data.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SVGPathDataError {
+    /// The byte offset in the path data at which the error was found.
+    pub position: usize,
+    /// What went wrong.
+    pub kind: SVGPathDataErrorKind,
+}
+
+/// The kinds of errors that can occur while parsing SVG path data.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SVGPathDataErrorKind {
+    /// Path data must begin with a move-to command.
+    ExpectedMoveTo,
+    /// A command letter was expected.
+    ExpectedCommand,
+    /// A number was expected.
+    ExpectedNumber,
+    /// An arc flag, `0` or `1`, was expected.
+    ExpectedFlag,
+}
+
+/// Parses SVG path data into an outline, failing if the data contains any errors.
+pub fn parse(data: &str) -> Result<Outline, SVGPathDataError> {
+    match parse_prefix(data) {
+        (outline, None) => Ok(outline),
+        (_, Some(error)) => Err(error),
+    }
+}
+
+/// Parses as much of the SVG path data as is valid, as browsers do.
+///
+/// Returns the outline described by the path data up to the first error, along with that error,
+/// if any.
+pub fn parse_prefix(data: &str) -> (Outline, Option<SVGPathDataError>) {
+    let mut parser = Parser::new(data);
+    let error = parser.parse().err();
+    (parser.finish(), error)
+}
+
+/// Writes the outline as SVG path data.
+///
+/// If `decimal_places` is `Some`, coordinates are rounded to that many digits after the decimal
+/// point. Otherwise, they are written with as many digits as are needed to read them back exactly.
+pub fn write(outline: &Outline, decimal_places: Option<usize>) -> String {
+    let mut writer = Writer { output: String::new(), decimal_places };
+    for contour in outline.contours() {
+        let first_position = match contour.first_position() {
+            None => continue,
+            Some(first_position) => first_position,
+        };
+        writer.command('M', &[first_position]);
+
+        for segment in contour.iter(ContourIterFlags::IGNORE_CLOSE_SEGMENT) {
+            match segment.kind {
+                SegmentKind::None => {}
+                SegmentKind::Line => writer.command('L', &[segment.baseline.to()]),
+                SegmentKind::Quadratic => {
+                    writer.command('Q', &[segment.ctrl.from(), segment.baseline.to()])
+                }
+                SegmentKind::Cubic => {
+                    writer.command('C', &[
+                        segment.ctrl.from(),
+                        segment.ctrl.to(),
+                        segment.baseline.to(),
+                    ])
+                }
+            }
+        }
+
+        if contour.is_closed() {
+            writer.command('Z', &[]);
+        }
+    }
+    writer.output
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    position: usize,
+    outline: Outline,
+    contour: Contour,
+    current_point: Vector2F,
+    subpath_start: Vector2F,
+    // The control point to reflect for a following smooth curve command, if the previous command
+    // was a curve of the same kind.
+    last_cubic_ctrl: Option<Vector2F>,
+    last_quadratic_ctrl: Option<Vector2F>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(data: &'a str) -> Parser<'a> {
+        Parser {
+            data: data.as_bytes(),
+            position: 0,
+            outline: Outline::new(),
+            contour: Contour::new(),
+            current_point: Vector2F::zero(),
+            subpath_start: Vector2F::zero(),
+            last_cubic_ctrl: None,
+            last_quadratic_ctrl: None,
+        }
+    }
+
+    fn finish(mut self) -> Outline {
+        self.flush_contour();
+        self.outline
+    }
+
+    fn parse(&mut self) -> Result<(), SVGPathDataError> {
+        self.skip_whitespace();
+        if self.position == self.data.len() {
+            return Ok(());
+        }
+
+        let mut command = self.data[self.position];
+        if command != b'M' && command != b'm' {
+            return Err(self.error(SVGPathDataErrorKind::ExpectedMoveTo));
+        }
+        self.position += 1;
+
+        loop {
+            self.parse_command(command)?;
+
+            self.skip_whitespace();
+            if self.position == self.data.len() {
+                return Ok(());
+            }
+
+            let next = self.data[self.position];
+            if next.is_ascii_alphabetic() {
+                command = next;
+                self.position += 1;
+                continue;
+            }
+
+            // Additional arguments repeat the previous command. Move-to commands become line-to
+            // commands, and close-path commands take no arguments.
+            command = match command {
+                b'M' => b'L',
+                b'm' => b'l',
+                b'Z' | b'z' => return Err(self.error(SVGPathDataErrorKind::ExpectedCommand)),
+                command => command,
+            };
+            self.skip_comma_whitespace();
+        }
+    }
+
+    fn parse_command(&mut self, command: u8) -> Result<(), SVGPathDataError> {
+        let relative = command.is_ascii_lowercase();
+        let origin = if relative { self.current_point } else { Vector2F::zero() };
+        let (mut cubic_ctrl, mut quadratic_ctrl) = (None, None);
+
+        match command.to_ascii_uppercase() {
+            b'M' => {
+                let to = origin + self.parse_point()?;
+                self.flush_contour();
+                self.contour.push_endpoint(to);
+                self.current_point = to;
+                self.subpath_start = to;
+            }
+            b'Z' => {
+                if !self.contour.is_empty() {
+                    self.contour.close();
+                    self.flush_contour();
+                }
+                self.current_point = self.subpath_start;
+            }
+            b'L' => {
+                let to = origin + self.parse_point()?;
+                self.line_to(to);
+            }
+            b'H' => {
+                let x = origin.x() + self.parse_number()?;
+                self.line_to(vec2f(x, self.current_point.y()));
+            }
+            b'V' => {
+                let y = origin.y() + self.parse_number()?;
+                self.line_to(vec2f(self.current_point.x(), y));
+            }
+            b'C' => {
+                let ctrl0 = origin + self.parse_point()?;
+                let ctrl1 = origin + self.parse_point()?;
+                let to = origin + self.parse_point()?;
+                self.start_contour_if_necessary();
+                self.contour.push_cubic(ctrl0, ctrl1, to);
+                self.current_point = to;
+                cubic_ctrl = Some(ctrl1);
+            }
+            b'S' => {
+                let ctrl0 = self.reflect(self.last_cubic_ctrl);
+                let ctrl1 = origin + self.parse_point()?;
+                let to = origin + self.parse_point()?;
+                self.start_contour_if_necessary();
+                self.contour.push_cubic(ctrl0, ctrl1, to);
+                self.current_point = to;
+                cubic_ctrl = Some(ctrl1);
+            }
+            b'Q' => {
+                let ctrl = origin + self.parse_point()?;
+                let to = origin + self.parse_point()?;
+                self.start_contour_if_necessary();
+                self.contour.push_quadratic(ctrl, to);
+                self.current_point = to;
+                quadratic_ctrl = Some(ctrl);
+            }
+            b'T' => {
+                let ctrl = self.reflect(self.last_quadratic_ctrl);
+                let to = origin + self.parse_point()?;
+                self.start_contour_if_necessary();
+                self.contour.push_quadratic(ctrl, to);
+                self.current_point = to;
+                quadratic_ctrl = Some(ctrl);
+            }
+            b'A' => {
+                let radius = self.parse_point()?;
+                let x_axis_rotation = self.parse_number()?.to_radians();
+                let large_arc = self.parse_flag()?;
+                let sweep = self.parse_flag()?;
+                let to = origin + self.parse_point()?;
+                self.arc_to(radius, x_axis_rotation, large_arc, sweep, to);
+            }
+            _ => {
+                self.position -= 1;
+                return Err(self.error(SVGPathDataErrorKind::ExpectedCommand));
+            }
+        }
+
+        self.last_cubic_ctrl = cubic_ctrl;
+        self.last_quadratic_ctrl = quadratic_ctrl;
+        Ok(())
+    }
+
+    fn line_to(&mut self, to: Vector2F) {
+        self.start_contour_if_necessary();
+        self.contour.push_endpoint(to);
+        self.current_point = to;
+    }
+
+    // https://www.w3.org/TR/SVG2/implnote.html#ArcOutOfRangeParameters
+    fn arc_to(&mut self,
+              radius: Vector2F,
+              x_axis_rotation: f32,
+              large_arc: bool,
+              sweep: bool,
+              to: Vector2F) {
+        if to == self.current_point {
+            return;
+        }
+        if radius.x() == 0.0 || radius.y() == 0.0 {
+            self.line_to(to);
+            return;
+        }
+
+        self.start_contour_if_necessary();
+        let direction = if sweep { ArcDirection::CW } else { ArcDirection::CCW };
+        self.contour.push_svg_arc(radius, x_axis_rotation, large_arc, direction, to);
+        self.current_point = to;
+    }
+
+    #[inline]
+    fn reflect(&self, ctrl: Option<Vector2F>) -> Vector2F {
+        match ctrl {
+            Some(ctrl) => self.current_point * 2.0 - ctrl,
+            None => self.current_point,
+        }
+    }
+
+    // Drawing commands following a close-path command begin a new subpath at the start of the
+    // closed one.
+    fn start_contour_if_necessary(&mut self) {
+        if self.contour.is_empty() {
+            self.contour.push_endpoint(self.current_point);
+        }
+    }
+
+    fn flush_contour(&mut self) {
+        if !self.contour.is_empty() {
+            self.outline.push_contour(mem::replace(&mut self.contour, Contour::new()));
+        }
+    }
+
+    fn parse_point(&mut self) -> Result<Vector2F, SVGPathDataError> {
+        let x = self.parse_number()?;
+        let y = self.parse_number()?;
+        Ok(vec2f(x, y))
+    }
+
+    fn parse_number(&mut self) -> Result<f32, SVGPathDataError> {
+        self.skip_whitespace();
+        let start = self.position;
+        self.skip_sign();
+        let integer_digits = self.skip_digits();
+        let fraction_digits = if self.eat(b'.') { self.skip_digits() } else { 0 };
+        if integer_digits == 0 && fraction_digits == 0 {
+            self.position = start;
+            return Err(self.error(SVGPathDataErrorKind::ExpectedNumber));
+        }
+
+        // Only consume an exponent if digits follow it, since `e` can't begin a command.
+        let mantissa_end = self.position;
+        if self.eat(b'e') || self.eat(b'E') {
+            self.skip_sign();
+            if self.skip_digits() == 0 {
+                self.position = mantissa_end;
+            }
+        }
+
+        let number = std::str::from_utf8(&self.data[start..self.position]).unwrap();
+        let number = number.parse().unwrap();
+        self.skip_comma_whitespace();
+        Ok(number)
+    }
+
+    // Flags may be written without separators, as in `a1 1 0 00 1 1`.
+    fn parse_flag(&mut self) -> Result<bool, SVGPathDataError> {
+        self.skip_whitespace();
+        let flag = if self.eat(b'0') {
+            false
+        } else if self.eat(b'1') {
+            true
+        } else {
+            return Err(self.error(SVGPathDataErrorKind::ExpectedFlag));
+        };
+        self.skip_comma_whitespace();
+        Ok(flag)
+    }
+
+    #[inline]
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.data.get(self.position) == Some(&byte) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_sign(&mut self) {
+        if !self.eat(b'+') {
+            self.eat(b'-');
+        }
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.position;
+        while let Some(byte) = self.data.get(self.position) {
+            if !byte.is_ascii_digit() {
+                break;
+            }
+            self.position += 1;
+        }
+        self.position - start
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&byte) = self.data.get(self.position) {
+            if !is_whitespace(byte) {
+                break;
+            }
+            self.position += 1;
+        }
+    }
+
+    fn skip_comma_whitespace(&mut self) {
+        self.skip_whitespace();
+        if self.eat(b',') {
+            self.skip_whitespace();
+        }
+    }
+
+    #[inline]
+    fn error(&self, kind: SVGPathDataErrorKind) -> SVGPathDataError {
+        SVGPathDataError { position: self.position, kind }
+    }
+}
+
+#[inline]
+fn is_whitespace(byte: u8) -> bool {
+    byte == b' ' || byte == b'\t' || byte == b'\n' || byte == b'\r' || byte == b'\x0c'
+}
+
+struct Writer {
+    output: String,
+    decimal_places: Option<usize>,
+}
+
+impl Writer {
+    fn command(&mut self, command: char, points: &[Vector2F]) {
+        if !self.output.is_empty() {
+            self.output.push(' ');
+        }
+        self.output.push(command);
+        for point in points {
+            self.number(point.x());
+            self.number(point.y());
+        }
+    }
+
+    fn number(&mut self, value: f32) {
+        self.output.push(' ');
+        let start = self.output.len();
+        match self.decimal_places {
+            None => write!(self.output, "{}", value).unwrap(),
+            Some(decimal_places) => {
+                write!(self.output, "{:.*}", decimal_places, value).unwrap();
+                if self.output[start..].contains('.') {
+                    let trimmed_length = self.output.trim_end_matches('0')
+                                                    .trim_end_matches('.')
+                                                    .len();
+                    self.output.truncate(trimmed_length);
+                }
+            }
+        }
+        if &self.output[start..] == "-0" {
+            self.output.remove(start);
+        }
+    }
+}
+
+impl Display for SVGPathDataError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let message = match self.kind {
+            SVGPathDataErrorKind::ExpectedMoveTo => "path data must begin with a move-to command",
+            SVGPathDataErrorKind::ExpectedCommand => "expected a path command",
+            SVGPathDataErrorKind::ExpectedNumber => "expected a number",
+            SVGPathDataErrorKind::ExpectedFlag => "expected an arc flag",
+        };
+        write!(formatter, "{} at offset {}", message, self.position)
+    }
+}
+
+impl Error for SVGPathDataError {}
+
+#[cfg(test)]
+mod test {
+    use crate::outline::Outline;
+    use crate::svg_path::{SVGPathDataError, SVGPathDataErrorKind};
+    use pathfinder_geometry::vector::vec2f;
+
+    #[test]
+    fn parse_commands() {
+        let outline = Outline::from_svg_path_data("M10,20 l5-5h10v10 Z m1 1 2 2").unwrap();
+        assert_eq!(format!("{:?}", outline), "M 10 20 L 15 15 L 25 15 L 25 25 z M 11 21 L 13 23");
+
+        let outline = Outline::from_svg_path_data("M0 0C0 1 2 3 4 5s6 7 8 9Q1 1 2 2t3 3").unwrap();
+        assert_eq!(format!("{:?}", outline),
+                   "M 0 0 C 0 1 2 3 4 5 C 6 7 10 12 12 14 Q 1 1 2 2 Q 3 3 5 5");
+
+        // Numbers can run together, and arc flags need no separators.
+        let outline = Outline::from_svg_path_data("M.5.5-1e1-.5e0").unwrap();
+        assert_eq!(format!("{:?}", outline), "M 0.5 0.5 L -10 -0.5");
+        let outline = Outline::from_svg_path_data("M0 0a5 5 0 0110 0").unwrap();
+        assert_eq!(outline.contours()[0].last_position(), Some(vec2f(10.0, 0.0)));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = Outline::from_svg_path_data("L 0 0").unwrap_err();
+        assert_eq!(error, SVGPathDataError {
+            position: 0,
+            kind: SVGPathDataErrorKind::ExpectedMoveTo,
+        });
+        let error = Outline::from_svg_path_data("M 0 0 L 1 x").unwrap_err();
+        assert_eq!(error.kind, SVGPathDataErrorKind::ExpectedNumber);
+        assert_eq!(error.position, 10);
+
+        // Everything before the error is kept.
+        let (outline, error) = crate::svg_path::parse_prefix("M 0 0 L 1 1 L 2");
+        assert_eq!(format!("{:?}", outline), "M 0 0 L 1 1");
+        assert_eq!(error.unwrap().kind, SVGPathDataErrorKind::ExpectedNumber);
+
+        assert!(Outline::from_svg_path_data("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn write_and_reparse() {
+        let data = "M 0.5 -1 L 2 3 Q 4 5 6 7 C 8 9 10 11 12 13 Z M 1 1 L 2 2";
+        let outline = Outline::from_svg_path_data(data).unwrap();
+        assert_eq!(outline.to_svg_path_data(), data);
+        assert_eq!(Outline::from_svg_path_data(&outline.to_svg_path_data()).unwrap()
+                                                                           .to_svg_path_data(),
+                   data);
+
+        let outline = Outline::from_svg_path_data("M 0.123456 -0.0001 L 1.5 2").unwrap();
+        assert_eq!(outline.to_svg_path_data_with_precision(2), "M 0.12 0 L 1.5 2");
+    }
+}
@@ -8,15 +8,30 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use pathfinder_content::outline::ContourIterFlags;
-use pathfinder_content::segment::SegmentKind;
-use pathfinder_geometry::vector::{Vector2F, vec2f};
-use pathfinder_renderer::scene::{DrawPathId, Scene};
-use std::fmt;
+use deflate::Compression;
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::outline::Outline;
+use pathfinder_content::pattern::Pattern;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2F;
+use pathfinder_renderer::scene::Scene;
 use std::io::{self, Write};
+use std::iter;
+use std::ops::Range;
+use std::time::SystemTime;
 
 mod pdf;
-use pdf::Pdf;
+mod ps;
+mod svg;
+
+use pdf::{PDFExporter, Pdf, write_pdf_outline};
+use ps::export_ps;
+use svg::export_svg;
+
+// Repeating patterns are drawn once per tile in PDF and PostScript. Past this many tiles, they
+// are drawn once.
+const MAX_PATTERN_TILES: usize = 4096;
 
 pub enum FileFormat {
     /// Scalable Vector Graphics
@@ -43,161 +58,167 @@ impl Export for Scene {
     }
 }
 
-fn export_svg<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
-    let view_box = scene.view_box();
-    writeln!(
-        writer,
-        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">",
-        view_box.origin().x(),
-        view_box.origin().y(),
-        view_box.size().x(),
-        view_box.size().y()
-    )?;
-    for draw_path_index in 0..scene.draw_path_count() {
-        let draw_path_id = DrawPathId(draw_path_index);
-        let draw_path = scene.get_draw_path(draw_path_id);
-        write!(writer, "    <path")?;
-        if !draw_path.name.is_empty() {
-            write!(writer, " id=\"{}\"", draw_path.name)?;
+/// Options for writing PDF documents with `export_pdf_document()`.
+#[derive(Clone, Debug)]
+pub struct PDFOptions {
+    /// The title of the document, if any.
+    pub title: Option<String>,
+    /// The author of the document, if any.
+    pub author: Option<String>,
+    /// The time at which the document was created, if it should be recorded.
+    pub creation_date: Option<SystemTime>,
+    /// Whether content streams, images, and forms are compressed with FlateDecode.
+    pub compress: bool,
+    /// The size of every page, in points. If set, each scene's view box is scaled uniformly to
+    /// fit its page and centered on it. Otherwise, each page is the size of its scene's view box.
+    pub page_size: Option<Vector2F>,
+}
+
+impl Default for PDFOptions {
+    #[inline]
+    fn default() -> PDFOptions {
+        PDFOptions {
+            title: None,
+            author: None,
+            creation_date: None,
+            compress: true,
+            page_size: None,
         }
-        writeln!(writer, " fill=\"{:?}\" d=\"{:?}\" />", draw_path.paint, draw_path.outline)?;
     }
-    writeln!(writer, "</svg>")?;
-    Ok(())
 }
 
-fn export_pdf<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
+/// Writes a PDF document with one page for each scene, in order.
+///
+/// Each page is the size of its scene's view box unless `PDFOptions::page_size` is set.
+pub fn export_pdf_document<'a, I, W>(scenes: I, writer: &mut W, options: &PDFOptions)
+                                     -> io::Result<()>
+                                     where I: IntoIterator<Item = &'a Scene>, W: Write {
     let mut pdf = Pdf::new();
-    let view_box = scene.view_box();
-    pdf.add_page(view_box.size());
-
-    let height = view_box.size().y();
-    let tr = |v: Vector2F| -> Vector2F {
-        let r = v - view_box.origin();
-        vec2f(r.x(), height - r.y())
-    };
-
-    for draw_path_index in 0..scene.draw_path_count() {
-        let draw_path_id = DrawPathId(draw_path_index);
-        let draw_path = scene.get_draw_path(draw_path_id);
-
-        // TODO(pcwalton): Gradients and patterns.
-        let paint = scene.get_paint(draw_path.paint);
-        if paint.is_color() {
-            pdf.set_fill_color(paint.base_color());
+    pdf.set_compression(if options.compress { Some(Compression::Fast) } else { None });
+    pdf.set_info(options.title.as_deref(), options.author.as_deref(), options.creation_date);
+
+    for scene in scenes {
+        let view_box = scene.view_box();
+        let page_size = options.page_size.unwrap_or_else(|| view_box.size());
+        pdf.add_page(page_size);
+
+        // PDF's Y axis points up, so flip the page to put the origin of the view box at the top
+        // left of the area it's fit into.
+        let scale = fit_scale(view_box.size(), page_size);
+        let offset = (page_size - view_box.size() * scale) * 0.5;
+        pdf.transform(&Transform2F::row_major(scale,
+                                              0.0,
+                                              offset.x() - view_box.origin().x() * scale,
+                                              0.0,
+                                              -scale,
+                                              page_size.y() - offset.y() +
+                                              view_box.origin().y() * scale));
+        if options.page_size.is_some() {
+            // Hide whatever lies outside the view box in the margins.
+            write_pdf_outline(&mut pdf, &Outline::from_rect(view_box));
+            pdf.clip(FillRule::Winding);
         }
 
-        for contour in draw_path.outline.contours() {
-            for (segment_index, segment) in contour.iter(ContourIterFlags::empty()).enumerate() {
-                if segment_index == 0 {
-                    pdf.move_to(tr(segment.baseline.from()));
-                }
-
-                match segment.kind {
-                    SegmentKind::None => {}
-                    SegmentKind::Line => pdf.line_to(tr(segment.baseline.to())),
-                    SegmentKind::Quadratic => {
-                        let current = segment.baseline.from();
-                        let c = segment.ctrl.from();
-                        let p = segment.baseline.to();
-                        let c1 = c * (2.0 / 3.0) + current * (1.0 / 3.0);
-                        let c2 = c * (2.0 / 3.0) + p * (1.0 / 3.0);
-                        pdf.cubic_to(c1, c2, p);
-                    }
-                    SegmentKind::Cubic => {
-                        pdf.cubic_to(tr(segment.ctrl.from()),
-                                     tr(segment.ctrl.to()),
-                                     tr(segment.baseline.to()))
-                    }
-                }
-            }
-
-            if contour.is_closed() {
-                pdf.close();
-            }
-        }
-
-        // closes implicitly
-        pdf.fill();
+        let ranges = scene.draw_path_ranges();
+        PDFExporter::new(scene, &mut pdf, ranges.render_targets).write_draw_paths(&ranges.main);
     }
+
     pdf.write_to(writer)
 }
 
-fn export_ps<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
-    struct P(Vector2F);
-    impl fmt::Display for P {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "{} {}", self.0.x(), self.0.y())
-        }
+// Returns the largest scale at which something of the given size fits within `bounds`.
+fn fit_scale(size: Vector2F, bounds: Vector2F) -> f32 {
+    if size.x() <= 0.0 || size.y() <= 0.0 {
+        return 1.0;
     }
+    f32::min(bounds.x() / size.x(), bounds.y() / size.y())
+}
+
+fn export_pdf<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
+    export_pdf_document(iter::once(scene), writer, &PDFOptions::default())
+}
 
-    let view_box = scene.view_box();
-    writeln!(writer, "%!PS-Adobe-3.0 EPSF-3.0")?;
-    writeln!(writer, "%%BoundingBox: {:.0} {:.0}",
-        P(view_box.origin()),
-        P(view_box.size()),
-    )?;
-    writeln!(writer, "%%HiResBoundingBox: {} {}",
-        P(view_box.origin()),
-        P(view_box.size()),
-    )?;
-    writeln!(writer, "0 {} translate", view_box.size().y())?;
-    writeln!(writer, "1 -1 scale")?;
-
-    for draw_path_index in 0..scene.draw_path_count() {
-        let draw_path_id = DrawPathId(draw_path_index);
-        let draw_path = scene.get_draw_path(draw_path_id);
-        if !draw_path.name.is_empty() {
-            writeln!(writer, "newpath % {}", draw_path.name)?;
+/// Returns the ranges of tiles needed to cover `bounds` with the pattern, in units of the
+/// pattern's size. Non-repeating patterns have a single tile.
+pub(crate) fn pattern_tiles(pattern: &Pattern, bounds: RectF) -> (Range<i32>, Range<i32>) {
+    let size = pattern.size().to_f32();
+    let coverage = pattern.transform().inverse() * bounds;
+    let tile_range = |repeat: bool, min: f32, max: f32, tile_size: f32| {
+        if repeat && tile_size > 0.0 {
+            (f32::floor(min / tile_size) as i32)..(f32::ceil(max / tile_size) as i32)
         } else {
-            writeln!(writer, "newpath")?;
+            0..1
         }
+    };
+    let tiles_x = tile_range(pattern.repeat_x(), coverage.min_x(), coverage.max_x(), size.x());
+    let tiles_y = tile_range(pattern.repeat_y(), coverage.min_y(), coverage.max_y(), size.y());
+    if tiles_x.len() * tiles_y.len() > MAX_PATTERN_TILES {
+        (0..1, 0..1)
+    } else {
+        (tiles_x, tiles_y)
+    }
+}
 
-        for contour in draw_path.outline.contours() {
-            for (segment_index, segment) in contour.iter(ContourIterFlags::empty()).enumerate() {
-                if segment_index == 0 {
-                    writeln!(writer, "{} moveto", P(segment.baseline.from()))?;
-                }
-
-                match segment.kind {
-                    SegmentKind::None => {}
-                    SegmentKind::Line => {
-                        writeln!(writer, "{} lineto", P(segment.baseline.to()))?;
-                    }
-                    SegmentKind::Quadratic => {
-                        let current = segment.baseline.from();
-                        let c = segment.ctrl.from();
-                        let p = segment.baseline.to();
-                        let c1 = c * (2.0 / 3.0) + current * (1.0 / 3.0);
-                        let c2 = c * (2.0 / 3.0) + p * (1.0 / 3.0);
-                        writeln!(writer, "{} {} {} curveto", P(c1), P(c2), P(p))?;
-                    }
-                    SegmentKind::Cubic => {
-                        writeln!(writer, "{} {} {} curveto",
-                            P(segment.ctrl.from()),
-                            P(segment.ctrl.to()),
-                            P(segment.baseline.to())
-                        )?;
-                    }
-                }
-            }
-
-            if contour.is_closed() {
-                writeln!(writer, "closepath")?;
-            }
+#[cfg(test)]
+mod test {
+    use crate::{PDFOptions, export_pdf_document};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::outline::Outline;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::{Vector2F, vec2f};
+    use pathfinder_renderer::paint::Paint;
+    use pathfinder_renderer::scene::{DrawPath, Scene};
+
+    #[test]
+    fn multi_page_pdf() {
+        let mut scenes = vec![];
+        for &size in &[vec2f(100.0, 200.0), vec2f(300.0, 50.0)] {
+            let mut scene = Scene::new();
+            scene.set_view_box(RectF::new(Vector2F::zero(), size));
+            let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+            let outline = Outline::from_rect(RectF::new(Vector2F::zero(), vec2f(10.0, 10.0)));
+            scene.push_draw_path(DrawPath::new(outline, paint));
+            scenes.push(scene);
         }
 
-        // TODO(pcwalton): Gradients and patterns.
-        let paint = scene.get_paint(draw_path.paint);
-        if paint.is_color() {
-            let color = paint.base_color();
-            writeln!(writer, "{} {} {} setrgbcolor", color.r, color.g, color.b)?;
-        }
+        let options = PDFOptions {
+            title: Some("Report".to_owned()),
+            compress: false,
+            ..PDFOptions::default()
+        };
+        let mut output = vec![];
+        export_pdf_document(&scenes, &mut output, &options).unwrap();
+        let pdf = String::from_utf8_lossy(&output);
+        assert!(pdf.contains("/Count 2"));
+        assert!(pdf.contains("/MediaBox [0 0 100 200]"));
+        assert!(pdf.contains("/MediaBox [0 0 300 50]"));
+        assert!(pdf.contains("<< /Title (Report) >>"));
+        assert!(pdf.contains("/Info "));
+        assert!(pdf.contains("\n0 0 m\n10 0 l\n10 10 l\n0 10 l\n0 0 l\nh\nf\n"));
+        assert!(!pdf.contains("FlateDecode"));
+    }
 
-        writeln!(writer, "fill")?;
+    #[test]
+    fn pdf_page_size() {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(vec2f(10.0, 10.0), vec2f(100.0, 50.0)));
+        let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+        let outline = Outline::from_rect(RectF::new(Vector2F::zero(), vec2f(10.0, 10.0)));
+        scene.push_draw_path(DrawPath::new(outline, paint));
+
+        let options = PDFOptions {
+            compress: false,
+            page_size: Some(vec2f(400.0, 400.0)),
+            ..PDFOptions::default()
+        };
+        let mut output = vec![];
+        export_pdf_document(&[scene], &mut output, &options).unwrap();
+        let pdf = String::from_utf8_lossy(&output);
+        assert!(pdf.contains("/MediaBox [0 0 400 400]"), "{}", pdf);
+        // The view box is scaled by 4 and centered vertically, with its origin at the top left.
+        assert!(pdf.contains("4 0 0 -4 -40 340 cm\n"), "{}", pdf);
+        assert!(pdf.contains("\n10 10 m\n110 10 l\n110 60 l\n10 60 l\n10 10 l\nh\nW n\n"),
+                "{}",
+                pdf);
     }
-    writeln!(writer, "showpage")?;
-    Ok(())
 }
-
-
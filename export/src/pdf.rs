@@ -13,8 +13,27 @@
 
 use deflate::Compression;
 use pathfinder_color::ColorU;
-use pathfinder_geometry::vector::Vector2F;
+use pathfinder_content::effects::BlendMode;
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::gradient::{ColorStop, Gradient, GradientGeometry, GradientWrap};
+use pathfinder_content::outline::{ContourIterFlags, Outline};
+use pathfinder_content::pattern::{Image, ImageHash, Pattern, PatternSource};
+use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_content::segment::SegmentKind;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use pathfinder_renderer::paint::PaintCompositeOp;
+use pathfinder_renderer::scene::{DrawPath, DrawPathId, Scene};
+use std::collections::HashMap;
 use std::io::{self, Write};
+use std::mem;
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Gradients that repeat are written out as one long gradient covering the filled area. This
+// limits how many repetitions that may take.
+const MAX_GRADIENT_REPETITIONS: i32 = 256;
 
 struct Counter<T> {
     inner: T,
@@ -52,20 +71,44 @@ impl<W: Write> Write for Counter<W> {
     }
 }
 
+
 /// Represents a PDF internal object
 struct PdfObject {
     contents: Vec<u8>,
     is_page: bool,
-    is_xobject: bool,
     offset: Option<u64>,
 }
 
+/// The kinds of resources that content streams refer to by name.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ResourceKind {
+    ExtGState,
+    Shading,
+    XObject,
+}
+
+/// Which part of a gradient's colors a shading carries.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShadingChannel {
+    /// The RGB color of each stop.
+    Color,
+    /// The alpha of each stop, as a gray level, for use in soft masks.
+    Alpha,
+}
+
 /// The top-level struct that represents a (partially) in-memory PDF file
 pub struct Pdf {
     page_buffer: Vec<u8>,
+    /// The resources that the current page or form refers to.
+    resources: Vec<(ResourceKind, usize)>,
+    /// The contents and resources of the pages or forms enclosing the form being written.
+    form_stack: Vec<(Vec<u8>, Vec<(ResourceKind, usize)>)>,
     objects: Vec<PdfObject>,
     page_size: Option<Vector2F>,
     compression: Option<Compression>,
+    info: Option<String>,
+    graphics_states: HashMap<(u32, &'static str), usize>,
+    images: HashMap<(ImageHash, bool), usize>,
 }
 
 impl Default for Pdf {
@@ -80,35 +123,92 @@ impl Pdf {
     pub fn new() -> Self {
         Self {
             page_buffer: Vec::new(),
+            resources: Vec::new(),
+            form_stack: Vec::new(),
             objects: vec![
                 PdfObject {
                     contents: Vec::new(),
                     is_page: false,
-                    is_xobject: false,
                     offset: None,
                 },
                 PdfObject {
                     contents: Vec::new(),
                     is_page: false,
-                    is_xobject: false,
                     offset: None,
                 },
             ],
             page_size: None,
-            compression: Some(Compression::Fast)
+            compression: Some(Compression::Fast),
+            info: None,
+            graphics_states: HashMap::new(),
+            images: HashMap::new(),
         }
     }
 
-    fn add_object(&mut self, data: Vec<u8>, is_page: bool, is_xobject: bool) -> usize {
+    /// Sets how streams are compressed, or disables compression if `None`.
+    #[inline]
+    pub fn set_compression(&mut self, compression: Option<Compression>) {
+        self.compression = compression;
+    }
+
+    /// Sets the document information that viewers show, such as the title.
+    pub fn set_info(&mut self,
+                    title: Option<&str>,
+                    author: Option<&str>,
+                    creation_date: Option<SystemTime>) {
+        let mut info = String::new();
+        if let Some(title) = title {
+            info.push_str(&format!(" /Title {}", text_string(title)));
+        }
+        if let Some(author) = author {
+            info.push_str(&format!(" /Author {}", text_string(author)));
+        }
+        if let Some(creation_date) = creation_date.and_then(date_string) {
+            info.push_str(&format!(" /CreationDate ({})", creation_date));
+        }
+        self.info = if info.is_empty() { None } else { Some(format!("<<{} >>\n", info)) };
+    }
+
+    fn add_object(&mut self, data: Vec<u8>, is_page: bool) -> usize {
         self.objects.push(PdfObject {
             contents: data,
             is_page,
-            is_xobject,
             offset: None,
         });
         self.objects.len()
     }
 
+    /// Adds a stream object with the given dictionary entries, compressing the data if
+    /// compression is enabled.
+    fn add_stream(&mut self, dictionary: &str, data: &[u8]) -> usize {
+        let mut stream = Vec::new();
+        let dictionary = if dictionary.is_empty() {
+            String::new()
+        } else {
+            format!("{} ", dictionary)
+        };
+        if let Some(level) = self.compression {
+            let compressed = deflate::deflate_bytes_zlib_conf(data, level);
+            write!(stream,
+                "<< {}/Length {} /Filter /FlateDecode >>\nstream\n",
+                dictionary,
+                compressed.len()
+            ).unwrap();
+            stream.extend_from_slice(&compressed);
+        } else {
+            write!(stream, "<< {}/Length {} >>\nstream\n", dictionary, data.len()).unwrap();
+            stream.extend_from_slice(data);
+        }
+        stream.extend(b"\nendstream\n");
+        self.add_object(stream, false)
+    }
+
+    fn use_resource(&mut self, kind: ResourceKind, object_id: usize) {
+        if !self.resources.contains(&(kind, object_id)) {
+            self.resources.push((kind, object_id));
+        }
+    }
+
     /// Set the color for all subsequent drawing operations
     #[inline]
     pub fn set_fill_color(&mut self, color: ColorU) {
@@ -120,6 +220,71 @@ impl Pdf {
         ).unwrap();
     }
 
+    /// Sets the constant alpha, blend mode, and soft mask for subsequent drawing operations.
+    ///
+    /// The soft mask, if present, must be a form created by `add_soft_mask()`.
+    pub fn set_graphics_state(&mut self,
+                              alpha: f32,
+                              blend_mode: BlendMode,
+                              soft_mask: Option<usize>) {
+        let blend_mode = blend_mode_name(blend_mode);
+        if alpha == 1.0 && blend_mode == "Normal" && soft_mask.is_none() {
+            return;
+        }
+
+        let cache_key = (alpha.to_bits(), blend_mode);
+        let object_id = match (soft_mask, self.graphics_states.get(&cache_key)) {
+            (None, Some(&object_id)) => object_id,
+            _ => {
+                let mut dictionary = format!("<< /Type /ExtGState /ca {} /BM /{}",
+                                             alpha,
+                                             blend_mode);
+                if let Some(soft_mask) = soft_mask {
+                    dictionary.push_str(&format!(
+                        " /SMask << /Type /Mask /S /Luminosity /G {} 0 R >>",
+                        soft_mask
+                    ));
+                }
+                dictionary.push_str(" >>\n");
+                let object_id = self.add_object(dictionary.into_bytes(), false);
+                if soft_mask.is_none() {
+                    self.graphics_states.insert(cache_key, object_id);
+                }
+                object_id
+            }
+        };
+
+        self.use_resource(ResourceKind::ExtGState, object_id);
+        writeln!(self.page_buffer, "/GS{} gs", object_id).unwrap();
+    }
+
+    /// Saves the graphics state, including the clip and transform.
+    #[inline]
+    pub fn save_state(&mut self) {
+        writeln!(self.page_buffer, "q").unwrap();
+    }
+
+    /// Restores the graphics state saved by the matching `save_state()`.
+    #[inline]
+    pub fn restore_state(&mut self) {
+        writeln!(self.page_buffer, "Q").unwrap();
+    }
+
+    /// Applies the transform before the current one.
+    pub fn transform(&mut self, transform: &Transform2F) {
+        if transform.is_identity() {
+            return;
+        }
+        writeln!(self.page_buffer, "{} {} {} {} {} {} cm",
+            transform.m11(),
+            transform.m21(),
+            transform.m12(),
+            transform.m22(),
+            transform.m13(),
+            transform.m23()
+        ).unwrap();
+    }
+
     /// Move to a new page in the PDF document
     #[inline]
     pub fn add_page(&mut self, size: Vector2F) {
@@ -145,58 +310,150 @@ impl Pdf {
     pub fn cubic_to(&mut self, c1: Vector2F, c2: Vector2F, p: Vector2F) {
         writeln!(self.page_buffer, "{} {} {} {} {} {} c", c1.x(), c1.y(), c2.x(), c2.y(), p.x(), p.y()).unwrap();
     }
-    pub fn fill(&mut self) {
-        writeln!(self.page_buffer, "f").unwrap();
+
+    /// Fills the current path with the current color.
+    pub fn fill(&mut self, fill_rule: FillRule) {
+        match fill_rule {
+            FillRule::Winding => writeln!(self.page_buffer, "f").unwrap(),
+            FillRule::EvenOdd => writeln!(self.page_buffer, "f*").unwrap(),
+        }
+    }
+
+    /// Intersects the clip with the current path and ends the path without painting it.
+    pub fn clip(&mut self, fill_rule: FillRule) {
+        match fill_rule {
+            FillRule::Winding => writeln!(self.page_buffer, "W n").unwrap(),
+            FillRule::EvenOdd => writeln!(self.page_buffer, "W* n").unwrap(),
+        }
     }
 
     pub fn close(&mut self) {
         writeln!(self.page_buffer, "h").unwrap();
     }
+
+    /// Adds a shading that paints the given linear or radial gradient, returning its object ID.
+    ///
+    /// See `shading_dictionary()` for the meaning of the arguments.
+    pub fn add_shading(&mut self, gradient: &Gradient, coverage: RectF, channel: ShadingChannel)
+                       -> Option<usize> {
+        let dictionary = shading_dictionary(gradient, coverage, channel)?;
+        Some(self.add_object(format!("{}\n", dictionary).into_bytes(), false))
+    }
+
+    /// Paints the shading with the given object ID over the current clip.
+    pub fn paint_shading(&mut self, shading: usize) {
+        self.use_resource(ResourceKind::Shading, shading);
+        writeln!(self.page_buffer, "/Sh{} sh", shading).unwrap();
+    }
+
+    /// Adds a form that paints the given alpha shading, for use as a soft mask with
+    /// `set_graphics_state()`.
+    pub fn add_soft_mask(&mut self, shading: usize, bounds: RectF) -> usize {
+        let dictionary = format!(
+            "/Type /XObject /Subtype /Form /BBox [{} {} {} {}] \
+             /Group << /S /Transparency /CS /DeviceGray >> \
+             /Resources << /Shading << /Sh{} {} 0 R >> >>",
+            bounds.min_x(),
+            bounds.min_y(),
+            bounds.max_x(),
+            bounds.max_y(),
+            shading,
+            shading
+        );
+        self.add_stream(&dictionary, format!("/Sh{} sh\n", shading).as_bytes())
+    }
+
+    /// Adds an image XObject, returning its object ID.
+    ///
+    /// Like all image XObjects, the image occupies the unit square, with its first row at the top.
+    pub fn add_image(&mut self, image: &Image, interpolate: bool) -> usize {
+        let cache_key = (image.get_hash(), interpolate);
+        if let Some(&object_id) = self.images.get(&cache_key) {
+            return object_id;
+        }
+
+        let size = image.size();
+        let mut color_data = Vec::with_capacity(image.pixels().len() * 3);
+        let mut alpha_data = Vec::with_capacity(image.pixels().len());
+        for pixel in image.pixels().iter() {
+            color_data.extend_from_slice(&[pixel.r, pixel.g, pixel.b]);
+            alpha_data.push(pixel.a);
+        }
+
+        let mut dictionary = format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+             /BitsPerComponent 8 /Interpolate {}",
+            size.x(),
+            size.y(),
+            interpolate
+        );
+        if !image.is_opaque() {
+            let soft_mask = self.add_stream(&format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray \
+                 /BitsPerComponent 8 /Interpolate {}",
+                size.x(),
+                size.y(),
+                interpolate
+            ), &alpha_data);
+            dictionary.push_str(&format!(" /SMask {} 0 R", soft_mask));
+        }
+
+        let object_id = self.add_stream(&dictionary, &color_data);
+        self.images.insert(cache_key, object_id);
+        object_id
+    }
+
+    /// Paints the image or form with the given object ID.
+    pub fn draw_xobject(&mut self, xobject: usize) {
+        self.use_resource(ResourceKind::XObject, xobject);
+        writeln!(self.page_buffer, "/X{} Do", xobject).unwrap();
+    }
+
+    /// Starts writing a form XObject. Drawing operations go to the form until `end_form()`.
+    pub fn begin_form(&mut self) {
+        let page_buffer = mem::take(&mut self.page_buffer);
+        let resources = mem::take(&mut self.resources);
+        self.form_stack.push((page_buffer, resources));
+    }
+
+    /// Finishes the form started by the matching `begin_form()`, returning its object ID.
+    ///
+    /// Everything drawn in the form is clipped to the rectangle from the origin to `size`.
+    pub fn end_form(&mut self, size: Vector2F) -> usize {
+        let (page_buffer, resources) = self.form_stack.pop().expect("No form to end!");
+        let contents = mem::replace(&mut self.page_buffer, page_buffer);
+        let resources = mem::replace(&mut self.resources, resources);
+        let dictionary = format!("/Type /XObject /Subtype /Form /BBox [0 0 {} {}] /Resources {}",
+                                 size.x(),
+                                 size.y(),
+                                 resource_dictionary(&resources));
+        self.add_stream(&dictionary, &contents)
+    }
+
     /// Dump a page out to disk
     fn end_page(&mut self) {
         let size = match self.page_size.take() {
             Some(size) => size,
             None => return // no page started
         };
-        let page_stream = if let Some(level) = self.compression {
-            let compressed = deflate::deflate_bytes_zlib_conf(&self.page_buffer, level);
-            let mut page = format!(
-                "<< /Length {} /Filter [/FlateDecode] >>\nstream\n",
-                compressed.len()
-            )
-            .into_bytes();
-            page.extend_from_slice(&compressed);
-            page.extend(b"endstream\n");
-            page
-        } else {
-            let mut page = Vec::new();
-            page.extend(format!("<< /Length {} >>\nstream\n", self.page_buffer.len()).bytes());
-            page.extend(&self.page_buffer);
-            page.extend(b"endstream\n");
-            page
-        };
 
         // Create the stream object for this page
-        let stream_object_id = self.add_object(page_stream, false, false);
+        let page_buffer = mem::take(&mut self.page_buffer);
+        let stream_object_id = self.add_stream("", &page_buffer);
 
         // Create the page object, which describes settings for the whole page
-        let mut page_object = b"<< /Type /Page\n \
-            /Parent 2 0 R\n \
-            /Resources <<\n"
-            .to_vec();
-
-        for (idx, _obj) in self.objects.iter().enumerate().filter(|&(_, o)| o.is_xobject) {
-            write!(page_object, "/XObject {} 0 R ", idx+1).unwrap();
-        }
-
+        let resources = mem::take(&mut self.resources);
+        let mut page_object = Vec::new();
         write!(page_object,
-            " >>\n \
+            "<< /Type /Page\n \
+                /Parent 2 0 R\n \
+                /Resources {}\n \
                 /MediaBox [0 0 {} {}]\n \
                 /Contents {} 0 R\n\
                 >>\n",
-            size.x(), size.y(), stream_object_id
+            resource_dictionary(&resources), size.x(), size.y(), stream_object_id
         ).unwrap();
-        self.add_object(page_object, true, false);
+        self.add_object(page_object, true);
     }
 
     /// Write the in-memory PDF representation to disk
@@ -207,6 +464,9 @@ impl Pdf {
         if !self.page_buffer.is_empty() {
             self.end_page();
         }
+        let info_object_id = self.info.take().map(|info| {
+            self.add_object(info.into_bytes(), false)
+        });
 
         // Write out each object
         for (idx, obj) in self.objects.iter_mut().enumerate().skip(2) {
@@ -235,18 +495,21 @@ impl Pdf {
         out.write_all(b"1 0 obj\n<< /Type /Catalog\n/Pages 2 0 R >>\nendobj\n")?;
 
         // Write the cross-reference table
-        let startxref = out.pos() + 1; // NOTE: apparently there's some 1-based indexing??
+        let startxref = out.pos();
         out.write_all(b"xref\n")?;
         write!(out, "0 {}\n", self.objects.len() + 1)?;
         out.write_all(b"0000000000 65535 f \n")?;
 
         for obj in &self.objects {
-            write!(out, "{:010} 00000 f \n", obj.offset.unwrap())?;
+            write!(out, "{:010} 00000 n \n", obj.offset.unwrap())?;
         }
 
         // Write the document trailer
         out.write_all(b"trailer\n")?;
-        write!(out, "<< /Size {}\n", self.objects.len())?;
+        write!(out, "<< /Size {}\n", self.objects.len() + 1)?;
+        if let Some(info_object_id) = info_object_id {
+            write!(out, "/Info {} 0 R\n", info_object_id)?;
+        }
         out.write_all(b"/Root 1 0 R >>\n")?;
 
         // Write the offset to the xref table
@@ -258,3 +521,506 @@ impl Pdf {
         Ok(())
     }
 }
+
+// Writes the draw paths of a scene into the current page of a PDF document.
+pub(crate) struct PDFExporter<'a, 'b> {
+    scene: &'a Scene,
+    pdf: &'b mut Pdf,
+    render_target_ranges: HashMap<RenderTargetId, Vec<Range<DrawPathId>>>,
+    // The forms that render targets have been written to so far.
+    forms: HashMap<RenderTargetId, usize>,
+}
+
+impl<'a, 'b> PDFExporter<'a, 'b> {
+    pub(crate) fn new(scene: &'a Scene,
+                      pdf: &'b mut Pdf,
+                      render_target_ranges: HashMap<RenderTargetId, Vec<Range<DrawPathId>>>)
+                      -> PDFExporter<'a, 'b> {
+        PDFExporter { scene, pdf, render_target_ranges, forms: HashMap::new() }
+    }
+
+    pub(crate) fn write_draw_paths(&mut self, ranges: &[Range<DrawPathId>]) {
+        for range in ranges {
+            for draw_path_index in range.start.0..range.end.0 {
+                self.write_draw_path(self.scene.get_draw_path(DrawPathId(draw_path_index)));
+            }
+        }
+    }
+
+    fn write_draw_path(&mut self, draw_path: &DrawPath) {
+        let paint = self.scene.get_paint(draw_path.paint);
+        if paint.is_fully_transparent() {
+            return;
+        }
+
+        self.pdf.save_state();
+
+        let mut clip_path_id = draw_path.clip_path;
+        while let Some(id) = clip_path_id {
+            let clip_path = self.scene.get_clip_path(id);
+            write_pdf_outline(self.pdf, &clip_path.outline);
+            self.pdf.clip(clip_path.fill_rule);
+            clip_path_id = clip_path.clip_path;
+        }
+
+        let base_color = paint.base_color();
+        let alpha = base_color.a as f32 / 255.0;
+        let overlay_composite_op = paint.overlay().as_ref().map(|overlay| overlay.composite_op());
+        match (paint.gradient(), paint.pattern(), overlay_composite_op) {
+            (Some(gradient), _, Some(PaintCompositeOp::SrcIn)) => {
+                write_pdf_outline(self.pdf, &draw_path.outline);
+                self.pdf.clip(draw_path.fill_rule);
+                if !self.write_gradient(gradient, draw_path.outline.bounds(), alpha, draw_path) {
+                    // PDF has no conic gradients, so approximate them with their middle color.
+                    let mut color = gradient.sample(0.5).to_f32();
+                    color.set_a(color.a() * alpha);
+                    self.write_color_fill(draw_path, color.to_u8());
+                }
+            }
+            (_, Some(pattern), Some(PaintCompositeOp::SrcIn)) => {
+                write_pdf_outline(self.pdf, &draw_path.outline);
+                self.pdf.clip(draw_path.fill_rule);
+                self.pdf.set_graphics_state(alpha, draw_path.blend_mode, None);
+                self.write_pattern(pattern, draw_path.outline.bounds());
+            }
+            // Without an overlay, or with the overlay acting as a mask, the base color is what
+            // shows.
+            _ => self.write_color_fill(draw_path, base_color),
+        }
+
+        self.pdf.restore_state();
+    }
+
+    fn write_color_fill(&mut self, draw_path: &DrawPath, color: ColorU) {
+        self.pdf.set_graphics_state(color.a as f32 / 255.0, draw_path.blend_mode, None);
+        self.pdf.set_fill_color(color);
+        write_pdf_outline(self.pdf, &draw_path.outline);
+        self.pdf.fill(draw_path.fill_rule);
+    }
+
+    // Paints the gradient over the current clip. Returns false if PDF can't express it.
+    fn write_gradient(&mut self,
+                      gradient: &Gradient,
+                      bounds: RectF,
+                      alpha: f32,
+                      draw_path: &DrawPath)
+                      -> bool {
+        let transform = match gradient.geometry {
+            GradientGeometry::Radial { transform, .. } => transform,
+            GradientGeometry::Linear(_) | GradientGeometry::Conic { .. } => Transform2F::default(),
+        };
+        if transform.matrix.det() == 0.0 {
+            return true;
+        }
+
+        let coverage = transform.inverse() * bounds;
+        let shading = match self.pdf.add_shading(gradient, coverage, ShadingChannel::Color) {
+            None => return gradient.stops().is_empty(),
+            Some(shading) => shading,
+        };
+        let soft_mask = if gradient.is_opaque() {
+            None
+        } else {
+            self.pdf.add_shading(gradient, coverage, ShadingChannel::Alpha).map(|alpha_shading| {
+                self.pdf.add_soft_mask(alpha_shading, coverage)
+            })
+        };
+
+        self.pdf.transform(&transform);
+        self.pdf.set_graphics_state(alpha, draw_path.blend_mode, soft_mask);
+        self.pdf.paint_shading(shading);
+        true
+    }
+
+    // Paints the pattern over the current clip, tiling it over `bounds` if it repeats.
+    fn write_pattern(&mut self, pattern: &Pattern, bounds: RectF) {
+        let transform = pattern.transform();
+        if transform.matrix.det() == 0.0 {
+            return;
+        }
+
+        let size = pattern.size().to_f32();
+        let (xobject, xobject_transform) = match *pattern.source() {
+            PatternSource::Image(ref image) => {
+                // Image XObjects fill the unit square with their first row at the top, and the
+                // page is flipped.
+                let image = self.pdf.add_image(image, pattern.smoothing_enabled());
+                (image, Transform2F::row_major(size.x(), 0.0, 0.0, 0.0, -size.y(), size.y()))
+            }
+            PatternSource::RenderTarget { id, .. } => {
+                let form = match self.forms.get(&id) {
+                    Some(&form) => form,
+                    None => {
+                        self.pdf.begin_form();
+                        let ranges = self.render_target_ranges.get(&id).cloned();
+                        self.write_draw_paths(ranges.as_deref().unwrap_or(&[]));
+                        let form = self.pdf.end_form(size);
+                        self.forms.insert(id, form);
+                        form
+                    }
+                };
+                (form, Transform2F::default())
+            }
+        };
+
+        let (tiles_x, tiles_y) = crate::pattern_tiles(pattern, bounds);
+        for tile_y in tiles_y {
+            for tile_x in tiles_x.clone() {
+                let tile_origin = vec2f(tile_x as f32, tile_y as f32) * size;
+                self.pdf.save_state();
+                self.pdf.transform(&(transform *
+                                     Transform2F::from_translation(tile_origin) *
+                                     xobject_transform));
+                self.pdf.draw_xobject(xobject);
+                self.pdf.restore_state();
+            }
+        }
+    }
+}
+
+pub(crate) fn write_pdf_outline(pdf: &mut Pdf, outline: &Outline) {
+    for contour in outline.contours() {
+        for (segment_index, segment) in contour.iter(ContourIterFlags::empty()).enumerate() {
+            if segment_index == 0 {
+                pdf.move_to(segment.baseline.from());
+            }
+
+            match segment.kind {
+                SegmentKind::None => {}
+                SegmentKind::Line => pdf.line_to(segment.baseline.to()),
+                SegmentKind::Quadratic => {
+                    let current = segment.baseline.from();
+                    let c = segment.ctrl.from();
+                    let p = segment.baseline.to();
+                    let c1 = c * (2.0 / 3.0) + current * (1.0 / 3.0);
+                    let c2 = c * (2.0 / 3.0) + p * (1.0 / 3.0);
+                    pdf.cubic_to(c1, c2, p);
+                }
+                SegmentKind::Cubic => {
+                    pdf.cubic_to(segment.ctrl.from(), segment.ctrl.to(), segment.baseline.to())
+                }
+            }
+        }
+
+        if contour.is_closed() {
+            pdf.close();
+        }
+    }
+}
+
+/// Builds a shading dictionary that paints the given linear or radial gradient. PostScript
+/// accepts the same dictionary for `shfill`.
+///
+/// `coverage` is the area, in the gradient's own space, that the shading must paint correctly;
+/// repeating and reflecting gradients are unrolled until they cover it. Returns `None` for conic
+/// gradients, which PDF cannot express, and for gradients without any stops.
+pub(crate) fn shading_dictionary(gradient: &Gradient, coverage: RectF, channel: ShadingChannel)
+                                 -> Option<String> {
+    if gradient.stops().is_empty() {
+        return None;
+    }
+
+    let corners = [
+        coverage.origin(),
+        coverage.upper_right(),
+        coverage.lower_left(),
+        coverage.lower_right(),
+    ];
+    let color_space = match channel {
+        ShadingChannel::Color => "DeviceRGB",
+        ShadingChannel::Alpha => "DeviceGray",
+    };
+
+    let (shading_type, coords, range) = match gradient.geometry {
+        GradientGeometry::Linear(line) => {
+            let vector = line.vector();
+            let square_length = vector.square_length();
+            let range = if gradient.wrap == GradientWrap::Clamp || square_length == 0.0 {
+                (0, 1)
+            } else {
+                let times = corners.iter().map(|&corner| {
+                    (corner - line.from()).dot(vector) / square_length
+                });
+                let min_time = times.clone().fold(f32::INFINITY, f32::min);
+                let max_time = times.fold(f32::NEG_INFINITY, f32::max);
+                let start = f32::floor(min_time) as i32;
+                let end = (f32::ceil(max_time) as i32).max(start + 1);
+                (start, end.min(start + MAX_GRADIENT_REPETITIONS))
+            };
+            let (from, to) = (line.from() + vector * range.0 as f32,
+                              line.from() + vector * range.1 as f32);
+            (2, format!("{} {} {} {}", from.x(), from.y(), to.x(), to.y()), range)
+        }
+        GradientGeometry::Radial { line, radii, .. } => {
+            let circle_at = |time: f32| {
+                (line.from().lerp(line.to(), time), radii.x() + (radii.y() - radii.x()) * time)
+            };
+
+            // Radii can't be negative, so repetition only goes outward, until the outer
+            // circle encloses everything. If the circles form a cone instead, they never do, so
+            // stop once the radius is as far as the farthest corner.
+            let mut end = 1;
+            if gradient.wrap != GradientWrap::Clamp && radii.y() > radii.x() {
+                let is_cone = radii.y() - radii.x() <= line.vector().length();
+                let max_distance = corners.iter()
+                                          .map(|&corner| (corner - line.from()).length())
+                                          .fold(0.0, f32::max);
+                while end < MAX_GRADIENT_REPETITIONS {
+                    let (center, radius) = circle_at(end as f32);
+                    if corners.iter().all(|&corner| (corner - center).length() <= radius) ||
+                            (is_cone && radius >= max_distance) {
+                        break;
+                    }
+                    end += 1;
+                }
+            }
+            let (to, to_radius) = circle_at(end as f32);
+            let from = line.from();
+            (3,
+             format!("{} {} {} {} {} {}",
+                     from.x(), from.y(), radii.x(), to.x(), to.y(), to_radius),
+             (0, end))
+        }
+        GradientGeometry::Conic { .. } => return None,
+    };
+
+    Some(format!(
+        "<< /ShadingType {} /ColorSpace /{} /Coords [{}] /Domain [{} {}] /Function {} \
+         /Extend [true true] >>",
+        shading_type,
+        color_space,
+        coords,
+        range.0,
+        range.1,
+        stitching_function(gradient, range, channel)
+    ))
+}
+
+// Encodes a string as a PDF text string: a literal string if it's ASCII, and UTF-16 otherwise.
+fn text_string(string: &str) -> String {
+    if !string.is_ascii() {
+        let mut hex = String::from("<FEFF");
+        for unit in string.encode_utf16() {
+            hex.push_str(&format!("{:04X}", unit));
+        }
+        hex.push('>');
+        return hex;
+    }
+
+    let mut literal = String::from("(");
+    for character in string.chars() {
+        match character {
+            '(' | ')' | '\\' => {
+                literal.push('\\');
+                literal.push(character);
+            }
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            _ => literal.push(character),
+        }
+    }
+    literal.push(')');
+    literal
+}
+
+// Formats a time as a PDF date, in UTC. Returns `None` for times before 1970.
+fn date_string(time: SystemTime) -> Option<String> {
+    let seconds = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let (days, seconds_of_day) = ((seconds / 86400) as i64, seconds % 86400);
+
+    // Convert days since the epoch to a date in the proleptic Gregorian calendar. See Howard
+    // Hinnant's `civil_from_days()`.
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) /
+        365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    Some(format!("D:{:04}{:02}{:02}{:02}{:02}{:02}Z",
+                 year,
+                 month,
+                 day,
+                 seconds_of_day / 3600,
+                 seconds_of_day / 60 % 60,
+                 seconds_of_day % 60))
+}
+
+fn resource_dictionary(resources: &[(ResourceKind, usize)]) -> String {
+    let mut dictionary = String::from("<<");
+    for &(kind, category, prefix) in &[
+        (ResourceKind::ExtGState, "ExtGState", "GS"),
+        (ResourceKind::Shading, "Shading", "Sh"),
+        (ResourceKind::XObject, "XObject", "X"),
+    ] {
+        let object_ids: Vec<usize> = resources.iter()
+                                              .filter(|&&(other_kind, _)| other_kind == kind)
+                                              .map(|&(_, object_id)| object_id)
+                                              .collect();
+        if object_ids.is_empty() {
+            continue;
+        }
+        dictionary.push_str(&format!(" /{} <<", category));
+        for object_id in object_ids {
+            dictionary.push_str(&format!(" /{}{} {} 0 R", prefix, object_id, object_id));
+        }
+        dictionary.push_str(" >>");
+    }
+    dictionary.push_str(" >>");
+    dictionary
+}
+
+// Builds a function that maps times in `range` to the gradient's colors, unrolling repetitions.
+fn stitching_function(gradient: &Gradient, range: (i32, i32), channel: ShadingChannel) -> String {
+    // Make sure the stops span the whole gradient, as PDF functions don't extend themselves.
+    let mut stops = gradient.stops().to_vec();
+    let (first, last) = (stops[0], stops[stops.len() - 1]);
+    if first.offset > 0.0 {
+        stops.insert(0, ColorStop::new(first.color, 0.0));
+    }
+    if last.offset < 1.0 {
+        stops.push(ColorStop::new(last.color, 1.0));
+    }
+
+    let components = |color: ColorU| {
+        let color = color.to_f32();
+        match channel {
+            ShadingChannel::Color => format!("{} {} {}", color.r(), color.g(), color.b()),
+            ShadingChannel::Alpha => format!("{}", color.a()),
+        }
+    };
+
+    let (mut functions, mut bounds, mut encode) = (String::new(), String::new(), String::new());
+    for repetition in range.0..range.1 {
+        let reflected = gradient.wrap == GradientWrap::Reflect && repetition.rem_euclid(2) == 1;
+        let positioned: Vec<(f32, ColorU)> = if reflected {
+            stops.iter().rev().map(|stop| {
+                (repetition as f32 + 1.0 - stop.offset.clamp(0.0, 1.0), stop.color)
+            }).collect()
+        } else {
+            stops.iter().map(|stop| {
+                (repetition as f32 + stop.offset.clamp(0.0, 1.0), stop.color)
+            }).collect()
+        };
+
+        for pair in positioned.windows(2) {
+            let ((from_time, from_color), (to_time, to_color)) = (pair[0], pair[1]);
+            if to_time <= from_time {
+                continue;
+            }
+            if !functions.is_empty() {
+                bounds.push_str(&format!("{} ", from_time));
+            }
+            functions.push_str(&format!("<< /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] \
+                                         /N 1 >> ",
+                                        components(from_color),
+                                        components(to_color)));
+            encode.push_str("0 1 ");
+        }
+    }
+
+    // If every stop is at the same offset, the gradient is a solid color.
+    if functions.is_empty() {
+        let color = components(last.color);
+        functions = format!("<< /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >> ",
+                            color,
+                            color);
+        encode.push_str("0 1 ");
+    }
+
+    format!("<< /FunctionType 3 /Domain [{} {}] /Functions [{}] /Bounds [{}] /Encode [{}] >>",
+            range.0,
+            range.1,
+            functions.trim_end(),
+            bounds.trim_end(),
+            encode.trim_end())
+}
+
+fn blend_mode_name(blend_mode: BlendMode) -> &'static str {
+    match blend_mode {
+        BlendMode::Multiply => "Multiply",
+        BlendMode::Screen => "Screen",
+        BlendMode::Overlay => "Overlay",
+        BlendMode::Darken => "Darken",
+        BlendMode::Lighten => "Lighten",
+        BlendMode::ColorDodge => "ColorDodge",
+        BlendMode::ColorBurn => "ColorBurn",
+        BlendMode::HardLight => "HardLight",
+        BlendMode::SoftLight => "SoftLight",
+        BlendMode::Difference => "Difference",
+        BlendMode::Exclusion => "Exclusion",
+        BlendMode::Hue => "Hue",
+        BlendMode::Saturation => "Saturation",
+        BlendMode::Color => "Color",
+        BlendMode::Luminosity => "Luminosity",
+        // PDF has no Porter-Duff operators other than source-over.
+        BlendMode::SrcOver |
+        BlendMode::Clear |
+        BlendMode::Copy |
+        BlendMode::SrcIn |
+        BlendMode::SrcOut |
+        BlendMode::SrcAtop |
+        BlendMode::DestIn |
+        BlendMode::DestOut |
+        BlendMode::DestOver |
+        BlendMode::DestAtop |
+        BlendMode::Xor |
+        BlendMode::Lighter => "Normal",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ResourceKind, ShadingChannel, date_string, resource_dictionary};
+    use super::{stitching_function, text_string};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::gradient::{Gradient, GradientWrap};
+    use pathfinder_geometry::vector::{Vector2F, vec2f};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn info_strings() {
+        assert_eq!(text_string("Q3 (draft)\\"), "(Q3 \\(draft\\)\\\\)");
+        assert_eq!(text_string("Größe"), "<FEFF0047007200F600DF0065>");
+        assert_eq!(date_string(UNIX_EPOCH).unwrap(), "D:19700101000000Z");
+        assert_eq!(date_string(UNIX_EPOCH + Duration::from_secs(951_827_696)).unwrap(),
+                   "D:20000229123456Z");
+    }
+
+    #[test]
+    fn resources() {
+        assert_eq!(resource_dictionary(&[]), "<< >>");
+        let resources = [
+            (ResourceKind::XObject, 4),
+            (ResourceKind::ExtGState, 3),
+            (ResourceKind::XObject, 5),
+        ];
+        assert_eq!(resource_dictionary(&resources),
+                   "<< /ExtGState << /GS3 3 0 R >> /XObject << /X4 4 0 R /X5 5 0 R >> >>");
+    }
+
+    #[test]
+    fn gradient_functions() {
+        let mut gradient = Gradient::linear_from_points(Vector2F::zero(), vec2f(1.0, 0.0));
+        gradient.add_color_stop(ColorU::black(), 0.5);
+        gradient.add_color_stop(ColorU::white(), 1.0);
+        assert_eq!(stitching_function(&gradient, (0, 1), ShadingChannel::Alpha),
+                   "<< /FunctionType 3 /Domain [0 1] /Functions [\
+                    << /FunctionType 2 /Domain [0 1] /C0 [1] /C1 [1] /N 1 >> \
+                    << /FunctionType 2 /Domain [0 1] /C0 [1] /C1 [1] /N 1 >>] \
+                    /Bounds [0.5] /Encode [0 1 0 1] >>");
+
+        gradient.wrap = GradientWrap::Reflect;
+        assert_eq!(stitching_function(&gradient, (0, 2), ShadingChannel::Color),
+                   "<< /FunctionType 3 /Domain [0 2] /Functions [\
+                    << /FunctionType 2 /Domain [0 1] /C0 [0 0 0] /C1 [0 0 0] /N 1 >> \
+                    << /FunctionType 2 /Domain [0 1] /C0 [0 0 0] /C1 [1 1 1] /N 1 >> \
+                    << /FunctionType 2 /Domain [0 1] /C0 [1 1 1] /C1 [0 0 0] /N 1 >> \
+                    << /FunctionType 2 /Domain [0 1] /C0 [0 0 0] /C1 [0 0 0] /N 1 >>] \
+                    /Bounds [0.5 1 1.5] /Encode [0 1 0 1 0 1 0 1] >>");
+    }
+}
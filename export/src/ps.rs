@@ -0,0 +1,396 @@
+// pathfinder/export/src/ps.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Exports scenes to Encapsulated PostScript.
+//!
+//! Gradients need PostScript level 3 for `shfill`, and images with transparent pixels need it for
+//! masked images. PostScript has no transparency otherwise, so translucent paints are drawn
+//! opaque, blend modes are ignored, and only fully transparent paths are skipped.
+//! Conic gradients are approximated by the color halfway through the gradient, as in PDF.
+
+use crate::pdf::{self, ShadingChannel};
+use pathfinder_color::ColorU;
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::gradient::{Gradient, GradientGeometry};
+use pathfinder_content::outline::{ContourIterFlags, Outline};
+use pathfinder_content::pattern::{Image, Pattern, PatternSource};
+use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_content::segment::SegmentKind;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use pathfinder_renderer::paint::PaintCompositeOp;
+use pathfinder_renderer::scene::{DrawPath, DrawPathId, Scene};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Range;
+
+// How many bytes of image data go on each line of hexadecimal.
+const IMAGE_BYTES_PER_LINE: usize = 32;
+
+pub(crate) fn export_ps<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
+    let view_box = scene.view_box();
+    writeln!(writer, "%!PS-Adobe-3.0 EPSF-3.0")?;
+    writeln!(writer, "%%BoundingBox: 0 0 {} {}",
+        f32::ceil(view_box.width()),
+        f32::ceil(view_box.height()),
+    )?;
+    writeln!(writer, "%%HiResBoundingBox: 0 0 {} {}", view_box.width(), view_box.height())?;
+    writeln!(writer, "%%LanguageLevel: 3")?;
+    writeln!(writer, "%%EndComments")?;
+
+    // PostScript's Y axis points up, so flip the page to put the origin of the view box at the top
+    // left.
+    writeln!(writer, "0 {} translate", view_box.height())?;
+    writeln!(writer, "1 -1 scale")?;
+    writeln!(writer, "{} translate", P(-view_box.origin()))?;
+
+    let ranges = scene.draw_path_ranges();
+    let exporter = PSExporter { scene, render_target_ranges: ranges.render_targets };
+    exporter.write_draw_paths(writer, &ranges.main)?;
+
+    writeln!(writer, "showpage")?;
+    writeln!(writer, "%%EOF")?;
+    Ok(())
+}
+
+struct PSExporter<'a> {
+    scene: &'a Scene,
+    render_target_ranges: HashMap<RenderTargetId, Vec<Range<DrawPathId>>>,
+}
+
+impl<'a> PSExporter<'a> {
+    fn write_draw_paths<W: Write>(&self, writer: &mut W, ranges: &[Range<DrawPathId>])
+                                  -> io::Result<()> {
+        for range in ranges {
+            for draw_path_index in range.start.0..range.end.0 {
+                let draw_path = self.scene.get_draw_path(DrawPathId(draw_path_index));
+                self.write_draw_path(writer, draw_path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_draw_path<W: Write>(&self, writer: &mut W, draw_path: &DrawPath) -> io::Result<()> {
+        let paint = self.scene.get_paint(draw_path.paint);
+        if paint.is_fully_transparent() {
+            return Ok(());
+        }
+
+        // A line break in the name would end the comment, so control characters are dropped.
+        let name: String = draw_path.name.chars().filter(|c| !c.is_control()).collect();
+        if !name.is_empty() {
+            writeln!(writer, "gsave % {}", name)?;
+        } else {
+            writeln!(writer, "gsave")?;
+        }
+
+        let mut clip_path_id = draw_path.clip_path;
+        while let Some(id) = clip_path_id {
+            let clip_path = self.scene.get_clip_path(id);
+            write_clip(writer, &clip_path.outline, clip_path.fill_rule)?;
+            clip_path_id = clip_path.clip_path;
+        }
+
+        let overlay_composite_op = paint.overlay().as_ref().map(|overlay| overlay.composite_op());
+        match (paint.gradient(), paint.pattern(), overlay_composite_op) {
+            (Some(gradient), _, Some(PaintCompositeOp::SrcIn)) => {
+                write_clip(writer, &draw_path.outline, draw_path.fill_rule)?;
+                if !write_gradient(writer, gradient, draw_path.outline.bounds())? {
+                    write_color_fill(writer, draw_path, gradient.sample(0.5))?;
+                }
+            }
+            (_, Some(pattern), Some(PaintCompositeOp::SrcIn)) => {
+                write_clip(writer, &draw_path.outline, draw_path.fill_rule)?;
+                self.write_pattern(writer, pattern, draw_path.outline.bounds())?;
+            }
+            // Without an overlay, or with the overlay acting as a mask, the base color is what
+            // shows.
+            _ => write_color_fill(writer, draw_path, paint.base_color())?,
+        }
+
+        writeln!(writer, "grestore")?;
+        Ok(())
+    }
+
+    // Paints the pattern over the current clip, tiling it over `bounds` if it repeats.
+    fn write_pattern<W: Write>(&self, writer: &mut W, pattern: &Pattern, bounds: RectF)
+                               -> io::Result<()> {
+        let transform = pattern.transform();
+        if transform.matrix.det() == 0.0 {
+            return Ok(());
+        }
+
+        let size = pattern.size().to_f32();
+        let (tiles_x, tiles_y) = crate::pattern_tiles(pattern, bounds);
+        for tile_y in tiles_y {
+            for tile_x in tiles_x.clone() {
+                let tile_origin = vec2f(tile_x as f32, tile_y as f32) * size;
+                writeln!(writer, "gsave")?;
+                write_concat(writer, &(transform * Transform2F::from_translation(tile_origin)))?;
+                match *pattern.source() {
+                    PatternSource::Image(ref image) => {
+                        write_image(writer, image, pattern.smoothing_enabled())?;
+                    }
+                    PatternSource::RenderTarget { id, .. } => {
+                        writeln!(writer, "0 0 {} rectclip", P(size))?;
+                        if let Some(ranges) = self.render_target_ranges.get(&id) {
+                            self.write_draw_paths(writer, ranges)?;
+                        }
+                    }
+                }
+                writeln!(writer, "grestore")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_color_fill<W: Write>(writer: &mut W, draw_path: &DrawPath, color: ColorU)
+                              -> io::Result<()> {
+    let color = color.to_f32();
+    writeln!(writer, "{} {} {} setrgbcolor", color.r(), color.g(), color.b())?;
+    write_outline(writer, &draw_path.outline)?;
+    match draw_path.fill_rule {
+        FillRule::Winding => writeln!(writer, "fill"),
+        FillRule::EvenOdd => writeln!(writer, "eofill"),
+    }
+}
+
+fn write_clip<W: Write>(writer: &mut W, outline: &Outline, fill_rule: FillRule)
+                        -> io::Result<()> {
+    write_outline(writer, outline)?;
+    match fill_rule {
+        FillRule::Winding => writeln!(writer, "clip newpath"),
+        FillRule::EvenOdd => writeln!(writer, "eoclip newpath"),
+    }
+}
+
+// Paints the gradient over the current clip. Returns false if PostScript can't express it.
+fn write_gradient<W: Write>(writer: &mut W, gradient: &Gradient, bounds: RectF)
+                            -> io::Result<bool> {
+    let transform = match gradient.geometry {
+        GradientGeometry::Radial { transform, .. } => transform,
+        GradientGeometry::Linear(_) | GradientGeometry::Conic { .. } => Transform2F::default(),
+    };
+    if transform.matrix.det() == 0.0 {
+        return Ok(true);
+    }
+
+    let coverage = transform.inverse() * bounds;
+    let shading = match pdf::shading_dictionary(gradient, coverage, ShadingChannel::Color) {
+        None => return Ok(gradient.stops().is_empty()),
+        Some(shading) => shading,
+    };
+    write_concat(writer, &transform)?;
+    writeln!(writer, "{} shfill", shading)?;
+    Ok(true)
+}
+
+// Draws the image with its pixels in the unit squares from the origin to its size.
+//
+// Images with transparent pixels are drawn as an ImageType 3 masked image. PostScript masks are
+// one bit deep, so pixels are either painted or not depending on whether their alpha is at least
+// half.
+fn write_image<W: Write>(writer: &mut W, image: &Image, interpolate: bool) -> io::Result<()> {
+    let size = image.size();
+    let geometry = format!("/ImageType 1 /Width {} /Height {} /ImageMatrix [1 0 0 1 0 0]",
+                           size.x(),
+                           size.y());
+    let data_dict = format!("<< {} /BitsPerComponent 8 /Decode [0 1 0 1 0 1] /Interpolate {} \
+                             /DataSource currentfile /ASCIIHexDecode filter >>",
+                            geometry,
+                            interpolate);
+
+    writeln!(writer, "/DeviceRGB setcolorspace")?;
+    if image.is_opaque() {
+        writeln!(writer, "{} image", data_dict)?;
+    } else {
+        // The mask's data source comes first in the file, so it's read before the image data.
+        writeln!(writer, "<< /ImageType 3 /InterleaveType 3")?;
+        writeln!(writer,
+                 "/MaskDict << {} /BitsPerComponent 1 /Decode [1 0] \
+                  /DataSource currentfile /ASCIIHexDecode filter >>",
+                 geometry)?;
+        writeln!(writer, "/DataDict {}", data_dict)?;
+        writeln!(writer, ">> image")?;
+        write_hex_data(writer, &image_mask_data(image))?;
+    }
+
+    let mut color_data = Vec::with_capacity(image.pixels().len() * 3);
+    for pixel in image.pixels().iter() {
+        color_data.extend_from_slice(&[pixel.r, pixel.g, pixel.b]);
+    }
+    write_hex_data(writer, &color_data)
+}
+
+// Packs the alpha channel of the image into a mask with one bit per pixel, set where the pixel is
+// painted. Each row starts on a byte boundary.
+fn image_mask_data(image: &Image) -> Vec<u8> {
+    let width = image.size().x() as usize;
+    let mut mask_data = vec![];
+    for row in image.pixels().chunks(width.max(1)) {
+        for pixels in row.chunks(8) {
+            let mut byte = 0;
+            for (pixel_index, pixel) in pixels.iter().enumerate() {
+                if pixel.a >= 128 {
+                    byte |= 0x80 >> pixel_index;
+                }
+            }
+            mask_data.push(byte);
+        }
+    }
+    mask_data
+}
+
+// Writes the data in hexadecimal, terminated with the end-of-data marker of `ASCIIHexDecode`.
+fn write_hex_data<W: Write>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+    for chunk in data.chunks(IMAGE_BYTES_PER_LINE) {
+        for byte in chunk {
+            write!(writer, "{:02x}", byte)?;
+        }
+        writeln!(writer)?;
+    }
+    writeln!(writer, ">")
+}
+
+fn write_concat<W: Write>(writer: &mut W, transform: &Transform2F) -> io::Result<()> {
+    if transform.is_identity() {
+        return Ok(());
+    }
+    writeln!(writer, "[{} {} {} {} {} {}] concat",
+        transform.m11(),
+        transform.m21(),
+        transform.m12(),
+        transform.m22(),
+        transform.m13(),
+        transform.m23(),
+    )
+}
+
+fn write_outline<W: Write>(writer: &mut W, outline: &Outline) -> io::Result<()> {
+    for contour in outline.contours() {
+        for (segment_index, segment) in contour.iter(ContourIterFlags::empty()).enumerate() {
+            if segment_index == 0 {
+                writeln!(writer, "{} moveto", P(segment.baseline.from()))?;
+            }
+
+            match segment.kind {
+                SegmentKind::None => {}
+                SegmentKind::Line => {
+                    writeln!(writer, "{} lineto", P(segment.baseline.to()))?;
+                }
+                SegmentKind::Quadratic => {
+                    let current = segment.baseline.from();
+                    let c = segment.ctrl.from();
+                    let p = segment.baseline.to();
+                    let c1 = c * (2.0 / 3.0) + current * (1.0 / 3.0);
+                    let c2 = c * (2.0 / 3.0) + p * (1.0 / 3.0);
+                    writeln!(writer, "{} {} {} curveto", P(c1), P(c2), P(p))?;
+                }
+                SegmentKind::Cubic => {
+                    writeln!(writer, "{} {} {} curveto",
+                        P(segment.ctrl.from()),
+                        P(segment.ctrl.to()),
+                        P(segment.baseline.to())
+                    )?;
+                }
+            }
+        }
+
+        if contour.is_closed() {
+            writeln!(writer, "closepath")?;
+        }
+    }
+    Ok(())
+}
+
+struct P(Vector2F);
+
+impl fmt::Display for P {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.0.x(), self.0.y())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Export, FileFormat};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::fill::FillRule;
+    use pathfinder_content::gradient::Gradient;
+    use pathfinder_content::outline::Outline;
+    use pathfinder_content::pattern::{Image, Pattern};
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::{Vector2F, vec2f, vec2i};
+    use pathfinder_renderer::paint::Paint;
+    use pathfinder_renderer::scene::{ClipPath, DrawPath, Scene};
+    use std::sync::Arc;
+
+    #[test]
+    fn export_eps() {
+        let rect = Outline::from_rect(RectF::new(Vector2F::zero(), vec2f(10.0, 10.0)));
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(vec2f(5.0, 5.0), vec2f(20.5, 10.0)));
+
+        let mut clip_path = ClipPath::new(rect.clone());
+        clip_path.set_fill_rule(FillRule::EvenOdd);
+        let clip_path = scene.push_clip_path(clip_path);
+        let color = scene.push_paint(&Paint::from_color(ColorU::new(255, 0, 0, 255)));
+        let mut draw_path = DrawPath::new(rect.clone(), color);
+        draw_path.set_clip_path(Some(clip_path));
+        draw_path.set_fill_rule(FillRule::EvenOdd);
+        scene.push_draw_path(draw_path);
+
+        let mut gradient = Gradient::linear_from_points(Vector2F::zero(), vec2f(10.0, 0.0));
+        gradient.add_color_stop(ColorU::black(), 0.0);
+        gradient.add_color_stop(ColorU::white(), 1.0);
+        let gradient = scene.push_paint(&Paint::from_gradient(gradient));
+        scene.push_draw_path(DrawPath::new(rect, gradient));
+
+        let mut output = vec![];
+        scene.export(&mut output, FileFormat::PS).unwrap();
+        let ps = String::from_utf8(output).unwrap();
+        assert!(ps.contains("%%BoundingBox: 0 0 21 10\n"));
+        assert!(ps.contains("%%HiResBoundingBox: 0 0 20.5 10\n"));
+        assert!(ps.contains("0 10 translate\n1 -1 scale\n-5 -5 translate\n"));
+        assert!(ps.contains("closepath\neoclip newpath\n1 0 0 setrgbcolor\n"));
+        assert!(ps.contains("closepath\neofill\n"));
+        assert!(ps.contains("closepath\nclip newpath\n<< /ShadingType 2 /ColorSpace /DeviceRGB \
+                             /Coords [0 0 10 0] /Domain [0 1]"));
+        assert!(ps.contains(">> shfill\n"));
+    }
+
+    #[test]
+    fn export_eps_masked_image() {
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::zero(), vec2f(10.0, 1.0)));
+        let mut pixels = vec![ColorU::new(255, 0, 0, 255); 10];
+        pixels[1] = ColorU::transparent_black();
+        pixels[9] = ColorU::new(255, 0, 0, 64);
+        let image = Image::new(vec2i(10, 1), Arc::new(pixels));
+        let pattern = scene.push_paint(&Paint::from_pattern(Pattern::from_image(image)));
+        let rect = Outline::from_rect(RectF::new(Vector2F::zero(), vec2f(10.0, 1.0)));
+        let mut draw_path = DrawPath::new(rect, pattern);
+        draw_path.set_name("image\n% not a comment".to_owned());
+        scene.push_draw_path(draw_path);
+
+        let mut output = vec![];
+        scene.export(&mut output, FileFormat::PS).unwrap();
+        let ps = String::from_utf8(output).unwrap();
+        assert!(ps.contains("gsave % image% not a comment\n"), "{}", ps);
+        assert!(ps.contains("<< /ImageType 3 /InterleaveType 3\n/MaskDict << /ImageType 1 \
+                             /Width 10 /Height 1 /ImageMatrix [1 0 0 1 0 0] /BitsPerComponent 1 \
+                             /Decode [1 0]"), "{}", ps);
+        assert!(ps.contains("/DataDict << /ImageType 1 /Width 10 /Height 1"), "{}", ps);
+        assert!(ps.contains(">> image\nbf80\n>\nff0000000000ff0000"), "{}", ps);
+    }
+}
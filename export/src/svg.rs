@@ -0,0 +1,535 @@
+// pathfinder/export/src/svg.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Exports scenes to SVG.
+//!
+//! Gradients, image patterns, and render target patterns become `<defs>` that the paths refer to,
+//! and clip paths become `<clipPath>` elements. SVG has no conic gradients, so those are
+//! approximated by the color halfway through the gradient. Porter-Duff blend modes other than
+//! source-over have no CSS equivalent and are dropped.
+
+use image::png::PngEncoder;
+use image::ColorType;
+use pathfinder_color::{self as color, ColorU};
+use pathfinder_content::effects::BlendMode;
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::gradient::{Gradient, GradientColorSpace, GradientGeometry, GradientWrap};
+use pathfinder_content::pattern::{Image, Pattern, PatternSource};
+use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2F;
+use pathfinder_renderer::paint::{Paint, PaintCompositeOp, PaintId};
+use pathfinder_renderer::scene::{ClipPathId, DrawPathId, Scene};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+use std::ops::Range;
+
+pub(crate) fn export_svg<W: Write>(scene: &Scene, writer: &mut W) -> io::Result<()> {
+    let exporter = SVGExporter::new(scene);
+    exporter.export(writer)
+}
+
+struct SVGExporter<'a> {
+    scene: &'a Scene,
+    main_ranges: Vec<Range<DrawPathId>>,
+    render_target_ranges: HashMap<RenderTargetId, Vec<Range<DrawPathId>>>,
+}
+
+impl<'a> SVGExporter<'a> {
+    fn new(scene: &'a Scene) -> SVGExporter<'a> {
+        let ranges = scene.draw_path_ranges();
+        SVGExporter {
+            scene,
+            main_ranges: ranges.main,
+            render_target_ranges: ranges.render_targets,
+        }
+    }
+
+    fn export<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let view_box = self.scene.view_box();
+        writeln!(writer,
+                 "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+                  xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+                  width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">",
+                 view_box.width(),
+                 view_box.height(),
+                 view_box.origin().x(),
+                 view_box.origin().y(),
+                 view_box.width(),
+                 view_box.height())?;
+
+        // Every draw path appears in the display list exactly once, so gather the definitions
+        // they refer to up front.
+        let mut paint_ids = BTreeSet::new();
+        let mut clip_path_ids = BTreeSet::new();
+        for draw_path_index in 0..self.scene.draw_path_count() {
+            let draw_path = self.scene.get_draw_path(DrawPathId(draw_path_index));
+            paint_ids.insert(draw_path.paint.0);
+            let mut clip_path_id = draw_path.clip_path;
+            while let Some(id) = clip_path_id {
+                if !clip_path_ids.insert(id.0) {
+                    break;
+                }
+                clip_path_id = self.scene.get_clip_path(id).clip_path;
+            }
+        }
+
+        writeln!(writer, "    <defs>")?;
+        for &clip_path_index in &clip_path_ids {
+            self.write_clip_path(writer, ClipPathId(clip_path_index))?;
+        }
+        for &paint_index in &paint_ids {
+            let paint_id = PaintId(paint_index);
+            let paint = self.scene.get_paint(paint_id);
+            if let Some(gradient) = paint.gradient() {
+                write_gradient(writer, paint_id, gradient)?;
+            } else if let Some(pattern) = paint.pattern() {
+                self.write_pattern(writer, paint_id, pattern)?;
+            }
+        }
+        writeln!(writer, "    </defs>")?;
+
+        self.write_draw_paths(writer, &self.main_ranges, 1)?;
+        writeln!(writer, "</svg>")?;
+        Ok(())
+    }
+
+    fn write_draw_paths<W: Write>(&self,
+                                  writer: &mut W,
+                                  ranges: &[Range<DrawPathId>],
+                                  depth: usize)
+                                  -> io::Result<()> {
+        for range in ranges {
+            for draw_path_index in range.start.0..range.end.0 {
+                let draw_path = self.scene.get_draw_path(DrawPathId(draw_path_index));
+                write!(writer, "{}<path", Indent(depth))?;
+                if !draw_path.name.is_empty() {
+                    write!(writer, " id=\"{}\"", Escaped(&draw_path.name))?;
+                }
+                write_fill(writer, draw_path.paint, self.scene.get_paint(draw_path.paint))?;
+                if draw_path.fill_rule == FillRule::EvenOdd {
+                    write!(writer, " fill-rule=\"evenodd\"")?;
+                }
+                if let Some(clip_path_id) = draw_path.clip_path {
+                    write!(writer, " clip-path=\"url(#clip{})\"", clip_path_id.0)?;
+                }
+                if let Some(blend_mode) = css_blend_mode(draw_path.blend_mode) {
+                    write!(writer, " style=\"mix-blend-mode: {}\"", blend_mode)?;
+                }
+                writeln!(writer, " d=\"{}\"/>", draw_path.outline.to_svg_path_data())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_clip_path<W: Write>(&self, writer: &mut W, clip_path_id: ClipPathId)
+                                 -> io::Result<()> {
+        let clip_path = self.scene.get_clip_path(clip_path_id);
+        write!(writer, "        <clipPath id=\"clip{}\" clipPathUnits=\"userSpaceOnUse\"",
+               clip_path_id.0)?;
+        if let Some(parent_clip_path_id) = clip_path.clip_path {
+            write!(writer, " clip-path=\"url(#clip{})\"", parent_clip_path_id.0)?;
+        }
+        writeln!(writer, ">")?;
+        write!(writer, "            <path")?;
+        if clip_path.fill_rule == FillRule::EvenOdd {
+            write!(writer, " clip-rule=\"evenodd\"")?;
+        }
+        writeln!(writer, " d=\"{}\"/>", clip_path.outline.to_svg_path_data())?;
+        writeln!(writer, "        </clipPath>")?;
+        Ok(())
+    }
+
+    fn write_pattern<W: Write>(&self, writer: &mut W, paint_id: PaintId, pattern: &Pattern)
+                               -> io::Result<()> {
+        // SVG patterns always repeat. To keep a non-repeating pattern from repeating, stretch the
+        // tile along that axis until it covers everything in the scene.
+        let size = pattern.size().to_f32();
+        let transform = pattern.transform();
+        let (mut tile_origin, mut tile_lower_right) = (Vector2F::zero(), size);
+        if transform.matrix.det() != 0.0 {
+            let scene_rect = self.scene.bounds().union_rect(self.scene.view_box());
+            let covered = transform.inverse() * scene_rect;
+            if !pattern.repeat_x() {
+                tile_origin.set_x(f32::min(0.0, covered.min_x()));
+                tile_lower_right.set_x(f32::max(size.x(), covered.max_x()));
+            }
+            if !pattern.repeat_y() {
+                tile_origin.set_y(f32::min(0.0, covered.min_y()));
+                tile_lower_right.set_y(f32::max(size.y(), covered.max_y()));
+            }
+        }
+        let tile = RectF::from_points(tile_origin, tile_lower_right);
+
+        writeln!(writer,
+                 "        <pattern id=\"paint{}\" patternUnits=\"userSpaceOnUse\" \
+                  x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" patternTransform=\"{}\">",
+                 paint_id.0,
+                 tile.origin().x(),
+                 tile.origin().y(),
+                 tile.width(),
+                 tile.height(),
+                 Matrix(transform))?;
+
+        // Pattern content is positioned relative to the tile's origin, so move it back to where
+        // it belongs when the tile has been stretched.
+        let mut depth = 3;
+        if tile_origin != Vector2F::zero() {
+            writeln!(writer,
+                     "{}<g transform=\"translate({} {})\">",
+                     Indent(depth),
+                     -tile_origin.x(),
+                     -tile_origin.y())?;
+            depth += 1;
+        }
+        match *pattern.source() {
+            PatternSource::Image(ref image) => {
+                write!(writer,
+                       "{}<image width=\"{}\" height=\"{}\"",
+                       Indent(depth),
+                       size.x(),
+                       size.y())?;
+                if !pattern.smoothing_enabled() {
+                    write!(writer, " style=\"image-rendering: pixelated\"")?;
+                }
+                writeln!(writer, " xlink:href=\"data:image/png;base64,{}\"/>",
+                         encode_base64(&encode_png(image)?))?;
+            }
+            PatternSource::RenderTarget { id, .. } => {
+                // A nested `<svg>` clips its contents to the render target.
+                writeln!(writer,
+                         "{}<svg width=\"{}\" height=\"{}\">",
+                         Indent(depth),
+                         size.x(),
+                         size.y())?;
+                if let Some(ranges) = self.render_target_ranges.get(&id) {
+                    self.write_draw_paths(writer, ranges, depth + 1)?;
+                }
+                writeln!(writer, "{}</svg>", Indent(depth))?;
+            }
+        }
+        if depth > 3 {
+            writeln!(writer, "            </g>")?;
+        }
+        writeln!(writer, "        </pattern>")?;
+        Ok(())
+    }
+}
+
+fn write_gradient<W: Write>(writer: &mut W, paint_id: PaintId, gradient: &Gradient)
+                            -> io::Result<()> {
+    let element = match gradient.geometry {
+        GradientGeometry::Linear(line) => {
+            write!(writer,
+                   "        <linearGradient id=\"paint{}\" gradientUnits=\"userSpaceOnUse\" \
+                    x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"",
+                   paint_id.0,
+                   line.from_x(),
+                   line.from_y(),
+                   line.to_x(),
+                   line.to_y())?;
+            "linearGradient"
+        }
+        GradientGeometry::Radial { line, radii, transform } => {
+            write!(writer,
+                   "        <radialGradient id=\"paint{}\" gradientUnits=\"userSpaceOnUse\" \
+                    fx=\"{}\" fy=\"{}\" fr=\"{}\" cx=\"{}\" cy=\"{}\" r=\"{}\"",
+                   paint_id.0,
+                   line.from_x(),
+                   line.from_y(),
+                   radii.x(),
+                   line.to_x(),
+                   line.to_y(),
+                   radii.y())?;
+            if !transform.is_identity() {
+                write!(writer, " gradientTransform=\"{}\"", Matrix(transform))?;
+            }
+            "radialGradient"
+        }
+        // Conic gradients are filled with a solid color instead; see `write_fill()`.
+        GradientGeometry::Conic { .. } => return Ok(()),
+    };
+
+    match gradient.wrap {
+        GradientWrap::Clamp => {}
+        GradientWrap::Repeat => write!(writer, " spreadMethod=\"repeat\"")?,
+        GradientWrap::Reflect => write!(writer, " spreadMethod=\"reflect\"")?,
+    }
+    if gradient.interpolation.color_space == GradientColorSpace::LinearSRGB {
+        write!(writer, " color-interpolation=\"linearRGB\"")?;
+    }
+    writeln!(writer, ">")?;
+
+    for stop in gradient.stops() {
+        write!(writer, "            <stop offset=\"{}\" stop-color=\"{}\"",
+               stop.offset,
+               Hex(stop.color))?;
+        if !stop.color.is_opaque() {
+            write!(writer, " stop-opacity=\"{}\"", Opacity(stop.color))?;
+        }
+        writeln!(writer, "/>")?;
+    }
+    writeln!(writer, "        </{}>", element)?;
+    Ok(())
+}
+
+fn write_fill<W: Write>(writer: &mut W, paint_id: PaintId, paint: &Paint) -> io::Result<()> {
+    let base_color = paint.base_color();
+    let overlay_composite_op = paint.overlay().as_ref().map(|overlay| overlay.composite_op());
+    match (paint.gradient(), overlay_composite_op) {
+        (Some(gradient), Some(PaintCompositeOp::SrcIn)) => {
+            if let GradientGeometry::Conic { .. } = gradient.geometry {
+                let mut color = gradient.sample(0.5).to_f32();
+                color.set_a(color.a() * base_color.to_f32().a());
+                return write_color_fill(writer, color.to_u8());
+            }
+        }
+        (None, Some(PaintCompositeOp::SrcIn)) => {}
+        // Without an overlay, or with the overlay acting as a mask, the base color is what shows.
+        _ => return write_color_fill(writer, base_color),
+    }
+
+    write!(writer, " fill=\"url(#paint{})\"", paint_id.0)?;
+    if !base_color.is_opaque() {
+        write!(writer, " fill-opacity=\"{}\"", Opacity(base_color))?;
+    }
+    Ok(())
+}
+
+fn write_color_fill<W: Write>(writer: &mut W, color: ColorU) -> io::Result<()> {
+    write!(writer, " fill=\"{}\"", Hex(color))?;
+    if !color.is_opaque() {
+        write!(writer, " fill-opacity=\"{}\"", Opacity(color))?;
+    }
+    Ok(())
+}
+
+fn css_blend_mode(blend_mode: BlendMode) -> Option<&'static str> {
+    match blend_mode {
+        BlendMode::Lighter => Some("plus-lighter"),
+        BlendMode::Darken => Some("darken"),
+        BlendMode::Lighten => Some("lighten"),
+        BlendMode::Multiply => Some("multiply"),
+        BlendMode::Screen => Some("screen"),
+        BlendMode::HardLight => Some("hard-light"),
+        BlendMode::Overlay => Some("overlay"),
+        BlendMode::ColorDodge => Some("color-dodge"),
+        BlendMode::ColorBurn => Some("color-burn"),
+        BlendMode::SoftLight => Some("soft-light"),
+        BlendMode::Difference => Some("difference"),
+        BlendMode::Exclusion => Some("exclusion"),
+        BlendMode::Hue => Some("hue"),
+        BlendMode::Saturation => Some("saturation"),
+        BlendMode::Color => Some("color"),
+        BlendMode::Luminosity => Some("luminosity"),
+        BlendMode::SrcOver |
+        BlendMode::Clear |
+        BlendMode::Copy |
+        BlendMode::SrcIn |
+        BlendMode::SrcOut |
+        BlendMode::SrcAtop |
+        BlendMode::DestIn |
+        BlendMode::DestOut |
+        BlendMode::DestOver |
+        BlendMode::DestAtop |
+        BlendMode::Xor => None,
+    }
+}
+
+fn encode_png(image: &Image) -> io::Result<Vec<u8>> {
+    let mut png = vec![];
+    let size = image.size();
+    PngEncoder::new(&mut png).encode(color::color_slice_to_u8_slice(image.pixels()),
+                                     size.x() as u32,
+                                     size.y() as u32,
+                                     ColorType::Rgba8)
+                             .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+    Ok(png)
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                output.push(ALPHABET[(bits >> (18 - index * 6)) as usize & 0x3f] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+struct Indent(usize);
+
+impl Display for Indent {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        for _ in 0..self.0 {
+            formatter.write_str("    ")?;
+        }
+        Ok(())
+    }
+}
+
+struct Hex(ColorU);
+
+impl Display for Hex {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "#{:02x}{:02x}{:02x}", self.0.r, self.0.g, self.0.b)
+    }
+}
+
+struct Opacity(ColorU);
+
+impl Display for Opacity {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.0.a as f32 / 255.0)
+    }
+}
+
+struct Matrix(Transform2F);
+
+impl Display for Matrix {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter,
+               "matrix({} {} {} {} {} {})",
+               self.0.m11(),
+               self.0.m21(),
+               self.0.m12(),
+               self.0.m22(),
+               self.0.m13(),
+               self.0.m23())
+    }
+}
+
+struct Escaped<'a>(&'a str);
+
+impl<'a> Display for Escaped<'a> {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        for character in self.0.chars() {
+            match character {
+                '&' => formatter.write_str("&amp;")?,
+                '<' => formatter.write_str("&lt;")?,
+                '>' => formatter.write_str("&gt;")?,
+                '"' => formatter.write_str("&quot;")?,
+                _ => write!(formatter, "{}", character)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::encode_base64;
+    use crate::{Export, FileFormat};
+    use pathfinder_color::ColorU;
+    use pathfinder_content::effects::BlendMode;
+    use pathfinder_content::fill::FillRule;
+    use pathfinder_content::gradient::Gradient;
+    use pathfinder_content::outline::Outline;
+    use pathfinder_content::pattern::{Image, Pattern};
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, vec2f, vec2i};
+    use pathfinder_renderer::paint::Paint;
+    use pathfinder_renderer::scene::{ClipPath, DrawPath, Scene};
+    use std::sync::Arc;
+
+    fn export(scene: &Scene) -> String {
+        let mut output = vec![];
+        scene.export(&mut output, FileFormat::SVG).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn export_paints_and_clips() {
+        let rect = Outline::from_rect(RectF::new(Vector2F::zero(), vec2f(10.0, 10.0)));
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::zero(), vec2f(10.0, 10.0)));
+
+        let outer_clip = scene.push_clip_path(ClipPath::new(rect.clone()));
+        let mut inner_clip = ClipPath::new(rect.clone());
+        inner_clip.set_clip_path(Some(outer_clip));
+        inner_clip.set_fill_rule(FillRule::EvenOdd);
+        let inner_clip = scene.push_clip_path(inner_clip);
+
+        let color = scene.push_paint(&Paint::from_color(ColorU::new(255, 0, 0, 128)));
+        let mut draw_path = DrawPath::new(rect.clone(), color);
+        draw_path.set_clip_path(Some(inner_clip));
+        draw_path.set_fill_rule(FillRule::EvenOdd);
+        draw_path.set_blend_mode(BlendMode::Multiply);
+        draw_path.set_name("a<b".to_owned());
+        scene.push_draw_path(draw_path);
+
+        let mut gradient = Gradient::linear_from_points(Vector2F::zero(), vec2f(10.0, 0.0));
+        gradient.add_color_stop(ColorU::black(), 0.0);
+        gradient.add_color_stop(ColorU::white(), 1.0);
+        let gradient = scene.push_paint(&Paint::from_gradient(gradient));
+        scene.push_draw_path(DrawPath::new(rect.clone(), gradient));
+
+        let image = Image::new(vec2i(1, 1), Arc::new(vec![ColorU::white()]));
+        let pattern = scene.push_paint(&Paint::from_pattern(Pattern::from_image(image)));
+        scene.push_draw_path(DrawPath::new(rect, pattern));
+
+        let svg = export(&scene);
+        assert!(svg.contains("<clipPath id=\"clip0\" clipPathUnits=\"userSpaceOnUse\">"));
+        assert!(svg.contains("<clipPath id=\"clip1\" clipPathUnits=\"userSpaceOnUse\" \
+                              clip-path=\"url(#clip0)\">"));
+        assert!(svg.contains("<path clip-rule=\"evenodd\" d=\"M 0 0 L 10 0 L 10 10 L 0 10 Z\"/>"));
+        assert!(svg.contains("<path id=\"a&lt;b\" fill=\"#ff0000\" fill-opacity=\"0.5019608\" \
+                              fill-rule=\"evenodd\" clip-path=\"url(#clip1)\" \
+                              style=\"mix-blend-mode: multiply\""));
+        assert!(svg.contains("<linearGradient id=\"paint1\" gradientUnits=\"userSpaceOnUse\" \
+                              x1=\"0\" y1=\"0\" x2=\"10\" y2=\"0\">"));
+        assert!(svg.contains("<stop offset=\"1\" stop-color=\"#ffffff\"/>"));
+        assert!(svg.contains("<path fill=\"url(#paint1)\""));
+        assert!(svg.contains("<pattern id=\"paint2\" patternUnits=\"userSpaceOnUse\" \
+                              x=\"0\" y=\"0\" width=\"10\" height=\"10\""));
+        assert!(svg.contains("<image width=\"1\" height=\"1\" \
+                              xlink:href=\"data:image/png;base64,iVBORw0KGgo"));
+    }
+
+    #[test]
+    fn export_translated_non_repeating_pattern() {
+        // Like `drawImage()` at (50, 50): the tile is stretched back to the scene's origin, so
+        // the image has to be moved back to the pattern's origin.
+        let mut scene = Scene::new();
+        scene.set_view_box(RectF::new(Vector2F::zero(), vec2f(100.0, 100.0)));
+        let image = Image::new(vec2i(10, 10), Arc::new(vec![ColorU::white(); 100]));
+        let mut pattern = Pattern::from_image(image);
+        pattern.apply_transform(Transform2F::from_translation(vec2f(50.0, 50.0)));
+        let paint = scene.push_paint(&Paint::from_pattern(pattern));
+        let rect = Outline::from_rect(RectF::new(vec2f(50.0, 50.0), vec2f(10.0, 10.0)));
+        scene.push_draw_path(DrawPath::new(rect, paint));
+
+        let svg = export(&scene);
+        assert!(svg.contains("<pattern id=\"paint0\" patternUnits=\"userSpaceOnUse\" \
+                              x=\"-50\" y=\"-50\" width=\"100\" height=\"100\" \
+                              patternTransform=\"matrix(1 0 0 1 50 50)\">"), "{}", svg);
+        assert!(svg.contains("<g transform=\"translate(50 50)\">\n                \
+                              <image width=\"10\" height=\"10\""), "{}", svg);
+    }
+}
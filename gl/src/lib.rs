@@ -690,14 +690,18 @@ impl Device for GLDevice {
                               }); ck();
             gl::TexParameteri(gl::TEXTURE_2D,
                               gl::TEXTURE_WRAP_S,
-                              if flags.contains(TextureSamplingFlags::REPEAT_U) {
+                              if flags.contains(TextureSamplingFlags::MIRROR_U) {
+                                  gl::MIRRORED_REPEAT as GLint
+                              } else if flags.contains(TextureSamplingFlags::REPEAT_U) {
                                   gl::REPEAT as GLint
                               } else {
                                   gl::CLAMP_TO_EDGE as GLint
                               }); ck();
             gl::TexParameteri(gl::TEXTURE_2D,
                               gl::TEXTURE_WRAP_T,
-                              if flags.contains(TextureSamplingFlags::REPEAT_V) {
+                              if flags.contains(TextureSamplingFlags::MIRROR_V) {
+                                  gl::MIRRORED_REPEAT as GLint
+                              } else if flags.contains(TextureSamplingFlags::REPEAT_V) {
                                   gl::REPEAT as GLint
                               } else {
                                   gl::CLAMP_TO_EDGE as GLint
@@ -16,6 +16,7 @@ extern crate bitflags;
 extern crate log;
 
 pub mod allocator;
+pub mod recording;
 
 use half::f16;
 use image::ImageFormat;
@@ -280,7 +281,7 @@ pub enum UniformData {
     Vec4(F32x4),
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum Primitive {
     Triangles,
     Lines,
@@ -525,6 +526,8 @@ bitflags! {
         const REPEAT_V    = 0x02;
         const NEAREST_MIN = 0x04;
         const NEAREST_MAG = 0x08;
+        const MIRROR_U    = 0x10;
+        const MIRROR_V    = 0x20;
     }
 }
 
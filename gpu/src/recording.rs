@@ -0,0 +1,713 @@
+// pathfinder/gpu/src/recording.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A device that records the calls made to it instead of talking to a GPU.
+//!
+//! This is useful for testing code that drives a `Device`, such as the renderer, in environments
+//! with no graphics stack. Every call that creates, modifies, or uses GPU state is appended to an
+//! inspectable log, and every object the device hands out is tracked so that leaks can be
+//! detected.
+
+use crate::{BufferData, BufferTarget, BufferUploadMode, ComputeDimensions, ComputeState, Device};
+use crate::{FeatureLevel, Primitive, ProgramKind, RenderOptions, RenderState, RenderTarget};
+use crate::{ShaderKind, TextureData, TextureDataRef, TextureFormat, TextureSamplingFlags};
+use crate::VertexAttrDescriptor;
+use half::f16;
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::Vector2I;
+use pathfinder_resources::ResourceLoader;
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::ops::Range;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// A device that records every call it receives and returns synthetic handles.
+pub struct RecordingDevice {
+    state: Rc<RecordingState>,
+    feature_level: FeatureLevel,
+}
+
+struct RecordingState {
+    calls: RefCell<Vec<RecordedCall>>,
+    next_object_id: Cell<u64>,
+    live_object_counts: RefCell<[usize; OBJECT_KIND_COUNT]>,
+}
+
+/// A unique identifier for an object created by a `RecordingDevice`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ObjectId(pub u64);
+
+const OBJECT_KIND_COUNT: usize = 8;
+
+/// The kinds of objects that a `RecordingDevice` tracks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ObjectKind {
+    Buffer,
+    Fence,
+    Framebuffer,
+    Program,
+    Shader,
+    Texture,
+    TimerQuery,
+    VertexArray,
+}
+
+/// A single call made to a `RecordingDevice`.
+#[derive(Clone, Debug)]
+pub enum RecordedCall {
+    CreateTexture { texture: ObjectId, format: TextureFormat, size: Vector2I },
+    CreateTextureFromData { texture: ObjectId, format: TextureFormat, size: Vector2I },
+    CreateShader { shader: ObjectId, name: String, kind: ShaderKind },
+    CreateVertexArray { vertex_array: ObjectId },
+    CreateProgram { program: ObjectId, name: String, shaders: ProgramKind<ObjectId> },
+    SetComputeProgramLocalSize { program: ObjectId, local_size: ComputeDimensions },
+    BindBuffer { vertex_array: ObjectId, buffer: ObjectId, target: BufferTarget },
+    ConfigureVertexAttr {
+        vertex_array: ObjectId,
+        attr: String,
+        descriptor: VertexAttrDescriptor,
+    },
+    CreateFramebuffer { framebuffer: ObjectId, texture: ObjectId },
+    DestroyFramebuffer { framebuffer: ObjectId, texture: ObjectId },
+    CreateBuffer { buffer: ObjectId, mode: BufferUploadMode },
+    /// Allocates storage for a buffer. The size is in bytes.
+    AllocateBuffer { buffer: ObjectId, size: usize, target: BufferTarget },
+    /// Uploads data to a buffer. The position and size are in bytes.
+    UploadToBuffer { buffer: ObjectId, position: usize, size: usize, target: BufferTarget },
+    SetTextureSamplingMode { texture: ObjectId, flags: TextureSamplingFlags },
+    UploadToTexture { texture: ObjectId, rect: RectI },
+    /// Reads pixels back. A target of `None` refers to the default framebuffer.
+    ReadPixels { target: Option<ObjectId>, viewport: RectI },
+    ReadBuffer { buffer: ObjectId, target: BufferTarget, range: Range<usize> },
+    BeginCommands,
+    EndCommands,
+    DrawArrays { index_count: u32, state: RecordedRenderState },
+    DrawElements { index_count: u32, state: RecordedRenderState },
+    DrawElementsInstanced { index_count: u32, instance_count: u32, state: RecordedRenderState },
+    DispatchCompute { dimensions: ComputeDimensions, state: RecordedComputeState },
+    AddFence { fence: ObjectId },
+    WaitForFence { fence: ObjectId },
+    CreateTimerQuery { query: ObjectId },
+    BeginTimerQuery { query: ObjectId },
+    EndTimerQuery { query: ObjectId },
+}
+
+/// The state that a draw call was issued with.
+#[derive(Clone, Debug)]
+pub struct RecordedRenderState {
+    /// The framebuffer drawn to, or `None` for the default framebuffer.
+    pub target: Option<ObjectId>,
+    pub program: ObjectId,
+    pub vertex_array: ObjectId,
+    pub primitive: Primitive,
+    /// The names of the uniforms that were set.
+    pub uniforms: Vec<String>,
+    pub textures: Vec<ObjectId>,
+    pub images: Vec<ObjectId>,
+    pub storage_buffers: Vec<ObjectId>,
+    pub viewport: RectI,
+    pub options: RenderOptions,
+}
+
+/// The state that a compute dispatch was issued with.
+#[derive(Clone, Debug)]
+pub struct RecordedComputeState {
+    pub program: ObjectId,
+    /// The names of the uniforms that were set.
+    pub uniforms: Vec<String>,
+    pub textures: Vec<ObjectId>,
+    pub images: Vec<ObjectId>,
+    pub storage_buffers: Vec<ObjectId>,
+}
+
+// Registers an object as live for as long as it exists.
+struct RecordingObject {
+    id: ObjectId,
+    kind: ObjectKind,
+    state: Rc<RecordingState>,
+}
+
+pub struct RecordingBuffer {
+    object: RecordingObject,
+    mode: BufferUploadMode,
+    size: Cell<usize>,
+}
+
+pub struct RecordingFence {
+    object: RecordingObject,
+}
+
+pub struct RecordingFramebuffer {
+    object: RecordingObject,
+    texture: RecordingTexture,
+}
+
+pub struct RecordingProgram {
+    object: RecordingObject,
+    name: String,
+    local_size: Option<ComputeDimensions>,
+}
+
+pub struct RecordingShader {
+    object: RecordingObject,
+}
+
+pub struct RecordingTexture {
+    object: RecordingObject,
+    format: TextureFormat,
+    size: Vector2I,
+    sampling_flags: Cell<TextureSamplingFlags>,
+}
+
+pub struct RecordingTimerQuery {
+    object: RecordingObject,
+}
+
+pub struct RecordingVertexArray {
+    object: RecordingObject,
+}
+
+/// A named program parameter: a vertex attribute, uniform, texture, image, or storage buffer.
+#[derive(Clone, Debug)]
+pub struct RecordingParameter {
+    pub program: ObjectId,
+    pub name: String,
+}
+
+/// Pixels or buffer contents that are available immediately. The data is always zeroed.
+pub struct RecordingTextureDataReceiver {
+    data: TextureData,
+}
+
+pub struct RecordingBufferDataReceiver {
+    data: Vec<u8>,
+}
+
+impl RecordingDevice {
+    /// Creates a new recording device that reports the given feature level.
+    pub fn new(feature_level: FeatureLevel) -> RecordingDevice {
+        RecordingDevice {
+            state: Rc::new(RecordingState {
+                calls: RefCell::new(vec![]),
+                next_object_id: Cell::new(0),
+                live_object_counts: RefCell::new([0; OBJECT_KIND_COUNT]),
+            }),
+            feature_level,
+        }
+    }
+
+    /// Returns a copy of all the calls recorded so far, in order.
+    #[inline]
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.state.calls.borrow().clone()
+    }
+
+    /// Returns all the calls recorded so far, in order, and clears the log.
+    #[inline]
+    pub fn take_calls(&self) -> Vec<RecordedCall> {
+        mem::take(&mut *self.state.calls.borrow_mut())
+    }
+
+    /// Returns the number of objects of the given kind that have been created and not yet
+    /// dropped.
+    #[inline]
+    pub fn live_object_count(&self, kind: ObjectKind) -> usize {
+        self.state.live_object_counts.borrow()[kind as usize]
+    }
+
+    fn record(&self, call: RecordedCall) {
+        self.state.calls.borrow_mut().push(call);
+    }
+
+    fn create_object(&self, kind: ObjectKind) -> RecordingObject {
+        let id = ObjectId(self.state.next_object_id.get());
+        self.state.next_object_id.set(id.0 + 1);
+        self.state.live_object_counts.borrow_mut()[kind as usize] += 1;
+        RecordingObject { id, kind, state: self.state.clone() }
+    }
+
+    fn create_texture_object(&self, format: TextureFormat, size: Vector2I) -> RecordingTexture {
+        RecordingTexture {
+            object: self.create_object(ObjectKind::Texture),
+            format,
+            size,
+            sampling_flags: Cell::new(TextureSamplingFlags::empty()),
+        }
+    }
+
+    fn record_render_state(&self, render_state: &RenderState<RecordingDevice>)
+                           -> RecordedRenderState {
+        RecordedRenderState {
+            target: match *render_state.target {
+                RenderTarget::Default => None,
+                RenderTarget::Framebuffer(framebuffer) => Some(framebuffer.object.id),
+            },
+            program: render_state.program.object.id,
+            vertex_array: render_state.vertex_array.object.id,
+            primitive: render_state.primitive,
+            uniforms: render_state.uniforms
+                                  .iter()
+                                  .map(|(uniform, _)| uniform.name.clone())
+                                  .collect(),
+            textures: render_state.textures
+                                  .iter()
+                                  .map(|(_, texture)| texture.object.id)
+                                  .collect(),
+            images: render_state.images.iter().map(|(_, image, _)| image.object.id).collect(),
+            storage_buffers: render_state.storage_buffers
+                                         .iter()
+                                         .map(|(_, buffer)| buffer.object.id)
+                                         .collect(),
+            viewport: render_state.viewport,
+            options: render_state.options.clone(),
+        }
+    }
+}
+
+impl Device for RecordingDevice {
+    type Buffer = RecordingBuffer;
+    type BufferDataReceiver = RecordingBufferDataReceiver;
+    type Fence = RecordingFence;
+    type Framebuffer = RecordingFramebuffer;
+    type ImageParameter = RecordingParameter;
+    type Program = RecordingProgram;
+    type Shader = RecordingShader;
+    type StorageBuffer = RecordingParameter;
+    type Texture = RecordingTexture;
+    type TextureParameter = RecordingParameter;
+    type TextureDataReceiver = RecordingTextureDataReceiver;
+    type TimerQuery = RecordingTimerQuery;
+    type Uniform = RecordingParameter;
+    type VertexArray = RecordingVertexArray;
+    type VertexAttr = RecordingParameter;
+
+    #[inline]
+    fn backend_name(&self) -> &'static str {
+        "Recording"
+    }
+
+    #[inline]
+    fn device_name(&self) -> String {
+        "Recording Device".to_owned()
+    }
+
+    #[inline]
+    fn feature_level(&self) -> FeatureLevel {
+        self.feature_level
+    }
+
+    fn create_texture(&self, format: TextureFormat, size: Vector2I) -> RecordingTexture {
+        let texture = self.create_texture_object(format, size);
+        self.record(RecordedCall::CreateTexture { texture: texture.object.id, format, size });
+        texture
+    }
+
+    fn create_texture_from_data(&self, format: TextureFormat, size: Vector2I, data: TextureDataRef)
+                                -> RecordingTexture {
+        data.check_and_extract_data_ptr(size, format);
+        let texture = self.create_texture_object(format, size);
+        self.record(RecordedCall::CreateTextureFromData {
+            texture: texture.object.id,
+            format,
+            size,
+        });
+        texture
+    }
+
+    fn create_shader(&self, _: &dyn ResourceLoader, name: &str, kind: ShaderKind)
+                     -> RecordingShader {
+        self.create_shader_from_source(name, &[], kind)
+    }
+
+    fn create_shader_from_source(&self, name: &str, _: &[u8], kind: ShaderKind)
+                                 -> RecordingShader {
+        let shader = RecordingShader { object: self.create_object(ObjectKind::Shader) };
+        self.record(RecordedCall::CreateShader {
+            shader: shader.object.id,
+            name: name.to_owned(),
+            kind,
+        });
+        shader
+    }
+
+    fn create_vertex_array(&self) -> RecordingVertexArray {
+        let vertex_array =
+            RecordingVertexArray { object: self.create_object(ObjectKind::VertexArray) };
+        self.record(RecordedCall::CreateVertexArray { vertex_array: vertex_array.object.id });
+        vertex_array
+    }
+
+    fn create_program_from_shaders(&self,
+                                   _: &dyn ResourceLoader,
+                                   name: &str,
+                                   shaders: ProgramKind<RecordingShader>)
+                                   -> RecordingProgram {
+        let shaders = match shaders {
+            ProgramKind::Raster { vertex, fragment } => {
+                ProgramKind::Raster { vertex: vertex.object.id, fragment: fragment.object.id }
+            }
+            ProgramKind::Compute(compute) => ProgramKind::Compute(compute.object.id),
+        };
+        let program = RecordingProgram {
+            object: self.create_object(ObjectKind::Program),
+            name: name.to_owned(),
+            local_size: None,
+        };
+        self.record(RecordedCall::CreateProgram {
+            program: program.object.id,
+            name: name.to_owned(),
+            shaders,
+        });
+        program
+    }
+
+    fn set_compute_program_local_size(&self,
+                                      program: &mut RecordingProgram,
+                                      local_size: ComputeDimensions) {
+        program.local_size = Some(local_size);
+        self.record(RecordedCall::SetComputeProgramLocalSize {
+            program: program.object.id,
+            local_size,
+        });
+    }
+
+    #[inline]
+    fn get_vertex_attr(&self, program: &RecordingProgram, name: &str)
+                       -> Option<RecordingParameter> {
+        Some(RecordingParameter::new(program, name))
+    }
+
+    #[inline]
+    fn get_uniform(&self, program: &RecordingProgram, name: &str) -> RecordingParameter {
+        RecordingParameter::new(program, name)
+    }
+
+    #[inline]
+    fn get_texture_parameter(&self, program: &RecordingProgram, name: &str)
+                             -> RecordingParameter {
+        RecordingParameter::new(program, name)
+    }
+
+    #[inline]
+    fn get_image_parameter(&self, program: &RecordingProgram, name: &str) -> RecordingParameter {
+        RecordingParameter::new(program, name)
+    }
+
+    #[inline]
+    fn get_storage_buffer(&self, program: &RecordingProgram, name: &str, _: u32)
+                          -> RecordingParameter {
+        RecordingParameter::new(program, name)
+    }
+
+    fn bind_buffer(&self,
+                   vertex_array: &RecordingVertexArray,
+                   buffer: &RecordingBuffer,
+                   target: BufferTarget) {
+        self.record(RecordedCall::BindBuffer {
+            vertex_array: vertex_array.object.id,
+            buffer: buffer.object.id,
+            target,
+        });
+    }
+
+    fn configure_vertex_attr(&self,
+                             vertex_array: &RecordingVertexArray,
+                             attr: &RecordingParameter,
+                             descriptor: &VertexAttrDescriptor) {
+        self.record(RecordedCall::ConfigureVertexAttr {
+            vertex_array: vertex_array.object.id,
+            attr: attr.name.clone(),
+            descriptor: *descriptor,
+        });
+    }
+
+    fn create_framebuffer(&self, texture: RecordingTexture) -> RecordingFramebuffer {
+        let framebuffer = RecordingFramebuffer {
+            object: self.create_object(ObjectKind::Framebuffer),
+            texture,
+        };
+        self.record(RecordedCall::CreateFramebuffer {
+            framebuffer: framebuffer.object.id,
+            texture: framebuffer.texture.object.id,
+        });
+        framebuffer
+    }
+
+    fn create_buffer(&self, mode: BufferUploadMode) -> RecordingBuffer {
+        let buffer = RecordingBuffer {
+            object: self.create_object(ObjectKind::Buffer),
+            mode,
+            size: Cell::new(0),
+        };
+        self.record(RecordedCall::CreateBuffer { buffer: buffer.object.id, mode });
+        buffer
+    }
+
+    fn allocate_buffer<T>(&self,
+                          buffer: &RecordingBuffer,
+                          data: BufferData<T>,
+                          target: BufferTarget) {
+        let size = match data {
+            BufferData::Uninitialized(length) => length * mem::size_of::<T>(),
+            BufferData::Memory(buffer) => mem::size_of_val(buffer),
+        };
+        buffer.size.set(size);
+        self.record(RecordedCall::AllocateBuffer { buffer: buffer.object.id, size, target });
+    }
+
+    fn upload_to_buffer<T>(&self,
+                           buffer: &RecordingBuffer,
+                           position: usize,
+                           data: &[T],
+                           target: BufferTarget) {
+        let (position, size) = (position * mem::size_of::<T>(), mem::size_of_val(data));
+        assert!(position + size <= buffer.size.get(), "Buffer upload out of bounds!");
+        self.record(RecordedCall::UploadToBuffer {
+            buffer: buffer.object.id,
+            position,
+            size,
+            target,
+        });
+    }
+
+    #[inline]
+    fn framebuffer_texture<'f>(&self, framebuffer: &'f RecordingFramebuffer)
+                               -> &'f RecordingTexture {
+        &framebuffer.texture
+    }
+
+    fn destroy_framebuffer(&self, framebuffer: RecordingFramebuffer) -> RecordingTexture {
+        let RecordingFramebuffer { object, texture } = framebuffer;
+        self.record(RecordedCall::DestroyFramebuffer {
+            framebuffer: object.id,
+            texture: texture.object.id,
+        });
+        texture
+    }
+
+    #[inline]
+    fn texture_format(&self, texture: &RecordingTexture) -> TextureFormat {
+        texture.format
+    }
+
+    #[inline]
+    fn texture_size(&self, texture: &RecordingTexture) -> Vector2I {
+        texture.size
+    }
+
+    fn set_texture_sampling_mode(&self,
+                                 texture: &RecordingTexture,
+                                 flags: TextureSamplingFlags) {
+        texture.sampling_flags.set(flags);
+        self.record(RecordedCall::SetTextureSamplingMode { texture: texture.object.id, flags });
+    }
+
+    fn upload_to_texture(&self, texture: &RecordingTexture, rect: RectI, data: TextureDataRef) {
+        assert!(rect.min_x() >= 0 && rect.min_y() >= 0 &&
+                rect.max_x() <= texture.size.x() && rect.max_y() <= texture.size.y(),
+                "Texture upload out of bounds!");
+        data.check_and_extract_data_ptr(rect.size(), texture.format);
+        self.record(RecordedCall::UploadToTexture { texture: texture.object.id, rect });
+    }
+
+    fn read_pixels(&self, target: &RenderTarget<RecordingDevice>, viewport: RectI)
+                   -> RecordingTextureDataReceiver {
+        let (target, format) = match *target {
+            RenderTarget::Default => (None, TextureFormat::RGBA8),
+            RenderTarget::Framebuffer(framebuffer) => {
+                (Some(framebuffer.object.id), framebuffer.texture.format)
+            }
+        };
+        self.record(RecordedCall::ReadPixels { target, viewport });
+
+        let length = viewport.area() as usize * format.channels();
+        let data = match format {
+            TextureFormat::R8 | TextureFormat::RGBA8 => TextureData::U8(vec![0; length]),
+            TextureFormat::R16F | TextureFormat::RGBA16F => {
+                TextureData::F16(vec![f16::default(); length])
+            }
+            TextureFormat::RGBA32F => TextureData::F32(vec![0.0; length]),
+        };
+        RecordingTextureDataReceiver { data }
+    }
+
+    fn read_buffer(&self, buffer: &RecordingBuffer, target: BufferTarget, range: Range<usize>)
+                   -> RecordingBufferDataReceiver {
+        assert!(range.end <= buffer.size.get(), "Buffer read out of bounds!");
+        let data = vec![0; range.end - range.start];
+        self.record(RecordedCall::ReadBuffer { buffer: buffer.object.id, target, range });
+        RecordingBufferDataReceiver { data }
+    }
+
+    #[inline]
+    fn begin_commands(&self) {
+        self.record(RecordedCall::BeginCommands);
+    }
+
+    #[inline]
+    fn end_commands(&self) {
+        self.record(RecordedCall::EndCommands);
+    }
+
+    fn draw_arrays(&self, index_count: u32, render_state: &RenderState<RecordingDevice>) {
+        let state = self.record_render_state(render_state);
+        self.record(RecordedCall::DrawArrays { index_count, state });
+    }
+
+    fn draw_elements(&self, index_count: u32, render_state: &RenderState<RecordingDevice>) {
+        let state = self.record_render_state(render_state);
+        self.record(RecordedCall::DrawElements { index_count, state });
+    }
+
+    fn draw_elements_instanced(&self,
+                               index_count: u32,
+                               instance_count: u32,
+                               render_state: &RenderState<RecordingDevice>) {
+        let state = self.record_render_state(render_state);
+        self.record(RecordedCall::DrawElementsInstanced { index_count, instance_count, state });
+    }
+
+    fn dispatch_compute(&self,
+                        dimensions: ComputeDimensions,
+                        compute_state: &ComputeState<RecordingDevice>) {
+        assert!(compute_state.program.local_size.is_some(),
+                "Compute program `{}` dispatched without a local size!",
+                compute_state.program.name);
+        let state = RecordedComputeState {
+            program: compute_state.program.object.id,
+            uniforms: compute_state.uniforms
+                                   .iter()
+                                   .map(|(uniform, _)| uniform.name.clone())
+                                   .collect(),
+            textures: compute_state.textures
+                                   .iter()
+                                   .map(|(_, texture)| texture.object.id)
+                                   .collect(),
+            images: compute_state.images.iter().map(|(_, image, _)| image.object.id).collect(),
+            storage_buffers: compute_state.storage_buffers
+                                          .iter()
+                                          .map(|(_, buffer)| buffer.object.id)
+                                          .collect(),
+        };
+        self.record(RecordedCall::DispatchCompute { dimensions, state });
+    }
+
+    fn add_fence(&self) -> RecordingFence {
+        let fence = RecordingFence { object: self.create_object(ObjectKind::Fence) };
+        self.record(RecordedCall::AddFence { fence: fence.object.id });
+        fence
+    }
+
+    fn wait_for_fence(&self, fence: &RecordingFence) {
+        self.record(RecordedCall::WaitForFence { fence: fence.object.id });
+    }
+
+    fn create_timer_query(&self) -> RecordingTimerQuery {
+        let query = RecordingTimerQuery { object: self.create_object(ObjectKind::TimerQuery) };
+        self.record(RecordedCall::CreateTimerQuery { query: query.object.id });
+        query
+    }
+
+    fn begin_timer_query(&self, query: &RecordingTimerQuery) {
+        self.record(RecordedCall::BeginTimerQuery { query: query.object.id });
+    }
+
+    fn end_timer_query(&self, query: &RecordingTimerQuery) {
+        self.record(RecordedCall::EndTimerQuery { query: query.object.id });
+    }
+
+    #[inline]
+    fn try_recv_timer_query(&self, _: &RecordingTimerQuery) -> Option<Duration> {
+        Some(Duration::default())
+    }
+
+    #[inline]
+    fn recv_timer_query(&self, _: &RecordingTimerQuery) -> Duration {
+        Duration::default()
+    }
+
+    #[inline]
+    fn try_recv_texture_data(&self, receiver: &RecordingTextureDataReceiver)
+                             -> Option<TextureData> {
+        Some(receiver.data.clone())
+    }
+
+    #[inline]
+    fn recv_texture_data(&self, receiver: &RecordingTextureDataReceiver) -> TextureData {
+        receiver.data.clone()
+    }
+
+    #[inline]
+    fn try_recv_buffer(&self, receiver: &RecordingBufferDataReceiver) -> Option<Vec<u8>> {
+        Some(receiver.data.clone())
+    }
+
+    #[inline]
+    fn recv_buffer(&self, receiver: &RecordingBufferDataReceiver) -> Vec<u8> {
+        receiver.data.clone()
+    }
+}
+
+impl Drop for RecordingObject {
+    fn drop(&mut self) {
+        self.state.live_object_counts.borrow_mut()[self.kind as usize] -= 1;
+    }
+}
+
+impl RecordingParameter {
+    #[inline]
+    fn new(program: &RecordingProgram, name: &str) -> RecordingParameter {
+        RecordingParameter { program: program.object.id, name: name.to_owned() }
+    }
+}
+
+macro_rules! impl_object_id {
+    ($ty:ty) => {
+        impl $ty {
+            /// Returns the unique ID of this object, as it appears in the call log.
+            #[inline]
+            pub fn id(&self) -> ObjectId {
+                self.object.id
+            }
+        }
+    }
+}
+
+impl_object_id!(RecordingBuffer);
+impl_object_id!(RecordingFence);
+impl_object_id!(RecordingFramebuffer);
+impl_object_id!(RecordingProgram);
+impl_object_id!(RecordingShader);
+impl_object_id!(RecordingTexture);
+impl_object_id!(RecordingTimerQuery);
+impl_object_id!(RecordingVertexArray);
+
+impl RecordingBuffer {
+    /// Returns the upload mode this buffer was created with.
+    #[inline]
+    pub fn mode(&self) -> BufferUploadMode {
+        self.mode
+    }
+
+    /// Returns the size of the storage allocated for this buffer, in bytes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size.get()
+    }
+}
+
+impl RecordingTexture {
+    /// Returns the sampling flags most recently set on this texture.
+    #[inline]
+    pub fn sampling_flags(&self) -> TextureSamplingFlags {
+        self.sampling_flags.get()
+    }
+}
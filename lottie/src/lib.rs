@@ -14,6 +14,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Error as JSONError;
 use std::io::Read;
 
+mod scene;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Lottie {
     #[serde(rename = "v")]
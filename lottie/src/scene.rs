@@ -0,0 +1,301 @@
+// pathfinder/lottie/src/scene.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Draws individual frames of Lottie animations into Pathfinder scenes.
+//!
+//! Only shape layers made of paths, groups, fills, and transforms are supported. Animated values
+//! are interpolated linearly between keyframes, ignoring easing curves, and animated paths hold
+//! each keyframe until the next one.
+
+use crate::{Layer, Lottie, MultidimensionalPropertyValue, PropertyValue, Shape, ShapeProperty};
+use crate::{ShapeVertices, Transform};
+use pathfinder_color::ColorF;
+use pathfinder_content::outline::{Contour, Outline};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::{DrawPath, Scene};
+
+impl Lottie {
+    /// Draws the given frame of this animation into the scene.
+    ///
+    /// Frames are numbered as in the file, so the animation runs from `in_point` up to but not
+    /// including `out_point`. The scene's view box is left untouched; callers will usually want to
+    /// set it to `(0, 0, width, height)`.
+    pub fn draw_frame_into_scene(&self, frame: f32, scene: &mut Scene) {
+        // Layers earlier in the list are drawn on top of later ones.
+        for layer in self.layers.iter().rev() {
+            match *layer {
+                Layer::Shape { ref transform, in_point, out_point, ref shapes, .. } => {
+                    if frame < in_point as f32 || frame >= out_point as f32 {
+                        continue;
+                    }
+                    let opacity = match transform.opacity {
+                        None => 1.0,
+                        Some(ref opacity) => sample(opacity, frame) * 0.01,
+                    };
+                    let transform = layer_transform(transform, frame);
+                    draw_group(shapes, &transform, opacity, frame, scene);
+                }
+            }
+        }
+    }
+}
+
+fn draw_group(items: &[Shape],
+              parent_transform: &Transform2F,
+              opacity: f32,
+              frame: f32,
+              scene: &mut Scene) {
+    let transform = *parent_transform * group_transform(items, frame);
+
+    // Like layers, items are listed from top to bottom. A fill paints every path that precedes it
+    // in the group, including the paths of nested groups.
+    for (item_index, item) in items.iter().enumerate().rev() {
+        match *item {
+            Shape::Group { items: ref child_items, .. } => {
+                draw_group(child_items, &transform, opacity, frame, scene);
+            }
+            Shape::Fill { opacity: ref fill_opacity, ref color, .. } => {
+                let mut outline = Outline::new();
+                for shape in &items[0..item_index] {
+                    push_shape_outline(shape, &transform, frame, &mut outline);
+                }
+                if outline.is_empty() {
+                    continue;
+                }
+
+                let color = sample_multidimensional(color, frame);
+                let mut alpha = color.get(3).cloned().unwrap_or(1.0) * opacity;
+                if let Some(ref fill_opacity) = *fill_opacity {
+                    alpha *= sample(fill_opacity, frame) * 0.01;
+                }
+                let channel = |index: usize| color.get(index).cloned().unwrap_or(0.0);
+                let color = ColorF::new(channel(0), channel(1), channel(2), alpha);
+                let paint = scene.push_paint(&Paint::from_color(color.to_u8()));
+                scene.push_draw_path(DrawPath::new(outline, paint));
+            }
+            Shape::Shape { .. } | Shape::Transform { .. } | Shape::Unimplemented => {}
+        }
+    }
+}
+
+fn push_shape_outline(shape: &Shape, transform: &Transform2F, frame: f32, outline: &mut Outline) {
+    match *shape {
+        Shape::Shape { ref vertices, .. } => {
+            if let Some(contour) = shape_contour(vertices, frame) {
+                outline.push_contour(contour.transformed(transform));
+            }
+        }
+        Shape::Group { ref items, .. } => {
+            let transform = *transform * group_transform(items, frame);
+            for item in items {
+                push_shape_outline(item, &transform, frame, outline);
+            }
+        }
+        Shape::Fill { .. } | Shape::Transform { .. } | Shape::Unimplemented => {}
+    }
+}
+
+fn shape_contour(vertices: &ShapeVertices, frame: f32) -> Option<Contour> {
+    let property = match *vertices {
+        ShapeVertices::Shape { ref value, .. } => value,
+        ShapeVertices::ShapeKeyframed { ref value, .. } => {
+            let mut keyframes = value.iter().filter_map(|keyframe| {
+                keyframe.start.first().and_then(|start| start.as_ref()).map(|start| {
+                    (keyframe.time as f32, start)
+                })
+            });
+            let mut property = keyframes.next()?.1;
+            for (time, start) in keyframes {
+                if time > frame {
+                    break;
+                }
+                property = start;
+            }
+            property
+        }
+    };
+    Some(property_contour(property))
+}
+
+fn property_contour(property: &ShapeProperty) -> Contour {
+    // In and out points are stored relative to their vertices.
+    let point = |points: &[[f32; 2]], index: usize| {
+        points.get(index).map_or(Vector2F::zero(), |point| vec2f(point[0], point[1]))
+    };
+    let vertex_count = property.vertices.len();
+    let mut contour = Contour::with_capacity(vertex_count * 3);
+    for index in 0..vertex_count {
+        let to = point(&property.vertices, index);
+        if index == 0 {
+            contour.push_endpoint(to);
+            continue;
+        }
+        let from = point(&property.vertices, index - 1);
+        contour.push_cubic(from + point(&property.out_points, index - 1),
+                           to + point(&property.in_points, index),
+                           to);
+    }
+    if property.closed && vertex_count > 1 {
+        let (from, to) = (point(&property.vertices, vertex_count - 1),
+                          point(&property.vertices, 0));
+        contour.push_cubic(from + point(&property.out_points, vertex_count - 1),
+                           to + point(&property.in_points, 0),
+                           to);
+        contour.close();
+    }
+    contour
+}
+
+fn layer_transform(transform: &Transform, frame: f32) -> Transform2F {
+    compose_transform(&transform.position,
+                      &transform.anchor_point,
+                      &transform.scale,
+                      &transform.rotation,
+                      frame)
+}
+
+fn group_transform(items: &[Shape], frame: f32) -> Transform2F {
+    for item in items {
+        if let Shape::Transform { ref position, ref anchor_point, ref scale, ref rotation, .. } =
+                *item {
+            return compose_transform(position, anchor_point, scale, rotation, frame);
+        }
+    }
+    Transform2F::default()
+}
+
+fn compose_transform(position: &MultidimensionalPropertyValue,
+                     anchor_point: &MultidimensionalPropertyValue,
+                     scale: &MultidimensionalPropertyValue,
+                     rotation: &PropertyValue,
+                     frame: f32)
+                     -> Transform2F {
+    let vector = |value: &MultidimensionalPropertyValue, default: f32| {
+        let values = sample_multidimensional(value, frame);
+        vec2f(values.first().cloned().unwrap_or(default), values.get(1).cloned().unwrap_or(default))
+    };
+    Transform2F::from_translation(vector(position, 0.0)) *
+        Transform2F::from_rotation(sample(rotation, frame).to_radians()) *
+        Transform2F::from_scale(vector(scale, 100.0) * 0.01) *
+        Transform2F::from_translation(-vector(anchor_point, 0.0))
+}
+
+fn sample(value: &PropertyValue, frame: f32) -> f32 {
+    match *value {
+        PropertyValue::Value { value, .. } => value,
+        PropertyValue::KeyframedValue { ref keyframes, .. } => {
+            let keyframes = keyframes.iter().filter_map(|keyframe| {
+                keyframe.start.as_ref().map(|start| (keyframe.time as f32, &start[..]))
+            });
+            interpolate(keyframes, frame).first().cloned().unwrap_or(0.0)
+        }
+    }
+}
+
+fn sample_multidimensional(value: &MultidimensionalPropertyValue, frame: f32) -> Vec<f32> {
+    match *value {
+        MultidimensionalPropertyValue::Value { ref value, .. } => value.clone(),
+        MultidimensionalPropertyValue::KeyframedValue { ref keyframes, .. } => {
+            let keyframes = keyframes.iter().filter_map(|keyframe| {
+                keyframe.start.as_ref().map(|start| (keyframe.time as f32, &start[..]))
+            });
+            interpolate(keyframes, frame)
+        }
+    }
+}
+
+// Linearly interpolates between the keyframes surrounding `frame`, holding the first and last
+// values outside the keyframed range.
+fn interpolate<'a, I>(keyframes: I, frame: f32) -> Vec<f32>
+                      where I: Iterator<Item = (f32, &'a [f32])> {
+    let mut prev: Option<(f32, &[f32])> = None;
+    for (time, values) in keyframes {
+        if time >= frame {
+            let (prev_time, prev_values) = match prev {
+                Some(prev) if time > prev.0 => prev,
+                _ => return values.to_vec(),
+            };
+            let t = (frame - prev_time) / (time - prev_time);
+            return prev_values.iter().zip(values.iter()).map(|(&a, &b)| a + (b - a) * t).collect();
+        }
+        prev = Some((time, values));
+    }
+    prev.map_or(vec![], |(_, values)| values.to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Lottie;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::{vec2f, vec2i};
+    use pathfinder_renderer::options::BuildOptions;
+    use pathfinder_renderer::scene::{DrawPathId, Scene};
+
+    const ANIMATION: &str = r#"{
+        "v": "5.5.2", "fr": 30, "ip": 0, "op": 20, "w": 100, "h": 100, "ddd": 0, "assets": [],
+        "layers": [{
+            "ddd": 0, "ind": 1, "nm": "Square", "ao": 0, "ip": 0, "op": 20, "st": 0, "bm": 0,
+            "sr": 1,
+            "ks": {
+                "p": {"a": 1, "k": [{"t": 0, "s": [10, 10]}, {"t": 10, "s": [50, 10]}]},
+                "a": {"a": 0, "k": [0, 0]},
+                "s": {"a": 0, "k": [100, 100]},
+                "r": {"a": 0, "k": 0}
+            },
+            "shapes": [{
+                "ty": "gr", "nm": "Group",
+                "it": [
+                    {
+                        "ty": "sh",
+                        "ks": {
+                            "a": 0,
+                            "k": {
+                                "c": true,
+                                "i": [[0, 0], [0, 0], [0, 0], [0, 0]],
+                                "o": [[0, 0], [0, 0], [0, 0], [0, 0]],
+                                "v": [[0, 0], [20, 0], [20, 20], [0, 20]]
+                            }
+                        }
+                    },
+                    {"ty": "fl", "c": {"a": 0, "k": [1, 0, 0, 1]}, "o": {"a": 0, "k": 50}},
+                    {
+                        "ty": "tr",
+                        "p": {"a": 0, "k": [0, 0]}, "a": {"a": 0, "k": [0, 0]},
+                        "s": {"a": 0, "k": [200, 100]}, "r": {"a": 0, "k": 0},
+                        "sk": {"a": 0, "k": 0}, "sa": {"a": 0, "k": 0}
+                    }
+                ]
+            }]
+        }]
+    }"#;
+
+    #[test]
+    fn draw_frames() {
+        let lottie = Lottie::from_reader(ANIMATION.as_bytes()).unwrap();
+
+        let mut scene = Scene::new();
+        lottie.draw_frame_into_scene(5.0, &mut scene);
+        assert_eq!(scene.draw_path_count(), 1);
+        let outline = scene.get_draw_path(DrawPathId(0)).outline();
+        assert_eq!(outline.bounds(), RectF::new(vec2f(30.0, 10.0), vec2f(40.0, 20.0)));
+
+        scene.set_view_box(RectF::new(vec2f(0.0, 0.0), vec2f(100.0, 100.0)));
+        let image = scene.rasterize(vec2i(100, 100), BuildOptions::default());
+        let pixel = image.pixels()[15 * 100 + 35];
+        assert_eq!((pixel.r, pixel.g, pixel.b), (255, 0, 0));
+        assert!((pixel.a as i32 - 128).abs() <= 1);
+
+        let mut scene = Scene::new();
+        lottie.draw_frame_into_scene(20.0, &mut scene);
+        assert_eq!(scene.draw_path_count(), 0);
+    }
+}
@@ -131,7 +131,7 @@ impl MetalDevice {
         let device = device.into_metal_device();
         let command_queue = device.new_command_queue();
 
-        let samplers = (0..16).map(|sampling_flags_value| {
+        let samplers = (0..64).map(|sampling_flags_value| {
             let sampling_flags = TextureSamplingFlags::from_bits(sampling_flags_value).unwrap();
             let sampler_descriptor = SamplerDescriptor::new();
             sampler_descriptor.set_support_argument_buffers(true);
@@ -149,13 +149,17 @@ impl MetalDevice {
                     MTLSamplerMinMagFilter::Linear
                 });
             sampler_descriptor.set_address_mode_s(
-                if sampling_flags.contains(TextureSamplingFlags::REPEAT_U) {
+                if sampling_flags.contains(TextureSamplingFlags::MIRROR_U) {
+                    MTLSamplerAddressMode::MirrorRepeat
+                } else if sampling_flags.contains(TextureSamplingFlags::REPEAT_U) {
                     MTLSamplerAddressMode::Repeat
                 } else {
                     MTLSamplerAddressMode::ClampToEdge
                 });
             sampler_descriptor.set_address_mode_t(
-                if sampling_flags.contains(TextureSamplingFlags::REPEAT_V) {
+                if sampling_flags.contains(TextureSamplingFlags::MIRROR_V) {
+                    MTLSamplerAddressMode::MirrorRepeat
+                } else if sampling_flags.contains(TextureSamplingFlags::REPEAT_V) {
                     MTLSamplerAddressMode::Repeat
                 } else {
                     MTLSamplerAddressMode::ClampToEdge
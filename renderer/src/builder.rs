@@ -227,15 +227,32 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
         let draw_path_count = self.scene.draw_paths().len();
         let effective_view_box = self.scene.effective_view_box(self.built_options);
 
-        let built_clip_paths = executor.build_vector(clip_path_count, |path_index| {
-            self.build_clip_path_on_cpu(PathBuildParams {
+        let clip_path_build_params = |path_index: usize| {
+            PathBuildParams {
                 path_id: PathId(path_index as u32),
                 view_box: effective_view_box,
                 prepare_mode: *prepare_mode,
-                built_options: &self.built_options,
-                scene: &self.scene,
+                built_options: self.built_options,
+                scene: self.scene,
+            }
+        };
+        let built_clip_paths = if self.scene.clip_paths().iter().any(|clip_path| {
+            clip_path.clip_path().is_some()
+        }) {
+            // Clip paths that are themselves clipped need the tiles of the clip paths that clip
+            // them, which are always defined earlier, so build them in order.
+            let mut built_clip_paths = Vec::with_capacity(clip_path_count);
+            for path_index in 0..clip_path_count {
+                let params = clip_path_build_params(path_index);
+                let built_clip_path = self.build_clip_path_on_cpu(params, &built_clip_paths);
+                built_clip_paths.push(built_clip_path);
+            }
+            built_clip_paths
+        } else {
+            executor.build_vector(clip_path_count, |path_index| {
+                self.build_clip_path_on_cpu(clip_path_build_params(path_index), &[])
             })
-        });
+        };
 
         let built_draw_paths = executor.build_vector(draw_path_count, |path_index| {
             self.build_draw_path_on_cpu(DrawPathBuildParams {
@@ -243,30 +260,29 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
                     path_id: PathId(path_index as u32),
                     view_box: effective_view_box,
                     prepare_mode: *prepare_mode,
-                    built_options: &self.built_options,
-                    scene: &self.scene,
+                    built_options: self.built_options,
+                    scene: self.scene,
                 },
-                paint_metadata: &paint_metadata,
+                paint_metadata,
                 built_clip_paths: &built_clip_paths,
             })
         });
 
-        BuiltPaths { draw: built_draw_paths }
+        BuiltPaths { clip: built_clip_paths, draw: built_draw_paths }
     }
 
-    fn build_clip_path_on_cpu(&self, params: PathBuildParams) -> BuiltPath {
-        let PathBuildParams { path_id, view_box, built_options, scene, prepare_mode } = params;
+    fn build_clip_path_on_cpu(&self, params: PathBuildParams, built_clip_paths: &[BuiltPath])
+                              -> BuiltPath {
+        let PathBuildParams { path_id, built_options, scene, .. } = params;
         let path_object = &scene.get_clip_path(path_id.to_clip_path_id());
         let outline = scene.apply_render_options(path_object.outline(), built_options);
 
         let mut tiler = Tiler::new(self,
-                                   path_id,
+                                   &params,
                                    &outline,
                                    path_object.fill_rule(),
-                                   view_box,
-                                   &prepare_mode,
                                    path_object.clip_path(),
-                                   &[],
+                                   built_clip_paths,
                                    TilingPathInfo::Clip);
 
         tiler.generate_tiles();
@@ -276,13 +292,7 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
 
     fn build_draw_path_on_cpu(&self, params: DrawPathBuildParams) -> BuiltDrawPath {
         let DrawPathBuildParams {
-            path_build_params: PathBuildParams {
-                path_id,
-                view_box,
-                built_options,
-                prepare_mode,
-                scene,
-            },
+            path_build_params: PathBuildParams { path_id, built_options, scene, .. },
             paint_metadata,
             built_clip_paths,
         } = params;
@@ -294,13 +304,11 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
         let paint_metadata = &paint_metadata[paint_id.0 as usize];
 
         let mut tiler = Tiler::new(self,
-                                   path_id,
+                                   &params.path_build_params,
                                    &outline,
                                    path_object.fill_rule(),
-                                   view_box,
-                                   &prepare_mode,
                                    path_object.clip_path(),
-                                   &built_clip_paths,
+                                   built_clip_paths,
                                    TilingPathInfo::Draw(DrawTilingPathInfo {
             paint_id,
             blend_mode: path_object.blend_mode(),
@@ -324,6 +332,7 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
                           prepare_mode: &PrepareMode,
                           built_paths: Option<BuiltPaths>) {
         let mut tile_batch_builder = TileBatchBuilder::new(built_paths);
+        tile_batch_builder.build_tile_batches_for_nested_clip_paths();
 
         // Prepare display items.
         for display_item in self.scene.display_list() {
@@ -388,14 +397,15 @@ impl<'a, 'b, 'c, 'd> SceneBuilder<'a, 'b, 'c, 'd> {
 }
 
 struct BuiltPaths {
+    clip: Vec<BuiltPath>,
     draw: Vec<BuiltDrawPath>,
 }
 
-struct PathBuildParams<'a> {
-    path_id: PathId,
-    view_box: RectF,
+pub(crate) struct PathBuildParams<'a> {
+    pub(crate) path_id: PathId,
+    pub(crate) view_box: RectF,
     built_options: &'a PreparedBuildOptions,
-    prepare_mode: PrepareMode,
+    pub(crate) prepare_mode: PrepareMode,
     scene: &'a Scene,
 }
 
@@ -421,6 +431,13 @@ impl BuiltPath {
 
         let ctrl_byte = tiling_path_info.to_ctrl();
 
+        // Clip path tiles are never drawn, but draw tiles can borrow their masks, so record how
+        // those masks are resolved.
+        let tile_ctrl = match *tiling_path_info {
+            TilingPathInfo::Draw(_) => ctrl_byte,
+            TilingPathInfo::Clip => tiles::mask_0_ctrl(fill_rule),
+        };
+
         let tile_map_bounds = if tiling_path_info.has_destructive_blend_mode() {
             view_box_bounds
         } else {
@@ -441,22 +458,21 @@ impl BuiltPath {
                                 path_id,
                                 color: paint_id.0,
                                 backdrop: 0,
-                                ctrl: ctrl_byte,
+                                ctrl: tile_ctrl,
                             }
                         }, tile_bounds),
-                    clip_tiles: match *tiling_path_info {
-                        TilingPathInfo::Draw(_) if clip_path_id.is_some() => {
-                            Some(DenseTileMap::from_builder(|_| {
-                                Clip {
-                                    dest_tile_id: AlphaTileId(!0),
-                                    dest_backdrop: 0,
-                                    src_tile_id: AlphaTileId(!0),
-                                    src_backdrop: 0,
-                                }
-                            }, tile_bounds))
-                        }
-                        _ => None,
-                    },
+                    clip_tiles: clip_path_id.map(|_| {
+                        DenseTileMap::from_builder(|_| {
+                            Clip {
+                                dest_tile_id: AlphaTileId(!0),
+                                dest_backdrop: 0,
+                                dest_ctrl: 0,
+                                src_tile_id: AlphaTileId(!0),
+                                src_backdrop: 0,
+                                src_ctrl: 0,
+                            }
+                        }, tile_bounds)
+                    }),
                 })
             }
             PrepareMode::TransformCPUBinGPU => {
@@ -869,6 +885,57 @@ impl TileBatchBuilder {
         }
     }
 
+    // On D3D9, intersects the masks of clip paths with those of the clip paths that clip them.
+    // Clips within a batch are applied all at once, so each level of nesting gets its own batch,
+    // outermost first. (D3D11 does this while binning.)
+    fn build_tile_batches_for_nested_clip_paths(&mut self) {
+        let built_paths = match self.level {
+            TileBatchBuilderLevel::D3D9 { ref built_paths } => built_paths,
+            TileBatchBuilderLevel::D3D11 => return,
+        };
+
+        let mut depths: Vec<usize> = Vec::with_capacity(built_paths.clip.len());
+        let mut clips_by_depth: Vec<Vec<Clip>> = vec![];
+        for built_clip_path in &built_paths.clip {
+            let depth = match built_clip_path.clip_path_id {
+                None => 0,
+                Some(parent_id) => depths.get(parent_id.0 as usize).map_or(1, |depth| depth + 1),
+            };
+            depths.push(depth);
+
+            let clip_tiles = match built_clip_path.data {
+                BuiltPathData::CPU(BuiltPathBinCPUData {
+                    clip_tiles: Some(ref clip_tiles),
+                    ..
+                }) => clip_tiles,
+                _ => continue,
+            };
+            while clips_by_depth.len() < depth {
+                clips_by_depth.push(vec![]);
+            }
+            for clip_tile in &clip_tiles.data {
+                if clip_tile.dest_tile_id != AlphaTileId(!0) &&
+                        clip_tile.src_tile_id != AlphaTileId(!0) {
+                    clips_by_depth[depth - 1].push(*clip_tile);
+                }
+            }
+        }
+
+        for clips in clips_by_depth {
+            if clips.is_empty() {
+                continue;
+            }
+            self.draw_commands.push(RenderCommand::DrawTilesD3D9(DrawTileBatchD3D9 {
+                tiles: vec![],
+                clips,
+                z_buffer_data: DenseTileMap::from_builder(|_| 0, RectI::default()),
+                color_texture: None,
+                filter: Filter::None,
+                blend_mode: BlendMode::SrcOver,
+            }));
+        }
+    }
+
     fn build_tile_batches_for_draw_path_display_item(&mut self,
                                                      scene: &Scene,
                                                      sink: &SceneSink,
@@ -902,8 +969,11 @@ impl TileBatchBuilder {
                                                           &draw_path)
                 }
                 Some(DrawTileBatch::D3D9(ref mut existing_batch)) => {
-                    !fixup_batch_for_new_path_if_possible(&mut existing_batch.color_texture,
-                                                          &draw_path)
+                    // The blend mode and filter apply to the whole batch.
+                    existing_batch.blend_mode != draw_path.blend_mode ||
+                        existing_batch.filter != draw_path.filter ||
+                        !fixup_batch_for_new_path_if_possible(&mut existing_batch.color_texture,
+                                                              &draw_path)
                 }
                 None => false,
             };
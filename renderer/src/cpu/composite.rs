@@ -0,0 +1,297 @@
+// pathfinder/renderer/src/cpu/composite.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Color filters, blend modes, and fixed-function blending for the software renderer.
+//!
+//! These mirror `tile_fragment.inc.glsl` and the blend states in `gpu/blend.rs` so that the
+//! software renderer produces the same output as the GPU.
+
+use crate::cpu::raster::TextureView;
+use crate::gpu::blend::ToBlendState;
+use pathfinder_color::{ColorF, ColorU};
+use pathfinder_content::effects::{BlendMode, BlurDirection, Filter, PatternFilter};
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use pathfinder_gpu::{BlendFactor, BlendOp, BlendState, TextureSamplingFlags};
+use pathfinder_simd::default::F32x4;
+use std::f32::consts::{FRAC_PI_3, PI};
+use std::mem;
+
+const FRAC_6_PI: f32 = 6.0 / PI;
+const FRAC_1_2_PI: f32 = 0.5 / PI;
+const SQRT_2_PI_INV: f32 = 0.398_942_3;
+
+/// Computes the color of the pattern texture at the given coordinate after applying a filter.
+pub(crate) fn filter_color(filter: &Filter,
+                           texture: TextureView,
+                           sampling_flags: TextureSamplingFlags,
+                           tex_coord: Vector2F)
+                           -> ColorF {
+    match *filter {
+        Filter::None => texture.sample(tex_coord, sampling_flags),
+        Filter::RadialGradient { line, radii, uv_origin } => {
+            let (d_p, d_c) = (tex_coord - line.from(), line.vector());
+            let d_r = radii[1] - radii[0];
+            let a = d_c.dot(d_c) - d_r * d_r;
+            let b = d_p.dot(d_c) + radii[0] * d_r;
+            let c = d_p.dot(d_p) - radii[0] * radii[0];
+            let discrim = b * b - a * c;
+            if discrim == 0.0 {
+                return ColorF::transparent_black();
+            }
+            let sqrt_discrim = f32::sqrt(discrim);
+            let (mut t0, mut t1) = ((b + sqrt_discrim) / a, (b - sqrt_discrim) / a);
+            if t0 > t1 {
+                mem::swap(&mut t0, &mut t1);
+            }
+            let t = if t0 >= 0.0 { t0 } else { t1 };
+            texture.sample(uv_origin + vec2f(t, 0.0), sampling_flags)
+        }
+        Filter::ConicGradient { center, start_angle, uv_origin } => {
+            let d_p = tex_coord - center;
+            let t = ((f32::atan2(d_p.y(), d_p.x()) - start_angle) * FRAC_1_2_PI).rem_euclid(1.0);
+            texture.sample(uv_origin + vec2f(t, 0.0), sampling_flags)
+        }
+        Filter::PatternFilter(PatternFilter::Blur { direction, sigma }) => {
+            let one_pixel = match direction {
+                BlurDirection::X => vec2f(1.0 / texture.size.x() as f32, 0.0),
+                BlurDirection::Y => vec2f(0.0, 1.0 / texture.size.y() as f32),
+            };
+            let support = f32::ceil(1.5 * sigma) as i32 * 2;
+            let sigma_inv = 1.0 / sigma;
+            let mut gauss_sum = SQRT_2_PI_INV * sigma_inv;
+            let mut color = texture.sample(tex_coord, sampling_flags).0 * F32x4::splat(gauss_sum);
+            for offset in 1..(support + 1) {
+                let coeff = SQRT_2_PI_INV * sigma_inv *
+                    f32::exp(-0.5 * (offset * offset) as f32 * sigma_inv * sigma_inv);
+                let offset = one_pixel * offset as f32;
+                let left = texture.sample(tex_coord - offset, sampling_flags).0;
+                let right = texture.sample(tex_coord + offset, sampling_flags).0;
+                color += (left + right) * F32x4::splat(coeff);
+                gauss_sum += 2.0 * coeff;
+            }
+            ColorF(color * F32x4::splat(1.0 / gauss_sum))
+        }
+        Filter::PatternFilter(PatternFilter::Text { fg_color, bg_color, defringing_kernel, .. }) => {
+            // FIXME: Gamma correction requires the gamma lookup table and is not applied here.
+            let alpha = match defringing_kernel {
+                Some(kernel) if kernel.0[3] != 0.0 => {
+                    let one_pixel = 1.0 / texture.size.x() as f32;
+                    let mut taps = [0.0; 9];
+                    for (index, tap) in taps.iter_mut().enumerate() {
+                        let offset = index as i32 - 4;
+                        if (offset == -4 || offset == 4) && kernel.0[0] <= 0.0 {
+                            continue;
+                        }
+                        let uv = tex_coord + vec2f(offset as f32 * one_pixel, 0.0);
+                        *tap = texture.sample(uv, sampling_flags).r();
+                    }
+                    let convolve = |start: usize| {
+                        let k = &kernel.0;
+                        let w = [k[0], k[1], k[2], k[3], k[2], k[1], k[0]];
+                        taps[start..(start + 7)].iter().zip(w.iter()).map(|(a, b)| a * b).sum()
+                    };
+                    [convolve(0), convolve(1), convolve(2)]
+                }
+                _ => {
+                    let alpha = texture.sample(tex_coord, sampling_flags).r();
+                    [alpha; 3]
+                }
+            };
+            ColorF::new(lerp(bg_color.r(), fg_color.r(), alpha[0]),
+                        lerp(bg_color.g(), fg_color.g(), alpha[1]),
+                        lerp(bg_color.b(), fg_color.b(), alpha[2]),
+                        1.0)
+        }
+        Filter::PatternFilter(PatternFilter::ColorMatrix(matrix)) => {
+            let src = texture.sample(tex_coord, sampling_flags);
+            let [c0, c1, c2, c3, offset] = matrix.0;
+            ColorF(c0 * F32x4::splat(src.r()) +
+                   c1 * F32x4::splat(src.g()) +
+                   c2 * F32x4::splat(src.b()) +
+                   c3 * F32x4::splat(src.a()) +
+                   offset)
+        }
+    }
+}
+
+/// Applies one of the blend modes that the GPU performs in the fragment shader.
+///
+/// Blend modes that map to fixed-function blend states pass the source color through unchanged.
+pub(crate) fn composite(src: ColorF, dest: ColorF, blend_mode: BlendMode) -> ColorF {
+    let src_rgb = [src.r(), src.g(), src.b()];
+    let dest_rgb = [dest.r(), dest.g(), dest.b()];
+    let blended = match blend_mode {
+        BlendMode::Multiply => map_rgb(dest_rgb, src_rgb, |d, s| d * s),
+        BlendMode::Screen => map_rgb(dest_rgb, src_rgb, screen),
+        BlendMode::Overlay => map_rgb(src_rgb, dest_rgb, hard_light),
+        BlendMode::Darken => map_rgb(dest_rgb, src_rgb, f32::min),
+        BlendMode::Lighten => map_rgb(dest_rgb, src_rgb, f32::max),
+        BlendMode::ColorDodge => map_rgb(dest_rgb, src_rgb, color_dodge),
+        BlendMode::ColorBurn => {
+            map_rgb(dest_rgb, src_rgb, |d, s| 1.0 - color_dodge(1.0 - d, 1.0 - s))
+        }
+        BlendMode::HardLight => map_rgb(dest_rgb, src_rgb, hard_light),
+        BlendMode::SoftLight => map_rgb(dest_rgb, src_rgb, soft_light),
+        BlendMode::Difference => map_rgb(dest_rgb, src_rgb, |d, s| f32::abs(d - s)),
+        BlendMode::Exclusion => map_rgb(dest_rgb, src_rgb, |d, s| d + s - 2.0 * d * s),
+        BlendMode::Hue | BlendMode::Saturation | BlendMode::Color | BlendMode::Luminosity => {
+            let (dest_hsl, src_hsl) = (rgb_to_hsl(dest_rgb), rgb_to_hsl(src_rgb));
+            let hsl = match blend_mode {
+                BlendMode::Hue => [src_hsl[0], dest_hsl[1], dest_hsl[2]],
+                BlendMode::Saturation => [dest_hsl[0], src_hsl[1], dest_hsl[2]],
+                BlendMode::Color => [src_hsl[0], src_hsl[1], dest_hsl[2]],
+                _ => [dest_hsl[0], dest_hsl[1], src_hsl[2]],
+            };
+            hsl_to_rgb(hsl)
+        }
+        BlendMode::Clear |
+        BlendMode::Copy |
+        BlendMode::SrcIn |
+        BlendMode::SrcOut |
+        BlendMode::SrcOver |
+        BlendMode::SrcAtop |
+        BlendMode::DestIn |
+        BlendMode::DestOut |
+        BlendMode::DestOver |
+        BlendMode::DestAtop |
+        BlendMode::Xor |
+        BlendMode::Lighter => return src,
+    };
+
+    let (src_alpha, dest_alpha) = (src.a(), dest.a());
+    let channel = |index: usize| {
+        src_alpha * (1.0 - dest_alpha) * src_rgb[index] +
+            src_alpha * dest_alpha * blended[index] +
+            (1.0 - src_alpha) * dest_rgb[index]
+    };
+    ColorF::new(channel(0), channel(1), channel(2), 1.0)
+}
+
+/// Combines a premultiplied source color with the destination using the fixed-function blend
+/// state that the GPU renderer would use for this blend mode.
+pub(crate) fn blend(src: ColorF, dest: ColorF, blend_mode: BlendMode) -> ColorF {
+    let blend_state = match blend_mode.to_blend_state() {
+        None => return src,
+        Some(blend_state) => blend_state,
+    };
+    let BlendState { src_rgb_factor, dest_rgb_factor, src_alpha_factor, dest_alpha_factor, op } =
+        blend_state;
+
+    let src_factor = blend_factor(src_rgb_factor, src_alpha_factor, src, dest);
+    let dest_factor = blend_factor(dest_rgb_factor, dest_alpha_factor, src, dest);
+    let (src_term, dest_term) = (src.0 * src_factor, dest.0 * dest_factor);
+
+    ColorF(match op {
+        BlendOp::Add => src_term + dest_term,
+        BlendOp::Subtract => src_term - dest_term,
+        BlendOp::ReverseSubtract => dest_term - src_term,
+        BlendOp::Min => src.0.min(dest.0),
+        BlendOp::Max => src.0.max(dest.0),
+    })
+}
+
+fn blend_factor(rgb_factor: BlendFactor, alpha_factor: BlendFactor, src: ColorF, dest: ColorF)
+                -> F32x4 {
+    let factor = |factor: BlendFactor, channel: usize| {
+        match factor {
+            BlendFactor::Zero => 0.0,
+            BlendFactor::One => 1.0,
+            BlendFactor::SrcAlpha => src.a(),
+            BlendFactor::OneMinusSrcAlpha => 1.0 - src.a(),
+            BlendFactor::DestAlpha => dest.a(),
+            BlendFactor::OneMinusDestAlpha => 1.0 - dest.a(),
+            BlendFactor::DestColor => dest.0[channel],
+        }
+    };
+    F32x4::new(factor(rgb_factor, 0),
+               factor(rgb_factor, 1),
+               factor(rgb_factor, 2),
+               factor(alpha_factor, 3))
+}
+
+/// Converts a color to 8 bits per channel the way the GPU does when writing to an RGBA8 target.
+#[inline]
+pub(crate) fn color_to_u8(color: ColorF) -> ColorU {
+    let color = color.0.clamp(F32x4::splat(0.0), F32x4::splat(1.0)) * F32x4::splat(255.0) +
+        F32x4::splat(0.5);
+    let color = color.floor().to_i32x4();
+    ColorU { r: color[0] as u8, g: color[1] as u8, b: color[2] as u8, a: color[3] as u8 }
+}
+
+#[inline]
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[inline]
+fn map_rgb<F>(dest: [f32; 3], src: [f32; 3], f: F) -> [f32; 3] where F: Fn(f32, f32) -> f32 {
+    [f(dest[0], src[0]), f(dest[1], src[1]), f(dest[2], src[2])]
+}
+
+fn screen(dest: f32, src: f32) -> f32 {
+    dest + src - dest * src
+}
+
+fn hard_light(dest: f32, src: f32) -> f32 {
+    if src <= 0.5 {
+        dest * 2.0 * src
+    } else {
+        screen(dest, 2.0 * src - 1.0)
+    }
+}
+
+fn soft_light(dest: f32, src: f32) -> f32 {
+    let darkened_dest = if dest <= 0.25 {
+        ((16.0 * dest - 12.0) * dest + 4.0) * dest
+    } else {
+        f32::sqrt(dest)
+    };
+    let factor = if src <= 0.5 { dest * (1.0 - dest) } else { darkened_dest - dest };
+    dest + (src * 2.0 - 1.0) * factor
+}
+
+fn color_dodge(dest: f32, src: f32) -> f32 {
+    if dest == 0.0 {
+        0.0
+    } else if src == 1.0 {
+        1.0
+    } else {
+        dest / (1.0 - src)
+    }
+}
+
+fn divide(num: f32, denom: f32) -> f32 {
+    if denom != 0.0 { num / denom } else { 0.0 }
+}
+
+fn rgb_to_hsl(rgb: [f32; 3]) -> [f32; 3] {
+    let v = rgb[0].max(rgb[1]).max(rgb[2]);
+    let x_min = rgb[0].min(rgb[1]).min(rgb[2]);
+    let (c, l) = (v - x_min, lerp(x_min, v, 0.5));
+    let terms = if rgb[0] == v {
+        [0.0, rgb[1], rgb[2]]
+    } else if rgb[1] == v {
+        [2.0, rgb[2], rgb[0]]
+    } else {
+        [4.0, rgb[0], rgb[1]]
+    };
+    let h = FRAC_PI_3 * divide(terms[0] * c + terms[1] - terms[2], c);
+    let s = divide(c, v);
+    [h, s, l]
+}
+
+fn hsl_to_rgb(hsl: [f32; 3]) -> [f32; 3] {
+    let a = hsl[1] * hsl[2].min(1.0 - hsl[2]);
+    let channel = |n: f32| {
+        let k = (n + hsl[0] * FRAC_6_PI).rem_euclid(12.0);
+        hsl[2] - (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0) * a
+    };
+    [channel(0.0), channel(8.0), channel(4.0)]
+}
@@ -0,0 +1,22 @@
+// pathfinder/renderer/src/cpu/mod.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A software renderer for Pathfinder 3 that requires no GPU.
+//!
+//! This renderer consumes the same render commands that the D3D9-level GPU renderer does and
+//! rasterizes them into an RGBA buffer in system memory.
+
+pub mod renderer;
+
+mod composite;
+mod raster;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,161 @@
+// pathfinder/renderer/src/cpu/raster.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Coverage computation and texture sampling for the software renderer.
+
+use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
+use pathfinder_color::{ColorF, ColorU};
+use pathfinder_geometry::line_segment::LineSegmentU16;
+use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f};
+use pathfinder_gpu::TextureSamplingFlags;
+
+pub(crate) const MASK_TILE_LENGTH: usize = (TILE_WIDTH * TILE_HEIGHT) as usize;
+
+/// Accumulates the signed area of a fill into the mask of a single alpha tile.
+///
+/// This computes exactly what the fill program computes with the area lookup table: for each
+/// pixel, the horizontal extent of the line within the pixel column, multiplied by the fraction of
+/// the pixel that lies below the line. Lines going right to left contribute positive coverage.
+pub(crate) fn accumulate_fill(mask: &mut [f32], line_segment: &LineSegmentU16) {
+    let from = vec2f(line_segment.from_x as f32, line_segment.from_y as f32) * (1.0 / 256.0);
+    let to = vec2f(line_segment.to_x as f32, line_segment.to_y as f32) * (1.0 / 256.0);
+    if from.x() == to.x() {
+        return;
+    }
+
+    let (left, right, sign) = if from.x() < to.x() { (from, to, -1.0) } else { (to, from, 1.0) };
+    let slope = (right.y() - left.y()) / (right.x() - left.x());
+
+    let min_column = (f32::floor(left.x()) as i32).max(0);
+    let max_column = (f32::ceil(right.x()) as i32).min(TILE_WIDTH as i32);
+    for column in min_column..max_column {
+        let x0 = left.x().max(column as f32);
+        let x1 = right.x().min((column + 1) as f32);
+        if x1 <= x0 {
+            continue;
+        }
+
+        let y0 = left.y() + slope * (x0 - left.x());
+        let y1 = left.y() + slope * (x1 - left.x());
+        let width = x1 - x0;
+
+        for row in 0..(TILE_HEIGHT as i32) {
+            let area = area_below_line(y0 - row as f32, y1 - row as f32);
+            if area != 0.0 {
+                mask[row as usize * TILE_WIDTH as usize + column as usize] += sign * width * area;
+            }
+        }
+    }
+}
+
+// Returns the fraction of the unit pixel row [0, 1] that lies below a line going from height `a`
+// to height `b`, averaged over the width of the line.
+fn area_below_line(a: f32, b: f32) -> f32 {
+    if a <= 0.0 && b <= 0.0 {
+        return 1.0;
+    }
+    if a >= 1.0 && b >= 1.0 {
+        return 0.0;
+    }
+
+    // The covered fraction `clamp(1 - y, 0, 1)` is piecewise linear in `y`, so split the line
+    // where it crosses the top and bottom of the row and integrate each piece exactly.
+    let mut breakpoints = [0.0, 1.0, 1.0, 1.0];
+    let mut breakpoint_count = 1;
+    if a != b {
+        for &edge in &[0.0, 1.0] {
+            let t = (edge - a) / (b - a);
+            if t > 0.0 && t < 1.0 {
+                breakpoints[breakpoint_count] = t;
+                breakpoint_count += 1;
+            }
+        }
+    }
+    breakpoints[breakpoint_count] = 1.0;
+    breakpoints[..(breakpoint_count + 1)].sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let covered = |t: f32| (1.0 - (a + (b - a) * t)).clamp(0.0, 1.0);
+    let mut area = 0.0;
+    for pair in breakpoints[..(breakpoint_count + 1)].windows(2) {
+        area += (pair[1] - pair[0]) * 0.5 * (covered(pair[0]) + covered(pair[1]));
+    }
+    area
+}
+
+/// Converts winding-number coverage to alpha using the given fill rule, as the tile program does.
+#[inline]
+pub(crate) fn coverage_to_alpha(coverage: f32, even_odd: bool) -> f32 {
+    if even_odd {
+        1.0 - f32::abs(1.0 - coverage.rem_euclid(2.0))
+    } else {
+        f32::abs(coverage).min(1.0)
+    }
+}
+
+/// A read-only view of an RGBA8 texture.
+#[derive(Clone, Copy)]
+pub(crate) struct TextureView<'a> {
+    pub(crate) texels: &'a [ColorU],
+    pub(crate) size: Vector2I,
+}
+
+impl<'a> TextureView<'a> {
+    /// Samples the texture at the given normalized texture coordinates, following the same
+    /// filtering and wrapping rules that the GPU applies for the given sampling flags.
+    pub(crate) fn sample(&self, uv: Vector2F, flags: TextureSamplingFlags) -> ColorF {
+        if self.size.x() <= 0 || self.size.y() <= 0 {
+            return ColorF::transparent_black();
+        }
+
+        let position = uv * self.size.to_f32();
+        if flags.contains(TextureSamplingFlags::NEAREST_MAG) {
+            let texel = position.floor().to_i32();
+            return self.fetch(texel.x(), texel.y(), flags);
+        }
+
+        let position = position - vec2f(0.5, 0.5);
+        let origin = position.floor();
+        let fraction = position - origin;
+        let origin = origin.to_i32();
+
+        let upper_left = self.fetch(origin.x(), origin.y(), flags);
+        let upper_right = self.fetch(origin.x() + 1, origin.y(), flags);
+        let lower_left = self.fetch(origin.x(), origin.y() + 1, flags);
+        let lower_right = self.fetch(origin.x() + 1, origin.y() + 1, flags);
+
+        let upper = upper_left.lerp(upper_right, fraction.x());
+        let lower = lower_left.lerp(lower_right, fraction.x());
+        upper.lerp(lower, fraction.y())
+    }
+
+    fn fetch(&self, x: i32, y: i32, flags: TextureSamplingFlags) -> ColorF {
+        let x = wrap(x,
+                     self.size.x(),
+                     flags.contains(TextureSamplingFlags::REPEAT_U),
+                     flags.contains(TextureSamplingFlags::MIRROR_U));
+        let y = wrap(y,
+                     self.size.y(),
+                     flags.contains(TextureSamplingFlags::REPEAT_V),
+                     flags.contains(TextureSamplingFlags::MIRROR_V));
+        self.texels[y as usize * self.size.x() as usize + x as usize].to_f32()
+    }
+}
+
+#[inline]
+fn wrap(coord: i32, size: i32, repeat: bool, mirror: bool) -> i32 {
+    if mirror {
+        let coord = coord.rem_euclid(size * 2);
+        if coord < size { coord } else { size * 2 - 1 - coord }
+    } else if repeat {
+        coord.rem_euclid(size)
+    } else {
+        coord.max(0).min(size - 1)
+    }
+}
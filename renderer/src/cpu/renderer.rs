@@ -0,0 +1,516 @@
+// pathfinder/renderer/src/cpu/renderer.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The software renderer that processes commands necessary to render a scene.
+
+use crate::concurrent::executor::Executor;
+use crate::cpu::composite::{self, color_to_u8};
+use crate::cpu::raster::{self, MASK_TILE_LENGTH, TextureView};
+use crate::gpu::options::RendererLevel;
+use crate::gpu_data::{AlphaTileId, Clip, ColorCombineMode, DrawTileBatchD3D9, Fill};
+use crate::gpu_data::{RenderCommand, TILE_CTRL_MASK_EVEN_ODD, TILE_CTRL_MASK_WINDING};
+use crate::gpu_data::{TextureLocation, TextureMetadataEntry, TexturePageDescriptor};
+use crate::gpu_data::{TexturePageId, TILE_CTRL_MASK_0_SHIFT};
+use crate::options::{BuildOptions, RenderCommandListener};
+use crate::scene::{Scene, SceneSink};
+use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
+use pathfinder_color::{ColorF, ColorU};
+use pathfinder_content::pattern::Image;
+use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::{Vector2I, vec2f, vec2i};
+use pathfinder_simd::default::F32x4;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+const TILE_CTRL_MASK_MASK: i32 = 0x3;
+
+/// A renderer that executes D3D9-level render commands on the CPU.
+///
+/// This renderer follows the same tiling, fill, clipping, and compositing rules as the GPU
+/// renderer does at `RendererLevel::D3D9`, but it writes its output into an RGBA buffer in system
+/// memory. It is useful for rendering in environments with no GPU available.
+///
+/// D3D11-level render commands are not supported; when building a scene for this renderer, use
+/// `RendererLevel::D3D9`, as `build_and_render()` does.
+pub struct CPURenderer {
+    options: CPURendererOptions,
+    dest_framebuffer: TexturePage,
+    pattern_texture_pages: Vec<Option<TexturePage>>,
+    render_targets: Vec<TextureLocation>,
+    render_target_stack: Vec<RenderTargetId>,
+    texture_metadata: Vec<TextureMetadataEntry>,
+    alpha_tile_masks: Vec<f32>,
+}
+
+/// Options that influence rendering that can be changed at runtime.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CPURendererOptions {
+    /// The size of the destination framebuffer, in device pixels.
+    pub dest_size: Vector2I,
+    /// The background color. If not present, transparent is assumed.
+    pub background_color: Option<ColorF>,
+}
+
+struct TexturePage {
+    size: Vector2I,
+    texels: Vec<ColorU>,
+    must_preserve_contents: bool,
+}
+
+// Where tiles are drawn: either the destination framebuffer or a render target.
+struct DrawTarget<'a> {
+    page: &'a mut TexturePage,
+    viewport: RectI,
+    // Render targets are stored bottom-up, like OpenGL framebuffers, so that the texture
+    // transforms the paint system computes for them apply unchanged.
+    flip_y: bool,
+}
+
+impl CPURenderer {
+    /// Creates a new software renderer with the given options.
+    pub fn new(options: CPURendererOptions) -> CPURenderer {
+        let dest_framebuffer = TexturePage::new(options.dest_size);
+        CPURenderer {
+            options,
+            dest_framebuffer,
+            pattern_texture_pages: vec![],
+            render_targets: vec![],
+            render_target_stack: vec![],
+            texture_metadata: vec![],
+            alpha_tile_masks: vec![],
+        }
+    }
+
+    /// Performs work necessary to begin rendering a scene.
+    ///
+    /// This must be called before `render_command()`.
+    pub fn begin_scene(&mut self) {
+        if self.dest_framebuffer.size != self.options.dest_size {
+            self.dest_framebuffer = TexturePage::new(self.options.dest_size);
+        }
+        self.dest_framebuffer.must_preserve_contents = false;
+        self.alpha_tile_masks.clear();
+    }
+
+    /// Issues a rendering command to the renderer.
+    ///
+    /// These commands are generated from methods like `Scene::build()`.
+    ///
+    /// `begin_scene()` must have been called first.
+    pub fn render_command(&mut self, command: &RenderCommand) {
+        debug!("render command: {:?}", command);
+        match *command {
+            RenderCommand::Start { .. } => self.render_targets.clear(),
+            RenderCommand::AllocateTexturePage { page_id, ref descriptor } => {
+                self.allocate_pattern_texture_page(page_id, descriptor)
+            }
+            RenderCommand::UploadTexelData { ref texels, location } => {
+                self.upload_texel_data(texels, location)
+            }
+            RenderCommand::DeclareRenderTarget { id, location } => {
+                self.declare_render_target(id, location)
+            }
+            RenderCommand::UploadTextureMetadata(ref metadata) => {
+                self.texture_metadata = metadata.clone()
+            }
+            RenderCommand::AddFillsD3D9(ref fills) => self.add_fills(fills),
+            RenderCommand::FlushFillsD3D9 => {}
+            RenderCommand::PushRenderTarget(render_target_id) => {
+                self.render_target_stack.push(render_target_id)
+            }
+            RenderCommand::PopRenderTarget => {
+                self.render_target_stack.pop().expect("Render target stack underflow!");
+            }
+            RenderCommand::DrawTilesD3D9(ref batch) => self.draw_tiles(batch),
+            RenderCommand::UploadSceneD3D11 { .. } |
+            RenderCommand::PrepareClipTilesD3D11(_) |
+            RenderCommand::DrawTilesD3D11(_) => {
+                panic!("The CPU renderer doesn't support D3D11-level render commands!")
+            }
+            RenderCommand::Finish { .. } => {}
+        }
+    }
+
+    /// Finishes rendering a scene.
+    ///
+    /// `begin_scene()` and all `render_command()` calls must have been issued before calling this
+    /// method. Afterward, the rendered image can be retrieved with `pixels()`.
+    pub fn end_scene(&mut self) {
+        if !self.dest_framebuffer.must_preserve_contents {
+            let viewport = RectI::new(Vector2I::default(), self.dest_framebuffer.size);
+            let background_color = self.options.background_color.unwrap_or_default();
+            self.dest_framebuffer.clear(viewport, color_to_u8(background_color));
+        }
+    }
+
+    /// A convenience method to build a scene and render it with this renderer.
+    pub fn build_and_render<E>(&mut self,
+                               scene: &mut Scene,
+                               build_options: BuildOptions,
+                               executor: E)
+                               where E: Executor {
+        let commands = Arc::new(Mutex::new(vec![]));
+        let commands_for_listener = commands.clone();
+        let listener = RenderCommandListener::new(Box::new(move |command| {
+            commands_for_listener.lock().unwrap().push(command)
+        }));
+        let mut sink = SceneSink::new(listener, RendererLevel::D3D9);
+        scene.build(build_options, &mut sink, &executor);
+        let commands = mem::take(&mut *commands.lock().unwrap());
+
+        self.begin_scene();
+        commands.iter().for_each(|command| self.render_command(command));
+        self.end_scene();
+    }
+
+    /// Returns the current rendering options.
+    #[inline]
+    pub fn options(&self) -> &CPURendererOptions {
+        &self.options
+    }
+
+    /// Returns a mutable reference to the current rendering options, allowing them to be changed.
+    ///
+    /// A change to the destination size takes effect at the next call to `begin_scene()`.
+    #[inline]
+    pub fn options_mut(&mut self) -> &mut CPURendererOptions {
+        &mut self.options
+    }
+
+    /// Returns the size of the rendered image, in device pixels.
+    #[inline]
+    pub fn dest_size(&self) -> Vector2I {
+        self.dest_framebuffer.size
+    }
+
+    /// Returns the rendered image as premultiplied RGBA pixels in row-major order, top row first.
+    #[inline]
+    pub fn pixels(&self) -> &[ColorU] {
+        &self.dest_framebuffer.texels
+    }
+
+    /// Returns a copy of the rendered image with unpremultiplied alpha.
+    pub fn image(&self) -> Image {
+        let pixels = self.dest_framebuffer.texels.iter().map(|&pixel| {
+            if pixel.a == 0 || pixel.a == 255 {
+                return pixel;
+            }
+            let mut color = pixel.to_f32();
+            color.0 *= F32x4::splat(1.0 / color.a());
+            color.set_a(pixel.a as f32 / 255.0);
+            color_to_u8(color)
+        }).collect();
+        Image::new(self.dest_framebuffer.size, Arc::new(pixels))
+    }
+
+    fn allocate_pattern_texture_page(&mut self,
+                                     page_id: TexturePageId,
+                                     descriptor: &TexturePageDescriptor) {
+        // Fill in IDs up to the requested page ID.
+        let page_index = page_id.0 as usize;
+        while self.pattern_texture_pages.len() < page_index + 1 {
+            self.pattern_texture_pages.push(None);
+        }
+        self.pattern_texture_pages[page_index] = Some(TexturePage::new(descriptor.size));
+    }
+
+    fn upload_texel_data(&mut self, texels: &[ColorU], location: TextureLocation) {
+        let texture_page = self.pattern_texture_pages[location.page.0 as usize]
+                               .as_mut()
+                               .expect("Texture page not allocated yet!");
+        let rect = location.rect;
+        let width = rect.width() as usize;
+        for (row_index, row) in texels.chunks(width).take(rect.height() as usize).enumerate() {
+            let start = texture_page.texel_index(vec2i(rect.min_x(),
+                                                       rect.min_y() + row_index as i32));
+            texture_page.texels[start..(start + row.len())].copy_from_slice(row);
+        }
+        texture_page.must_preserve_contents = true;
+    }
+
+    fn declare_render_target(&mut self,
+                             render_target_id: RenderTargetId,
+                             location: TextureLocation) {
+        while self.render_targets.len() < render_target_id.render_target as usize + 1 {
+            self.render_targets.push(TextureLocation {
+                page: TexturePageId(!0),
+                rect: RectI::default(),
+            });
+        }
+        let render_target = &mut self.render_targets[render_target_id.render_target as usize];
+        debug_assert_eq!(render_target.page, TexturePageId(!0));
+        *render_target = location;
+    }
+
+    fn add_fills(&mut self, fills: &[Fill]) {
+        for fill in fills {
+            let mask_start = fill.link as usize * MASK_TILE_LENGTH;
+            if self.alpha_tile_masks.len() < mask_start + MASK_TILE_LENGTH {
+                self.alpha_tile_masks.resize(mask_start + MASK_TILE_LENGTH, 0.0);
+            }
+            let mask = &mut self.alpha_tile_masks[mask_start..(mask_start + MASK_TILE_LENGTH)];
+            raster::accumulate_fill(mask, &fill.line_segment);
+        }
+    }
+
+    fn clip_tiles(&mut self, clips: &[Clip]) {
+        // Like the GPU, compute every clipped mask from the masks as they were before the batch so
+        // that the order in which clips are applied doesn't matter, and write them back after.
+        let alpha_tile_masks = &self.alpha_tile_masks;
+        let mut clipped_masks = Vec::with_capacity(clips.len());
+        for clip in clips {
+            let dest_mask_start = match alpha_tile_mask_start(alpha_tile_masks, clip.dest_tile_id) {
+                None => continue,
+                Some(dest_mask_start) => dest_mask_start,
+            };
+            let src_mask_start = alpha_tile_mask_start(alpha_tile_masks, clip.src_tile_id);
+            let (dest_even_odd, src_even_odd) =
+                (mask_ctrl_is_even_odd(clip.dest_ctrl), mask_ctrl_is_even_odd(clip.src_ctrl));
+            let mut clipped_mask = [0.0; MASK_TILE_LENGTH];
+            for (pixel_index, clipped_alpha) in clipped_mask.iter_mut().enumerate() {
+                let dest =
+                    alpha_tile_masks[dest_mask_start + pixel_index] + clip.dest_backdrop as f32;
+                let src = match src_mask_start {
+                    None => 0.0,
+                    Some(src_mask_start) => alpha_tile_masks[src_mask_start + pixel_index],
+                } + clip.src_backdrop as f32;
+                *clipped_alpha = f32::min(raster::coverage_to_alpha(dest, dest_even_odd),
+                                          raster::coverage_to_alpha(src, src_even_odd));
+            }
+            clipped_masks.push((dest_mask_start, clipped_mask));
+        }
+
+        for (dest_mask_start, clipped_mask) in clipped_masks {
+            self.alpha_tile_masks[dest_mask_start..(dest_mask_start + MASK_TILE_LENGTH)]
+                .copy_from_slice(&clipped_mask);
+        }
+    }
+
+    fn draw_tiles(&mut self, batch: &DrawTileBatchD3D9) {
+        if !batch.clips.is_empty() {
+            self.clip_tiles(&batch.clips);
+        }
+
+        if batch.tiles.is_empty() {
+            return;
+        }
+
+        // Temporarily take the draw target out so that pattern pages can be sampled while it is
+        // being written to.
+        let (render_target_page_index, viewport) = match self.render_target_stack.last() {
+            None => (None, RectI::new(Vector2I::default(), self.dest_framebuffer.size)),
+            Some(&render_target_id) => {
+                let location = self.render_targets[render_target_id.render_target as usize];
+                (Some(location.page.0 as usize), location.rect)
+            }
+        };
+        let mut render_target_page = match render_target_page_index {
+            None => None,
+            Some(page_index) => {
+                Some(self.pattern_texture_pages[page_index]
+                         .take()
+                         .expect("Draw target texture page not allocated!"))
+            }
+        };
+
+        {
+            let (page, flip_y, clear_color) = match render_target_page {
+                None => {
+                    let background_color = self.options.background_color.unwrap_or_default();
+                    (&mut self.dest_framebuffer, false, background_color)
+                }
+                Some(ref mut page) => (page, true, ColorF::default()),
+            };
+            if !page.must_preserve_contents {
+                page.clear(viewport, color_to_u8(clear_color));
+                page.must_preserve_contents = true;
+            }
+
+            let draw_target = DrawTarget { page, viewport, flip_y };
+            let (pattern_texture_pages, alpha_tile_masks) =
+                (&self.pattern_texture_pages, &self.alpha_tile_masks);
+            let color_texture = batch.color_texture.and_then(|color_texture| {
+                pattern_texture_pages
+                    .get(color_texture.page.0 as usize)
+                    .and_then(|page| page.as_ref())
+                    .map(|page| (page.view(), color_texture.sampling_flags))
+            });
+
+            for tile in &batch.tiles {
+                let tile_coords = vec2i(tile.tile_x as i32, tile.tile_y as i32);
+                let z_value = batch.z_buffer_data.get(tile_coords).cloned().unwrap_or(0);
+                if (tile.path_id.0 as i32) < z_value {
+                    continue;
+                }
+                let mask_start = alpha_tile_mask_start(&self.alpha_tile_masks, tile.alpha_tile_id);
+                if mask_start.is_none() && tile.backdrop == 0 {
+                    continue;
+                }
+                let mask = mask_start.map(|mask_start| {
+                    &alpha_tile_masks[mask_start..(mask_start + MASK_TILE_LENGTH)]
+                });
+
+                let metadata = &self.texture_metadata[tile.color as usize];
+                let mask_ctrl = (tile.ctrl as i32 >> TILE_CTRL_MASK_0_SHIFT) & TILE_CTRL_MASK_MASK;
+                let tile_origin = tile_coords * vec2i(TILE_WIDTH as i32, TILE_HEIGHT as i32);
+
+                for y in 0..(TILE_HEIGHT as i32) {
+                    for x in 0..(TILE_WIDTH as i32) {
+                        let position = tile_origin + vec2i(x, y);
+                        let texel_index = match draw_target.texel_index(position) {
+                            None => continue,
+                            Some(texel_index) => texel_index,
+                        };
+
+                        let coverage = match mask {
+                            None => 0.0,
+                            Some(mask) => mask[(y * TILE_WIDTH as i32 + x) as usize],
+                        } + tile.backdrop as f32;
+                        let mask_alpha = if mask_ctrl == 0 {
+                            1.0
+                        } else if (mask_ctrl & TILE_CTRL_MASK_WINDING) != 0 {
+                            raster::coverage_to_alpha(coverage, false)
+                        } else {
+                            debug_assert_ne!(mask_ctrl & TILE_CTRL_MASK_EVEN_ODD, 0);
+                            raster::coverage_to_alpha(coverage, true)
+                        };
+
+                        let mut color = metadata.base_color.to_f32();
+                        match (metadata.color_0_combine_mode, color_texture) {
+                            (ColorCombineMode::None, _) | (_, None) => {}
+                            (combine_mode, Some((texture, sampling_flags))) => {
+                                let pixel_center = position.to_f32() + vec2f(0.5, 0.5);
+                                let tex_coord = metadata.color_0_transform * pixel_center;
+                                let color_0 = composite::filter_color(&metadata.filter,
+                                                                      texture,
+                                                                      sampling_flags,
+                                                                      tex_coord);
+                                color = combine_color_0(color, color_0, combine_mode);
+                            }
+                        }
+                        color.set_a(color.a() * mask_alpha);
+
+                        let dest = draw_target.page.texels[texel_index].to_f32();
+                        color = composite::composite(color, dest, metadata.blend_mode);
+                        let alpha = color.a();
+                        color = ColorF::new(color.r() * alpha,
+                                            color.g() * alpha,
+                                            color.b() * alpha,
+                                            alpha);
+
+                        let result = composite::blend(color, dest, batch.blend_mode);
+                        draw_target.page.texels[texel_index] = color_to_u8(result);
+                    }
+                }
+            }
+        }
+
+        if let (Some(page_index), Some(page)) = (render_target_page_index, render_target_page) {
+            self.pattern_texture_pages[page_index] = Some(page);
+        }
+    }
+
+}
+
+// Returns the offset of the mask for the given alpha tile, if that tile has received any fills.
+fn alpha_tile_mask_start(alpha_tile_masks: &[f32], alpha_tile_id: AlphaTileId) -> Option<usize> {
+    if !alpha_tile_id.is_valid() {
+        return None;
+    }
+    let mask_start = alpha_tile_id.0 as usize * MASK_TILE_LENGTH;
+    if mask_start + MASK_TILE_LENGTH <= alpha_tile_masks.len() {
+        Some(mask_start)
+    } else {
+        None
+    }
+}
+
+// Returns true if the given clip mask control bits select the even-odd fill rule.
+fn mask_ctrl_is_even_odd(mask_ctrl: i16) -> bool {
+    ((mask_ctrl as i32 >> TILE_CTRL_MASK_0_SHIFT) & TILE_CTRL_MASK_EVEN_ODD) != 0
+}
+
+fn combine_color_0(dest_color: ColorF, src_color: ColorF, combine_mode: ColorCombineMode)
+                   -> ColorF {
+    match combine_mode {
+        ColorCombineMode::None => dest_color,
+        ColorCombineMode::SrcIn => {
+            ColorF::new(src_color.r(),
+                        src_color.g(),
+                        src_color.b(),
+                        src_color.a() * dest_color.a())
+        }
+        ColorCombineMode::DestIn => {
+            ColorF::new(dest_color.r(),
+                        dest_color.g(),
+                        dest_color.b(),
+                        src_color.a() * dest_color.a())
+        }
+    }
+}
+
+impl TexturePage {
+    fn new(size: Vector2I) -> TexturePage {
+        let texel_count = size.x().max(0) as usize * size.y().max(0) as usize;
+        TexturePage {
+            size,
+            texels: vec![ColorU::default(); texel_count],
+            must_preserve_contents: false,
+        }
+    }
+
+    #[inline]
+    fn texel_index(&self, position: Vector2I) -> usize {
+        position.y() as usize * self.size.x() as usize + position.x() as usize
+    }
+
+    fn clear(&mut self, rect: RectI, color: ColorU) {
+        let rect = match rect.intersection(RectI::new(Vector2I::default(), self.size)) {
+            None => return,
+            Some(rect) => rect,
+        };
+        for y in rect.min_y()..rect.max_y() {
+            let start = self.texel_index(vec2i(rect.min_x(), y));
+            let end = start + rect.width() as usize;
+            for texel in &mut self.texels[start..end] {
+                *texel = color;
+            }
+        }
+    }
+
+    #[inline]
+    fn view<'a>(&'a self) -> TextureView<'a> {
+        TextureView { texels: &self.texels, size: self.size }
+    }
+}
+
+impl<'a> DrawTarget<'a> {
+    // Returns the index of the texel corresponding to the given position within the viewport, if
+    // the position is inside it.
+    fn texel_index(&self, position: Vector2I) -> Option<usize> {
+        if position.x() < 0 || position.y() < 0 ||
+                position.x() >= self.viewport.width() ||
+                position.y() >= self.viewport.height() {
+            return None;
+        }
+        let y = if self.flip_y {
+            self.viewport.max_y() - 1 - position.y()
+        } else {
+            self.viewport.min_y() + position.y()
+        };
+        let position = vec2i(self.viewport.min_x() + position.x(), y);
+        if position.x() >= self.page.size.x() || position.y() < 0 ||
+                position.y() >= self.page.size.y() {
+            return None;
+        }
+        Some(self.page.texel_index(position))
+    }
+}
@@ -0,0 +1,240 @@
+// pathfinder/renderer/src/cpu/tests.rs
+//
+// For this file only, any copyright is dedicated to the Public Domain.
+// https://creativecommons.org/publicdomain/zero/1.0/
+
+use crate::concurrent::executor::SequentialExecutor;
+use crate::cpu::renderer::{CPURenderer, CPURendererOptions};
+use crate::options::BuildOptions;
+use crate::paint::Paint;
+use crate::scene::{ClipPath, DrawPath, Scene};
+use pathfinder_color::{ColorF, ColorU};
+use pathfinder_content::effects::BlendMode;
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::gradient::{Gradient, GradientWrap};
+use pathfinder_content::outline::{Contour, Outline};
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::vector::{Vector2I, vec2f, vec2i};
+
+fn render(scene: &mut Scene, size: Vector2I, background_color: Option<ColorF>) -> CPURenderer {
+    scene.set_view_box(RectF::new(vec2f(0.0, 0.0), size.to_f32()));
+    let mut renderer = CPURenderer::new(CPURendererOptions { dest_size: size, background_color });
+    renderer.build_and_render(scene, BuildOptions::default(), SequentialExecutor);
+    renderer
+}
+
+fn pixel(renderer: &CPURenderer, x: i32, y: i32) -> ColorU {
+    renderer.pixels()[(y * renderer.dest_size().x() + x) as usize]
+}
+
+#[test]
+fn test_fill_rect() {
+    let mut scene = Scene::new();
+    let paint = scene.push_paint(&Paint::from_color(ColorU::new(255, 0, 0, 255)));
+    let outline = Outline::from_rect(RectF::new(vec2f(20.5, 10.0), vec2f(30.0, 30.0)));
+    scene.push_draw_path(DrawPath::new(outline, paint));
+
+    let renderer = render(&mut scene, vec2i(64, 64), None);
+    assert_eq!(pixel(&renderer, 30, 20), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&renderer, 49, 39), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&renderer, 5, 5), ColorU::transparent_black());
+    assert_eq!(pixel(&renderer, 51, 20), ColorU::transparent_black());
+
+    // The left and right edges are half-covered.
+    assert_eq!(pixel(&renderer, 20, 20).a, 128);
+    assert_eq!(pixel(&renderer, 50, 20).a, 128);
+}
+
+#[test]
+fn test_fill_rule_even_odd() {
+    let mut scene = Scene::new();
+    let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+    let mut outline = Outline::new();
+    outline.push_contour(Contour::from_rect(RectF::new(vec2f(8.0, 8.0), vec2f(48.0, 48.0))));
+    outline.push_contour(Contour::from_rect(RectF::new(vec2f(20.0, 20.0), vec2f(24.0, 24.0))));
+    let mut draw_path = DrawPath::new(outline, paint);
+    draw_path.set_fill_rule(FillRule::EvenOdd);
+    scene.push_draw_path(draw_path);
+
+    let renderer = render(&mut scene, vec2i(64, 64), Some(ColorF::white()));
+    assert_eq!(pixel(&renderer, 10, 10), ColorU::black());
+    assert_eq!(pixel(&renderer, 32, 32), ColorU::white());
+    assert_eq!(pixel(&renderer, 60, 60), ColorU::white());
+}
+
+#[test]
+fn test_nested_clip_paths() {
+    // The clip paths overlap between x = 36 and x = 40, in a tile where both have masks.
+    let mut scene = Scene::new();
+    let outer = Outline::from_rect(RectF::new(vec2f(8.0, 0.0), vec2f(32.0, 64.0)));
+    let outer = scene.push_clip_path(ClipPath::new(outer));
+    let inner = Outline::from_rect(RectF::new(vec2f(36.0, 0.0), vec2f(20.0, 64.0)));
+    let mut inner = ClipPath::new(inner);
+    inner.set_clip_path(Some(outer));
+    let inner = scene.push_clip_path(inner);
+
+    let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+    let mut draw_path = DrawPath::new(Outline::from_rect(RectF::new(vec2f(0.0, 0.0),
+                                                                    vec2f(64.0, 64.0))),
+                                      paint);
+    draw_path.set_clip_path(Some(inner));
+    scene.push_draw_path(draw_path);
+
+    let renderer = render(&mut scene, vec2i(64, 64), Some(ColorF::white()));
+    assert_eq!(pixel(&renderer, 38, 32), ColorU::black());
+    assert_eq!(pixel(&renderer, 20, 32), ColorU::white());
+    assert_eq!(pixel(&renderer, 34, 32), ColorU::white());
+    assert_eq!(pixel(&renderer, 42, 32), ColorU::white());
+}
+
+#[test]
+fn test_clip_path_even_odd() {
+    let mut scene = Scene::new();
+    let mut outline = Outline::new();
+    outline.push_contour(Contour::from_rect(RectF::new(vec2f(8.0, 8.0), vec2f(48.0, 48.0))));
+    outline.push_contour(Contour::from_rect(RectF::new(vec2f(8.0, 8.0), vec2f(48.0, 48.0))));
+    let mut clip_path = ClipPath::new(outline);
+    clip_path.set_fill_rule(FillRule::EvenOdd);
+    let clip_path = scene.push_clip_path(clip_path);
+
+    let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+    let mut draw_path = DrawPath::new(Outline::from_rect(RectF::new(vec2f(-16.0, -16.0),
+                                                                    vec2f(96.0, 96.0))),
+                                      paint);
+    draw_path.set_clip_path(Some(clip_path));
+    scene.push_draw_path(draw_path);
+
+    // The two contours cancel out, both where the clip path has masks and where it's solid.
+    let renderer = render(&mut scene, vec2i(64, 64), Some(ColorF::white()));
+    assert_eq!(pixel(&renderer, 10, 10), ColorU::white());
+    assert_eq!(pixel(&renderer, 32, 32), ColorU::white());
+}
+
+#[test]
+fn test_clip_tile_combine_fill_rules() {
+    // The draw paths' edges lie in the same tiles as the clip paths' edges, so both tiles have
+    // masks. Each mask must be resolved with its own fill rule when they are combined.
+    let doubled_rect = |origin, size| {
+        let mut outline = Outline::new();
+        outline.push_contour(Contour::from_rect(RectF::new(origin, size)));
+        outline.push_contour(Contour::from_rect(RectF::new(origin, size)));
+        outline
+    };
+    let draw_rect = RectF::new(vec2f(4.5, 4.5), vec2f(55.0, 55.0));
+    let clip_rect = RectF::new(vec2f(8.0, 8.0), vec2f(48.0, 48.0));
+
+    // An even-odd clip path whose contours cancel out.
+    let mut scene = Scene::new();
+    let mut clip_path = ClipPath::new(doubled_rect(clip_rect.origin(), clip_rect.size()));
+    clip_path.set_fill_rule(FillRule::EvenOdd);
+    let clip_path = scene.push_clip_path(clip_path);
+    let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+    let mut draw_path = DrawPath::new(Outline::from_rect(draw_rect), paint);
+    draw_path.set_clip_path(Some(clip_path));
+    scene.push_draw_path(draw_path);
+    let renderer = render(&mut scene, vec2i(64, 64), Some(ColorF::white()));
+    assert_eq!(pixel(&renderer, 10, 10), ColorU::white());
+    assert_eq!(pixel(&renderer, 32, 32), ColorU::white());
+
+    // An even-odd draw path whose contours cancel out, clipped by a nonzero clip path.
+    let mut scene = Scene::new();
+    let clip_path = scene.push_clip_path(ClipPath::new(Outline::from_rect(clip_rect)));
+    let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+    let mut draw_path = DrawPath::new(doubled_rect(draw_rect.origin(), draw_rect.size()), paint);
+    draw_path.set_fill_rule(FillRule::EvenOdd);
+    draw_path.set_clip_path(Some(clip_path));
+    scene.push_draw_path(draw_path);
+    let renderer = render(&mut scene, vec2i(64, 64), Some(ColorF::white()));
+    assert_eq!(pixel(&renderer, 10, 10), ColorU::white());
+    assert_eq!(pixel(&renderer, 32, 32), ColorU::white());
+
+    // A nonzero draw path with a nonzero clip path still covers their intersection.
+    let mut scene = Scene::new();
+    let clip_path = scene.push_clip_path(ClipPath::new(Outline::from_rect(clip_rect)));
+    let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+    let mut draw_path = DrawPath::new(doubled_rect(draw_rect.origin(), draw_rect.size()), paint);
+    draw_path.set_clip_path(Some(clip_path));
+    scene.push_draw_path(draw_path);
+    let renderer = render(&mut scene, vec2i(64, 64), Some(ColorF::white()));
+    assert_eq!(pixel(&renderer, 10, 10), ColorU::black());
+    assert_eq!(pixel(&renderer, 6, 6), ColorU::white());
+}
+
+#[test]
+fn test_blend_mode_batch_break() {
+    // A path with a different blend mode must not join the batch of the path before it.
+    let mut scene = Scene::new();
+    let red = scene.push_paint(&Paint::from_color(ColorU::new(255, 0, 0, 255)));
+    let outline = Outline::from_rect(RectF::new(vec2f(0.0, 0.0), vec2f(64.0, 64.0)));
+    scene.push_draw_path(DrawPath::new(outline, red));
+    let half = scene.push_paint(&Paint::from_color(ColorU::new(0, 0, 255, 128)));
+    let outline = Outline::from_rect(RectF::new(vec2f(0.0, 0.0), vec2f(32.0, 64.0)));
+    let mut draw_path = DrawPath::new(outline, half);
+    draw_path.set_blend_mode(BlendMode::DestIn);
+    scene.push_draw_path(draw_path);
+
+    let renderer = render(&mut scene, vec2i(64, 64), None);
+    let masked = pixel(&renderer, 16, 32);
+    assert_eq!((masked.r, masked.g, masked.b), (128, 0, 0));
+    assert_eq!(masked.a, 128);
+    assert_eq!(pixel(&renderer, 48, 32), ColorU::new(255, 0, 0, 255));
+}
+
+#[test]
+fn test_rasterize_to_png() {
+    let mut scene = Scene::new();
+    scene.set_view_box(RectF::new(vec2f(0.0, 0.0), vec2f(16.0, 16.0)));
+    let paint = scene.push_paint(&Paint::from_color(ColorU::new(0, 0, 255, 128)));
+    let outline = Outline::from_rect(RectF::new(vec2f(0.0, 0.0), vec2f(8.0, 16.0)));
+    scene.push_draw_path(DrawPath::new(outline, paint));
+
+    let mut png = vec![];
+    scene.rasterize_to_png(vec2i(16, 16), BuildOptions::default(), &mut png).unwrap();
+    let image = image::load_from_memory(&png).unwrap().to_rgba8();
+    assert_eq!(image.dimensions(), (16, 16));
+    assert_eq!(image.get_pixel(2, 2).0, [0, 0, 255, 128]);
+    assert_eq!(image.get_pixel(12, 2).0, [0, 0, 0, 0]);
+}
+
+#[test]
+fn test_conic_gradient() {
+    let mut scene = Scene::new();
+    let mut gradient = Gradient::conic(vec2f(32.0, 32.0), 0.0);
+    gradient.add_color_stop(ColorU::new(255, 0, 0, 255), 0.0);
+    gradient.add_color_stop(ColorU::new(0, 0, 255, 255), 1.0);
+    let paint = scene.push_paint(&Paint::from_gradient(gradient));
+    let outline = Outline::from_rect(RectF::new(vec2f(0.0, 0.0), vec2f(64.0, 64.0)));
+    scene.push_draw_path(DrawPath::new(outline, paint));
+
+    let renderer = render(&mut scene, vec2i(64, 64), None);
+
+    // The gradient sweeps clockwise from the positive X axis, so it is red just below the X axis,
+    // halfway through on the left, and blue just above the X axis.
+    let below = pixel(&renderer, 60, 33);
+    assert!(below.r > 240 && below.b < 15, "{:?}", below);
+    let left = pixel(&renderer, 4, 32);
+    assert!((left.r as i32 - 128).abs() < 16 && (left.b as i32 - 128).abs() < 16, "{:?}", left);
+    let above = pixel(&renderer, 60, 31);
+    assert!(above.r < 15 && above.b > 240, "{:?}", above);
+}
+
+#[test]
+fn test_reflected_gradient() {
+    let mut scene = Scene::new();
+    let mut gradient = Gradient::linear_from_points(vec2f(0.0, 0.0), vec2f(32.0, 0.0));
+    gradient.add_color_stop(ColorU::black(), 0.0);
+    gradient.add_color_stop(ColorU::white(), 1.0);
+    gradient.wrap = GradientWrap::Reflect;
+    let paint = scene.push_paint(&Paint::from_gradient(gradient));
+    let outline = Outline::from_rect(RectF::new(vec2f(0.0, 0.0), vec2f(64.0, 4.0)));
+    scene.push_draw_path(DrawPath::new(outline, paint));
+
+    // The second half mirrors the first.
+    let renderer = render(&mut scene, vec2i(64, 4), None);
+    for x in 0..32 {
+        let (left, right) = (pixel(&renderer, x, 2), pixel(&renderer, 63 - x, 2));
+        assert!((left.r as i32 - right.r as i32).abs() <= 1, "{}: {:?} {:?}", x, left, right);
+    }
+    assert!(pixel(&renderer, 1, 2).r < 16);
+    assert!(pixel(&renderer, 32, 2).r > 240);
+}
@@ -87,6 +87,11 @@ impl<D> RendererD3D9<D> where D: Device {
             core.allocator.free_general_buffer(clip_buffer_info.clip_buffer_id);
         }
 
+        // Batches that intersect nested clip paths have no tiles of their own.
+        if batch.tiles.is_empty() {
+            return;
+        }
+
         let tile_buffer = self.upload_tiles(core, &batch.tiles);
         let z_buffer_texture_id = self.upload_z_buffer(core, &batch.z_buffer_data);
 
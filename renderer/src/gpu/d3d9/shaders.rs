@@ -220,10 +220,14 @@ impl<D> ClipTileCombineVertexArrayD3D9<D> where D: Device {
             device.get_vertex_attr(&clip_tile_combine_program.program, "DestTileIndex").unwrap();
         let dest_backdrop_attr =
             device.get_vertex_attr(&clip_tile_combine_program.program, "DestBackdrop").unwrap();
+        let dest_ctrl_attr =
+            device.get_vertex_attr(&clip_tile_combine_program.program, "DestCtrl").unwrap();
         let src_tile_index_attr =
             device.get_vertex_attr(&clip_tile_combine_program.program, "SrcTileIndex").unwrap();
         let src_backdrop_attr =
             device.get_vertex_attr(&clip_tile_combine_program.program, "SrcBackdrop").unwrap();
+        let src_ctrl_attr =
+            device.get_vertex_attr(&clip_tile_combine_program.program, "SrcCtrl").unwrap();
 
         device.bind_buffer(&vertex_array, quad_vertex_positions_buffer, BufferTarget::Vertex);
         device.configure_vertex_attr(&vertex_array, &tile_offset_attr, &VertexAttrDescriptor {
@@ -248,12 +252,21 @@ impl<D> ClipTileCombineVertexArrayD3D9<D> where D: Device {
         device.configure_vertex_attr(&vertex_array, &dest_backdrop_attr, &VertexAttrDescriptor {
             size: 1,
             class: VertexAttrClass::Int,
-            attr_type: VertexAttrType::I32,
+            attr_type: VertexAttrType::I16,
             stride: CLIP_TILE_INSTANCE_SIZE,
             offset: 4,
             divisor: 1,
             buffer_index: 1,
         });
+        device.configure_vertex_attr(&vertex_array, &dest_ctrl_attr, &VertexAttrDescriptor {
+            size: 1,
+            class: VertexAttrClass::Int,
+            attr_type: VertexAttrType::I16,
+            stride: CLIP_TILE_INSTANCE_SIZE,
+            offset: 6,
+            divisor: 1,
+            buffer_index: 1,
+        });
         device.configure_vertex_attr(&vertex_array, &src_tile_index_attr, &VertexAttrDescriptor {
             size: 1,
             class: VertexAttrClass::Int,
@@ -266,12 +279,21 @@ impl<D> ClipTileCombineVertexArrayD3D9<D> where D: Device {
         device.configure_vertex_attr(&vertex_array, &src_backdrop_attr, &VertexAttrDescriptor {
             size: 1,
             class: VertexAttrClass::Int,
-            attr_type: VertexAttrType::I32,
+            attr_type: VertexAttrType::I16,
             stride: CLIP_TILE_INSTANCE_SIZE,
             offset: 12,
             divisor: 1,
             buffer_index: 1,
         });
+        device.configure_vertex_attr(&vertex_array, &src_ctrl_attr, &VertexAttrDescriptor {
+            size: 1,
+            class: VertexAttrClass::Int,
+            attr_type: VertexAttrType::I16,
+            stride: CLIP_TILE_INSTANCE_SIZE,
+            offset: 14,
+            divisor: 1,
+            buffer_index: 1,
+        });
         device.bind_buffer(&vertex_array, quad_vertex_indices_buffer, BufferTarget::Index);
 
         ClipTileCombineVertexArrayD3D9 { vertex_array }
@@ -19,3 +19,6 @@ pub mod renderer;
 
 pub(crate) mod blend;
 pub(crate) mod shaders;
+
+#[cfg(test)]
+mod tests;
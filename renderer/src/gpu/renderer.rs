@@ -64,6 +64,7 @@ const COMBINER_CTRL_FILTER_RADIAL_GRADIENT: i32 =   0x1;
 const COMBINER_CTRL_FILTER_TEXT: i32 =              0x2;
 const COMBINER_CTRL_FILTER_BLUR: i32 =              0x3;
 const COMBINER_CTRL_FILTER_COLOR_MATRIX: i32 =      0x4;
+const COMBINER_CTRL_FILTER_CONIC_GRADIENT: i32 =    0x5;
 
 const COMBINER_CTRL_COLOR_FILTER_SHIFT: i32 =       4;
 const COMBINER_CTRL_COLOR_COMBINE_SHIFT: i32 =      8;
@@ -938,6 +939,17 @@ impl<D> Renderer<D> where D: Device {
                                   COMBINER_CTRL_COLOR_FILTER_SHIFT)
                 }
             }
+            Filter::ConicGradient { center, start_angle, uv_origin } => {
+                FilterParams {
+                    p0: center.0.concat_xy_xy(F32x2::new(start_angle, 0.0)),
+                    p1: uv_origin.0.concat_xy_xy(F32x2::default()),
+                    p2: F32x4::default(),
+                    p3: F32x4::default(),
+                    p4: F32x4::default(),
+                    ctrl: ctrl | (COMBINER_CTRL_FILTER_CONIC_GRADIENT <<
+                                  COMBINER_CTRL_COLOR_FILTER_SHIFT)
+                }
+            }
             Filter::PatternFilter(PatternFilter::Blur { sigma, direction }) => {
                 let sigma_inv = 1.0 / sigma;
                 let gauss_coeff_x = SQRT_2_PI_INV * sigma_inv;
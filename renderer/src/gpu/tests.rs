@@ -0,0 +1,59 @@
+// pathfinder/renderer/src/gpu/tests.rs
+//
+// For this file only, any copyright is dedicated to the Public Domain.
+// https://creativecommons.org/publicdomain/zero/1.0/
+
+use crate::concurrent::executor::SequentialExecutor;
+use crate::gpu::options::{DestFramebuffer, RendererLevel, RendererMode, RendererOptions};
+use crate::gpu::renderer::Renderer;
+use crate::options::BuildOptions;
+use crate::paint::Paint;
+use crate::scene::{DrawPath, Scene};
+use pathfinder_color::ColorU;
+use pathfinder_content::outline::Outline;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::vector::{vec2f, vec2i};
+use pathfinder_gpu::FeatureLevel;
+use pathfinder_gpu::recording::{ObjectKind, RecordedCall, RecordingDevice};
+use pathfinder_resources::embedded::EmbeddedResourceLoader;
+
+fn new_renderer() -> Renderer<RecordingDevice> {
+    let device = RecordingDevice::new(FeatureLevel::D3D10);
+    let options = RendererOptions {
+        dest: DestFramebuffer::full_window(vec2i(64, 64)),
+        ..RendererOptions::default()
+    };
+    Renderer::new(device,
+                  &EmbeddedResourceLoader::new(),
+                  RendererMode { level: RendererLevel::D3D9 },
+                  options)
+}
+
+#[test]
+fn test_render_scene_to_recording_device() {
+    let mut renderer = new_renderer();
+    renderer.device().take_calls();
+
+    let mut scene = Scene::new();
+    scene.set_view_box(RectF::new(vec2f(0.0, 0.0), vec2f(64.0, 64.0)));
+    let paint = scene.push_paint(&Paint::from_color(ColorU::black()));
+    let outline = Outline::from_rect(RectF::new(vec2f(8.5, 8.5), vec2f(32.0, 32.0)));
+    scene.push_draw_path(DrawPath::new(outline, paint));
+    scene.build_and_render(&mut renderer, BuildOptions::default(), SequentialExecutor);
+
+    let calls = renderer.device().take_calls();
+    assert!(matches!(calls.first(), Some(RecordedCall::BeginCommands)));
+    assert!(matches!(calls.last(), Some(RecordedCall::EndCommands)));
+    assert!(calls.iter().any(|call| {
+        matches!(*call, RecordedCall::DrawElementsInstanced { ref state, .. }
+                 if state.target.is_none())
+    }));
+}
+
+#[test]
+fn test_renderer_releases_gpu_objects() {
+    let device = new_renderer().destroy();
+    for &kind in &[ObjectKind::Buffer, ObjectKind::Framebuffer, ObjectKind::Texture] {
+        assert_eq!(device.live_object_count(kind), 0, "{:?} leaked", kind);
+    }
+}
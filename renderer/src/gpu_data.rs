@@ -365,13 +365,18 @@ pub struct ClipMetadata {
     pub pad1: u32,
 }
 
+/// A job that combines the mask of a draw tile with the mask of the clip tile beneath it.
+///
+/// Each mask is resolved with its own fill rule, given by its `TILE_CTRL_MASK_*` bits.
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
 pub struct Clip {
     pub dest_tile_id: AlphaTileId,
-    pub dest_backdrop: i32,
+    pub dest_backdrop: i16,
+    pub dest_ctrl: i16,
     pub src_tile_id: AlphaTileId,
-    pub src_backdrop: i32,
+    pub src_backdrop: i16,
+    pub src_ctrl: i16,
 }
 
 impl Default for Clip {
@@ -380,8 +385,10 @@ impl Default for Clip {
         Clip {
             dest_tile_id: AlphaTileId(!0),
             dest_backdrop: 0,
+            dest_ctrl: 0,
             src_tile_id: AlphaTileId(!0),
             src_backdrop: 0,
+            src_ctrl: 0,
         }
     }
 }
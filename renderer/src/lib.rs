@@ -18,6 +18,7 @@ extern crate bitflags;
 extern crate log;
 
 pub mod concurrent;
+pub mod cpu;
 pub mod gpu;
 pub mod options;
 pub mod paint;
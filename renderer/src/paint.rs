@@ -70,7 +70,7 @@ pub struct PaintOverlay {
 /// The contents of an overlay: either a gradient or a pattern.
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub(crate) enum PaintContents {
-    /// A gradient, either linear or radial.
+    /// A gradient: linear, radial, or conic.
     Gradient(Gradient),
     /// A raster image pattern.
     Pattern(Pattern),
@@ -369,6 +369,12 @@ pub(crate) enum PaintFilter {
         /// The radii of the two circles.
         radii: F32x2,
     },
+    ConicGradient {
+        /// The point that the gradient sweeps around.
+        center: Vector2F,
+        /// The angle at which the gradient starts, in radians.
+        start_angle: f32,
+    },
     PatternFilter(PatternFilter),
 }
 
@@ -467,6 +473,9 @@ impl Palette {
                                 GradientWrap::Repeat => {
                                     sampling_flags.insert(TextureSamplingFlags::REPEAT_U);
                                 }
+                                GradientWrap::Reflect => {
+                                    sampling_flags.insert(TextureSamplingFlags::MIRROR_U);
+                                }
                                 GradientWrap::Clamp => {}
                             }
 
@@ -485,6 +494,9 @@ impl Palette {
                                     GradientGeometry::Radial { line, radii, .. } => {
                                         PaintFilter::RadialGradient { line, radii }
                                     }
+                                    GradientGeometry::Conic { center, start_angle, .. } => {
+                                        PaintFilter::ConicGradient { center, start_angle }
+                                    }
                                 },
                                 transform: Transform2F::default(),
                                 composite_op: overlay.composite_op(),
@@ -603,6 +615,10 @@ impl Palette {
                 PaintContents::Gradient(Gradient {
                     geometry: GradientGeometry::Radial { ref transform, .. },
                     ..
+                }) |
+                PaintContents::Gradient(Gradient {
+                    geometry: GradientGeometry::Conic { ref transform, .. },
+                    ..
                 }) => transform.inverse(),
                 PaintContents::Pattern(ref pattern) => {
                     match pattern.source() {
@@ -780,6 +796,12 @@ impl PaintMetadata {
                             vec2f(0.0, color_metadata.page_scale.y() * 0.5));
                         Filter::RadialGradient { line, radii, uv_origin: uv_rect.origin() }
                     }
+                    PaintFilter::ConicGradient { center, start_angle } => {
+                        let uv_rect = rect_to_uv(color_metadata.location.rect,
+                                                 color_metadata.page_scale).contract(
+                            vec2f(0.0, color_metadata.page_scale.y() * 0.5));
+                        Filter::ConicGradient { center, start_angle, uv_origin: uv_rect.origin() }
+                    }
                     PaintFilter::PatternFilter(pattern_filter) => {
                         Filter::PatternFilter(pattern_filter)
                     }
@@ -11,21 +11,27 @@
 //! The vector scene to be rendered.
 
 use crate::builder::SceneBuilder;
-use crate::concurrent::executor::Executor;
+use crate::concurrent::executor::{Executor, SequentialExecutor};
+use crate::cpu::renderer::{CPURenderer, CPURendererOptions};
 use crate::gpu::options::RendererLevel;
 use crate::gpu::renderer::Renderer;
 use crate::gpu_data::RenderCommand;
 use crate::options::{BuildOptions, PreparedBuildOptions};
 use crate::options::{PreparedRenderTransform, RenderCommandListener};
 use crate::paint::{MergedPaletteInfo, Paint, PaintId, PaintInfo, PaintTextureManager, Palette};
+use image::png::PngEncoder;
+use image::{ColorType, ImageResult};
 use pathfinder_content::effects::BlendMode;
 use pathfinder_content::fill::FillRule;
 use pathfinder_content::outline::Outline;
+use pathfinder_content::pattern::{Image, PatternSource};
 use pathfinder_content::render_target::RenderTargetId;
 use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::transform2d::Transform2F;
-use pathfinder_geometry::vector::{Vector2I, vec2f};
+use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f};
 use pathfinder_gpu::Device;
+use std::collections::HashMap;
+use std::io::Write;
 use std::mem;
 use std::ops::Range;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -296,11 +302,42 @@ impl Scene {
         SceneBuilder::new(self, &prepared_options, sink).build(executor)
     }
 
+    /// Returns the display list, which describes which render targets the draw paths are drawn
+    /// to, in order.
     #[inline]
-    pub(crate) fn display_list(&self) -> &[DisplayItem] {
+    pub fn display_list(&self) -> &[DisplayItem] {
         &self.display_list
     }
 
+    /// Splits the display list into the ranges of draw paths drawn to the main framebuffer and
+    /// those drawn to each render target, in order.
+    pub fn draw_path_ranges(&self) -> DrawPathRanges {
+        let mut render_target_stack = vec![];
+        let mut ranges = DrawPathRanges::default();
+        for display_item in &self.display_list {
+            match *display_item {
+                DisplayItem::PushRenderTarget(render_target_id) => {
+                    render_target_stack.push(render_target_id)
+                }
+                DisplayItem::PopRenderTarget => {
+                    render_target_stack.pop();
+                }
+                DisplayItem::DrawPaths(ref range) => {
+                    match render_target_stack.last() {
+                        None => ranges.main.push(range.clone()),
+                        Some(render_target_id) => {
+                            ranges.render_targets
+                                  .entry(*render_target_id)
+                                  .or_default()
+                                  .push(range.clone())
+                        }
+                    }
+                }
+            }
+        }
+        ranges
+    }
+
     #[inline]
     pub(crate) fn draw_paths(&self) -> &[DrawPath] {
         &self.draw_paths
@@ -376,6 +413,117 @@ impl Scene {
         commands.into_iter().for_each(|command| renderer.render_command(&command));
         renderer.end_scene();
     }
+
+    /// Renders this scene offscreen and returns the resulting image.
+    ///
+    /// The image has the given size in device pixels, a transparent background, and
+    /// unpremultiplied alpha. Rendering is done on the CPU, so no GPU device is needed.
+    pub fn rasterize(&mut self, size: Vector2I, build_options: BuildOptions) -> Image {
+        let mut renderer = CPURenderer::new(CPURendererOptions {
+            dest_size: size,
+            background_color: None,
+        });
+        renderer.build_and_render(self, build_options, SequentialExecutor);
+        renderer.image()
+    }
+
+    /// Renders this scene offscreen, as `rasterize()` does, and writes the result to the given
+    /// writer as a PNG.
+    pub fn rasterize_to_png<W>(&mut self,
+                               size: Vector2I,
+                               build_options: BuildOptions,
+                               writer: W)
+                               -> ImageResult<()>
+                               where W: Write {
+        let image = self.rasterize(size, build_options);
+        PngEncoder::new(writer).encode(pathfinder_color::color_slice_to_u8_slice(image.pixels()),
+                                       size.x() as u32,
+                                       size.y() as u32,
+                                       ColorType::Rgba8)
+    }
+
+    /// Returns the IDs of all draw paths that cover the given point, topmost first.
+    ///
+    /// The point is in scene coordinates, before any transform in the build options is applied.
+    /// Each path's fill rule and chain of clip paths are respected, and paths with fully
+    /// transparent paints are skipped.
+    ///
+    /// Paths drawn to render targets are reported where a path painted with that render target
+    /// covers the point, just before the path that composites them.
+    pub fn hit_test(&self, point: Vector2F) -> Vec<DrawPathId> {
+        let ranges = self.draw_path_ranges();
+        let mut hits = vec![];
+        self.hit_test_ranges(&ranges.main, point, &ranges.render_targets, &mut hits);
+        hits
+    }
+
+    fn hit_test_ranges(&self,
+                       ranges: &[Range<DrawPathId>],
+                       point: Vector2F,
+                       render_target_ranges: &HashMap<RenderTargetId, Vec<Range<DrawPathId>>>,
+                       hits: &mut Vec<DrawPathId>) {
+        for range in ranges.iter().rev() {
+            for draw_path_index in (range.start.0..range.end.0).rev() {
+                let draw_path_id = DrawPathId(draw_path_index);
+                let draw_path = self.get_draw_path(draw_path_id);
+                if !draw_path.outline.contains_point(point, draw_path.fill_rule) ||
+                        !self.clip_path_contains_point(draw_path.clip_path, point) {
+                    continue;
+                }
+
+                let paint = self.get_paint(draw_path.paint);
+                let pattern = match paint.pattern() {
+                    Some(pattern) => pattern,
+                    None => {
+                        if !paint.is_fully_transparent() {
+                            hits.push(draw_path_id);
+                        }
+                        continue;
+                    }
+                };
+                let (render_target_id, size) = match *pattern.source() {
+                    PatternSource::RenderTarget { id, size } => (id, size),
+                    PatternSource::Image(_) => {
+                        hits.push(draw_path_id);
+                        continue;
+                    }
+                };
+
+                // Find the point within the render target, and look for paths drawn there.
+                let mut target_point = pattern.transform().inverse() * point;
+                let size = size.to_f32();
+                if pattern.repeat_x() {
+                    target_point.set_x(target_point.x().rem_euclid(size.x()));
+                }
+                if pattern.repeat_y() {
+                    target_point.set_y(target_point.y().rem_euclid(size.y()));
+                }
+                if !RectF::new(Vector2F::zero(), size).contains_point(target_point) {
+                    continue;
+                }
+
+                let hit_count = hits.len();
+                if let Some(ranges) = render_target_ranges.get(&render_target_id) {
+                    self.hit_test_ranges(ranges, target_point, render_target_ranges, hits);
+                }
+                if hits.len() > hit_count {
+                    hits.push(draw_path_id);
+                }
+            }
+        }
+    }
+
+    fn clip_path_contains_point(&self, mut clip_path_id: Option<ClipPathId>, point: Vector2F)
+                                -> bool {
+        while let Some(current_clip_path_id) = clip_path_id {
+            let clip_path = self.get_clip_path(current_clip_path_id);
+            if !clip_path.outline.contains_point(point, clip_path.fill_rule) {
+                return false;
+            }
+            clip_path_id = clip_path.clip_path;
+        }
+        true
+    }
 }
 
 /// Receives render commands and delivers them to a `RenderCommandListener`.
@@ -508,6 +656,15 @@ pub enum DisplayItem {
     PopRenderTarget,
 }
 
+/// The ranges of draw paths in the display list, grouped by where they are drawn.
+#[derive(Clone, Debug, Default)]
+pub struct DrawPathRanges {
+    /// The ranges of draw paths drawn to the main framebuffer, in order.
+    pub main: Vec<Range<DrawPathId>>,
+    /// The ranges of draw paths drawn to each render target, in order.
+    pub render_targets: HashMap<RenderTargetId, Vec<Range<DrawPathId>>>,
+}
+
 impl DrawPath {
     /// Creates a new draw path with the given outline and paint.
     ///
@@ -670,3 +827,75 @@ impl PathId {
         DrawPathId(self.0)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::paint::Paint;
+    use pathfinder_color::ColorU;
+    use pathfinder_content::fill::FillRule;
+    use pathfinder_content::outline::{Contour, Outline};
+    use pathfinder_content::pattern::Pattern;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::transform2d::Transform2F;
+    use pathfinder_geometry::vector::{Vector2F, vec2f, vec2i};
+    use super::{ClipPath, DrawPath, DrawPathId, RenderTarget, Scene};
+
+    fn rect(origin: Vector2F, size: f32) -> Outline {
+        Outline::from_rect(RectF::new(origin, Vector2F::splat(size)))
+    }
+
+    #[test]
+    fn test_hit_test() {
+        let mut scene = Scene::new();
+        let red = scene.push_paint(&Paint::from_color(ColorU::new(255, 0, 0, 255)));
+        let clear = scene.push_paint(&Paint::transparent_black());
+
+        let bottom = scene.push_draw_path(DrawPath::new(rect(Vector2F::zero(), 100.0), red));
+        let invisible = scene.push_draw_path(DrawPath::new(rect(Vector2F::zero(), 100.0), clear));
+
+        // A square with a hole in it under the even-odd rule.
+        let mut outline = rect(vec2f(10.0, 10.0), 40.0);
+        outline.push_contour(Contour::from_rect(RectF::new(vec2f(20.0, 20.0), vec2f(20.0, 20.0))));
+        let mut draw_path = DrawPath::new(outline, red);
+        draw_path.set_fill_rule(FillRule::EvenOdd);
+        let holey = scene.push_draw_path(draw_path);
+
+        // A square clipped by two nested clip paths.
+        let outer_clip = scene.push_clip_path(ClipPath::new(rect(vec2f(50.0, 50.0), 50.0)));
+        let mut inner_clip = ClipPath::new(rect(vec2f(0.0, 0.0), 75.0));
+        inner_clip.set_clip_path(Some(outer_clip));
+        let inner_clip = scene.push_clip_path(inner_clip);
+        let mut draw_path = DrawPath::new(rect(Vector2F::zero(), 100.0), red);
+        draw_path.set_clip_path(Some(inner_clip));
+        let clipped = scene.push_draw_path(draw_path);
+
+        assert_eq!(scene.hit_test(vec2f(15.0, 15.0)), vec![holey, bottom]);
+        assert_eq!(scene.hit_test(vec2f(30.0, 30.0)), vec![bottom]);
+        assert_eq!(scene.hit_test(vec2f(60.0, 60.0)), vec![clipped, bottom]);
+        assert_eq!(scene.hit_test(vec2f(80.0, 60.0)), vec![bottom]);
+        assert_eq!(scene.hit_test(vec2f(200.0, 200.0)), Vec::<DrawPathId>::new());
+        assert!(!scene.hit_test(vec2f(5.0, 5.0)).contains(&invisible));
+    }
+
+    #[test]
+    fn test_hit_test_render_target() {
+        let mut scene = Scene::new();
+        let red = scene.push_paint(&Paint::from_color(ColorU::new(255, 0, 0, 255)));
+
+        let render_target = RenderTarget::new(vec2i(50, 50), String::new());
+        let render_target_id = scene.push_render_target(render_target);
+        let inner = scene.push_draw_path(DrawPath::new(rect(vec2f(10.0, 10.0), 10.0), red));
+        scene.pop_render_target();
+
+        // Composite the render target, offset by 100 units.
+        let mut pattern = Pattern::from_render_target(render_target_id, vec2i(50, 50));
+        pattern.apply_transform(Transform2F::from_translation(vec2f(100.0, 100.0)));
+        let pattern_paint = scene.push_paint(&Paint::from_pattern(pattern));
+        let outline = rect(vec2f(100.0, 100.0), 50.0);
+        let composite = scene.push_draw_path(DrawPath::new(outline, pattern_paint));
+
+        assert_eq!(scene.hit_test(vec2f(115.0, 115.0)), vec![inner, composite]);
+        assert_eq!(scene.hit_test(vec2f(130.0, 130.0)), Vec::<DrawPathId>::new());
+        assert_eq!(scene.hit_test(vec2f(15.0, 15.0)), Vec::<DrawPathId>::new());
+    }
+}
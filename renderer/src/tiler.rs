@@ -11,17 +11,16 @@
 //! Implements the fast lattice-clipping algorithm from Nehab and Hoppe, "Random-Access Rendering
 //! of General Vector Graphics" 2006.
 
-use crate::builder::{BuiltPath, BuiltPathBinCPUData, BuiltPathData, ObjectBuilder, SceneBuilder};
+use crate::builder::{BuiltPath, BuiltPathBinCPUData, BuiltPathData, ObjectBuilder};
+use crate::builder::{PathBuildParams, SceneBuilder};
 use crate::gpu::options::RendererLevel;
 use crate::gpu_data::AlphaTileId;
-use crate::options::PrepareMode;
-use crate::scene::{ClipPathId, PathId};
-use crate::tiles::{TILE_HEIGHT, TILE_WIDTH, TilingPathInfo};
+use crate::scene::ClipPathId;
+use crate::tiles::{self, TILE_HEIGHT, TILE_WIDTH, TilingPathInfo};
 use pathfinder_content::fill::FillRule;
 use pathfinder_content::outline::{ContourIterFlags, Outline};
 use pathfinder_content::segment::Segment;
 use pathfinder_geometry::line_segment::LineSegment2F;
-use pathfinder_geometry::rect::RectF;
 use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f, vec2i};
 use pathfinder_simd::default::{F32x2, U32x2};
 
@@ -36,27 +35,24 @@ pub(crate) struct Tiler<'a, 'b, 'c, 'd> {
 
 impl<'a, 'b, 'c, 'd> Tiler<'a, 'b, 'c, 'd> {
     pub(crate) fn new(scene_builder: &'a SceneBuilder<'b, 'a, 'c, 'd>,
-                      path_id: PathId,
+                      params: &PathBuildParams,
                       outline: &'a Outline,
                       fill_rule: FillRule,
-                      view_box: RectF,
-                      prepare_mode: &PrepareMode,
                       clip_path_id: Option<ClipPathId>,
                       built_clip_paths: &'a [BuiltPath],
                       path_info: TilingPathInfo)
                       -> Tiler<'a, 'b, 'c, 'd> {
-        let bounds = outline.bounds().intersection(view_box).unwrap_or(RectF::default());
+        let view_box = params.view_box;
+        let bounds = outline.bounds().intersection(view_box).unwrap_or_default();
 
-        let clip_path = match clip_path_id {
-            Some(clip_path_id) => Some(&built_clip_paths[clip_path_id.0 as usize]),
-            _ => None,
-        };
+        let clip_path =
+            clip_path_id.map(|clip_path_id| &built_clip_paths[clip_path_id.0 as usize]);
 
-        let object_builder = ObjectBuilder::new(path_id,
+        let object_builder = ObjectBuilder::new(params.path_id,
                                                 bounds,
                                                 view_box,
                                                 fill_rule,
-                                                prepare_mode,
+                                                &params.prepare_mode,
                                                 clip_path_id,
                                                 &path_info);
 
@@ -89,6 +85,8 @@ impl<'a, 'b, 'c, 'd> Tiler<'a, 'b, 'c, 'd> {
     }
 
     fn prepare_tiles(&mut self) {
+        let fill_rule = self.object_builder.built_path.fill_rule;
+
         // Don't do this here if the GPU will do it.
         let (backdrops, tiles, clips) = match self.object_builder.built_path.data {
             BuiltPathData::CPU(ref mut tiled_data) => {
@@ -108,6 +106,7 @@ impl<'a, 'b, 'c, 'd> Tiler<'a, 'b, 'c, 'd> {
 
             let mut draw_alpha_tile_id = draw_tile.alpha_tile_id;
             let mut draw_tile_backdrop = backdrops[column] as i8;
+            let mut draw_tile_ctrl = draw_tile.ctrl;
 
             if let Some(built_clip_path) = self.clip_path {
                 let clip_tiles = match built_clip_path.data {
@@ -121,25 +120,32 @@ impl<'a, 'b, 'c, 'd> Tiler<'a, 'b, 'c, 'd> {
                             // Hard case: We have an alpha tile and a clip tile with masks. Add a
                             // job to combine the two masks. Because the mask combining step
                             // applies the backdrops, zero out the backdrop in the draw tile itself
-                            // so that we don't double-count it.
+                            // so that we don't double-count it. The combining step also resolves
+                            // each mask with its own fill rule.
                             let clip = clips.as_mut()
                                             .expect("Where are the clips?")
                                             .get_mut(tile_coords)
                                             .unwrap();
                             clip.dest_tile_id = draw_tile.alpha_tile_id;
-                            clip.dest_backdrop = draw_tile_backdrop as i32;
+                            clip.dest_backdrop = draw_tile_backdrop as i16;
+                            clip.dest_ctrl = tiles::mask_0_ctrl(fill_rule) as i16;
                             clip.src_tile_id = clip_tile.alpha_tile_id;
-                            clip.src_backdrop = clip_tile.backdrop as i32;
+                            clip.src_backdrop = clip_tile.backdrop as i16;
+                            clip.src_ctrl = tiles::mask_0_ctrl(built_clip_path.fill_rule) as i16;
                             draw_tile_backdrop = 0;
                         } else if clip_tile.alpha_tile_id != AlphaTileId(!0) &&
                                 draw_alpha_tile_id == AlphaTileId(!0) &&
-                                draw_tile_backdrop != 0 {
+                                tiles::solid_tile_is_filled(draw_tile_backdrop, fill_rule) {
                             // This is a solid draw tile, but there's a clip applied. Replace it
-                            // with an alpha tile pointing directly to the clip mask.
+                            // with an alpha tile pointing directly to the clip mask, resolved
+                            // with the clip's fill rule.
                             draw_alpha_tile_id = clip_tile.alpha_tile_id;
                             draw_tile_backdrop = clip_tile.backdrop;
+                            draw_tile_ctrl = (draw_tile_ctrl & !tiles::TILE_CTRL_MASK_0_BITS) |
+                                (clip_tile.ctrl & tiles::TILE_CTRL_MASK_0_BITS);
                         } else if clip_tile.alpha_tile_id == AlphaTileId(!0) &&
-                                clip_tile.backdrop == 0 {
+                                !tiles::solid_tile_is_filled(clip_tile.backdrop,
+                                                             built_clip_path.fill_rule) {
                             // This is a blank clip tile. Cull the draw tile entirely.
                             draw_alpha_tile_id = AlphaTileId(!0);
                             draw_tile_backdrop = 0;
@@ -155,6 +161,7 @@ impl<'a, 'b, 'c, 'd> Tiler<'a, 'b, 'c, 'd> {
 
             draw_tile.alpha_tile_id = draw_alpha_tile_id;
             draw_tile.backdrop = draw_tile_backdrop;
+            draw_tile.ctrl = draw_tile_ctrl;
 
             backdrops[column] += delta;
         }
@@ -43,21 +43,32 @@ impl TilingPathInfo {
     }
 
     pub(crate) fn to_ctrl(&self) -> u8 {
-        let mut ctrl = 0;
         match *self {
             TilingPathInfo::Draw(ref draw_tiling_path_info) => {
-                match draw_tiling_path_info.fill_rule {
-                    FillRule::EvenOdd => {
-                        ctrl |= (TILE_CTRL_MASK_EVEN_ODD << TILE_CTRL_MASK_0_SHIFT) as u8
-                    }
-                    FillRule::Winding => {
-                        ctrl |= (TILE_CTRL_MASK_WINDING << TILE_CTRL_MASK_0_SHIFT) as u8
-                    }
-                }
+                mask_0_ctrl(draw_tiling_path_info.fill_rule)
             }
-            TilingPathInfo::Clip => {}
+            TilingPathInfo::Clip => 0,
         }
-        ctrl
+    }
+}
+
+// The bits of the tile control byte that select how the first mask is resolved.
+pub(crate) const TILE_CTRL_MASK_0_BITS: u8 =
+    ((TILE_CTRL_MASK_WINDING | TILE_CTRL_MASK_EVEN_ODD) << TILE_CTRL_MASK_0_SHIFT) as u8;
+
+// Returns the tile control bits that resolve the first mask with the given fill rule.
+pub(crate) fn mask_0_ctrl(fill_rule: FillRule) -> u8 {
+    match fill_rule {
+        FillRule::EvenOdd => (TILE_CTRL_MASK_EVEN_ODD << TILE_CTRL_MASK_0_SHIFT) as u8,
+        FillRule::Winding => (TILE_CTRL_MASK_WINDING << TILE_CTRL_MASK_0_SHIFT) as u8,
+    }
+}
+
+// Returns true if a tile with no mask and the given backdrop lies inside a path.
+pub(crate) fn solid_tile_is_filled(backdrop: i8, fill_rule: FillRule) -> bool {
+    match fill_rule {
+        FillRule::EvenOdd => backdrop % 2 != 0,
+        FillRule::Winding => backdrop != 0,
     }
 }
 
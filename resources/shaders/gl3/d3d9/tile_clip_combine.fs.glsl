@@ -18,17 +18,28 @@ precision highp float;
 
 
 
+
+
 uniform sampler2D uSrc;
 
 in vec2 vTexCoord0;
 in float vBackdrop0;
+in float vCtrl0;
 in vec2 vTexCoord1;
 in float vBackdrop1;
+in float vCtrl1;
 
 out vec4 oFragColor;
 
+
+vec4 resolveMask(vec4 coverage, int maskCtrl){
+    if((maskCtrl & 0x2)!= 0)
+        return vec4(1.0)- abs(vec4(1.0)- mod(coverage, vec4(2.0)));
+    return abs(coverage);
+}
+
 void main(){
-    oFragColor = min(abs(texture(uSrc, vTexCoord0)+ vBackdrop0),
-                     abs(texture(uSrc, vTexCoord1)+ vBackdrop1));
+    oFragColor = min(resolveMask(texture(uSrc, vTexCoord0)+ vBackdrop0, int(vCtrl0)),
+                     resolveMask(texture(uSrc, vTexCoord1)+ vBackdrop1, int(vCtrl1)));
 }
 
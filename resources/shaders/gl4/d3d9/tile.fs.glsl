@@ -12,6 +12,8 @@
 
 
 
+
+
 #extension GL_GOOGLE_include_directive : enable
 
 precision highp float;
@@ -329,6 +331,26 @@ vec4 filterRadialGradient(vec2 colorTexCoord,
 
 
 
+
+
+
+vec4 filterConicGradient(vec2 colorTexCoord,
+                         sampler2D colorTexture,
+                         vec4 filterParams0,
+                         vec4 filterParams1){
+    vec2 center = filterParams0 . xy, uvOrigin = filterParams1 . xy;
+    float startAngle = filterParams0 . z;
+
+    vec2 dP = colorTexCoord - center;
+    float t = fract((atan(dP . y, dP . x)- startAngle)* 0.15915494309189535);
+    return texture(colorTexture, uvOrigin + vec2(t, 0.0));
+}
+
+
+
+
+
+
 vec4 filterBlur(vec2 colorTexCoord,
                 sampler2D colorTexture,
                 vec2 colorTextureSize,
@@ -406,6 +428,11 @@ vec4 filterColor(vec2 colorTexCoord,
                                     framebufferSize,
                                     filterParams0,
                                     filterParams1);
+    case 0x5 :
+        return filterConicGradient(colorTexCoord,
+                                   colorTexture,
+                                   filterParams0,
+                                   filterParams1);
     case 0x3 :
         return filterBlur(colorTexCoord,
                           colorTexture,
@@ -23,13 +23,17 @@ uniform vec2 uFramebufferSize;
 in ivec2 aTileOffset;
 in int aDestTileIndex;
 in int aDestBackdrop;
+in int aDestCtrl;
 in int aSrcTileIndex;
 in int aSrcBackdrop;
+in int aSrcCtrl;
 
 out vec2 vTexCoord0;
 out float vBackdrop0;
+out float vCtrl0;
 out vec2 vTexCoord1;
 out float vBackdrop1;
+out float vCtrl1;
 
 void main(){
     vec2 destPosition = vec2(ivec2(aDestTileIndex % 256, aDestTileIndex / 256)+ aTileOffset);
@@ -43,6 +47,9 @@ void main(){
     vBackdrop0 = float(aDestBackdrop);
     vBackdrop1 = float(aSrcBackdrop);
 
+    vCtrl0 = float(aDestCtrl);
+    vCtrl1 = float(aSrcCtrl);
+
     if(aDestTileIndex < 0)
         destPosition = vec2(0.0);
 
@@ -148,6 +148,17 @@ float4 filterRadialGradient(thread const float2& colorTexCoord, thread const tex
     return color;
 }
 
+static inline __attribute__((always_inline))
+float4 filterConicGradient(thread const float2& colorTexCoord, thread const texture2d<float> colorTexture, thread const sampler colorTextureSmplr, thread const float4& filterParams0, thread const float4& filterParams1)
+{
+    float2 center = filterParams0.xy;
+    float2 uvOrigin = filterParams1.xy;
+    float startAngle = filterParams0.z;
+    float2 dP = colorTexCoord - center;
+    float t = fract((precise::atan2(dP.y, dP.x) - startAngle) * 0.15915493667125701904296875);
+    return colorTexture.sample(colorTextureSmplr, (uvOrigin + float2(t, 0.0)));
+}
+
 static inline __attribute__((always_inline))
 float4 filterBlur(thread const float2& colorTexCoord, thread const texture2d<float> colorTexture, thread const sampler colorTextureSmplr, thread const float2& colorTextureSize, thread const float4& filterParams0, thread const float4& filterParams1)
 {
@@ -331,6 +342,13 @@ float4 filterColor(thread const float2& colorTexCoord, thread const texture2d<fl
             float4 param_5 = filterParams1;
             return filterRadialGradient(param, colorTexture, colorTextureSmplr, param_1, param_2, param_3, param_4, param_5);
         }
+        case 5:
+        {
+            float2 param_22 = colorTexCoord;
+            float4 param_23 = filterParams0;
+            float4 param_24 = filterParams1;
+            return filterConicGradient(param_22, colorTexture, colorTextureSmplr, param_23, param_24);
+        }
         case 3:
         {
             float2 param_6 = colorTexCoord;
@@ -13,14 +13,37 @@ struct main0_in
 {
     float2 vTexCoord0 [[user(locn0)]];
     float vBackdrop0 [[user(locn1)]];
-    float2 vTexCoord1 [[user(locn2)]];
-    float vBackdrop1 [[user(locn3)]];
+    float vCtrl0 [[user(locn2)]];
+    float2 vTexCoord1 [[user(locn3)]];
+    float vBackdrop1 [[user(locn4)]];
+    float vCtrl1 [[user(locn5)]];
 };
 
+// Implementation of the GLSL mod() function, which is slightly different than Metal fmod()
+template<typename Tx, typename Ty>
+inline Tx mod(Tx x, Ty y)
+{
+    return x - y * floor(x / y);
+}
+
+static inline __attribute__((always_inline))
+float4 resolveMask(thread const float4& coverage, thread const int& maskCtrl)
+{
+    if ((maskCtrl & 2) != 0)
+    {
+        return float4(1.0) - abs(float4(1.0) - mod(coverage, float4(2.0)));
+    }
+    return abs(coverage);
+}
+
 fragment main0_out main0(main0_in in [[stage_in]], texture2d<float> uSrc [[texture(0)]], sampler uSrcSmplr [[sampler(0)]])
 {
     main0_out out = {};
-    out.oFragColor = fast::min(abs(uSrc.sample(uSrcSmplr, in.vTexCoord0) + float4(in.vBackdrop0)), abs(uSrc.sample(uSrcSmplr, in.vTexCoord1) + float4(in.vBackdrop1)));
+    float4 param = uSrc.sample(uSrcSmplr, in.vTexCoord0) + float4(in.vBackdrop0);
+    int param_1 = int(in.vCtrl0);
+    float4 param_2 = uSrc.sample(uSrcSmplr, in.vTexCoord1) + float4(in.vBackdrop1);
+    int param_3 = int(in.vCtrl1);
+    out.oFragColor = fast::min(resolveMask(param, param_1), resolveMask(param_2, param_3));
     return out;
 }
 
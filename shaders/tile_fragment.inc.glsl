@@ -31,6 +31,7 @@
 
 #define FRAC_6_PI   1.9098593171027443
 #define FRAC_PI_3   1.0471975511965976
+#define FRAC_1_2_PI 0.15915494309189535
 
 #define TILE_CTRL_MASK_MASK                     0x3
 #define TILE_CTRL_MASK_WINDING                  0x1
@@ -47,6 +48,7 @@
 #define COMBINER_CTRL_FILTER_TEXT               0x2
 #define COMBINER_CTRL_FILTER_BLUR               0x3
 #define COMBINER_CTRL_FILTER_COLOR_MATRIX       0x4
+#define COMBINER_CTRL_FILTER_CONIC_GRADIENT     0x5
 
 #define COMBINER_CTRL_COMPOSITE_MASK            0xf
 #define COMBINER_CTRL_COMPOSITE_NORMAL          0x0
@@ -301,6 +303,26 @@ vec4 filterRadialGradient(vec2 colorTexCoord,
     return color;
 }
 
+// Conic gradients sweep clockwise around a center point. The angle of the fragment around the
+// center, relative to the start angle, selects the position along the linearized gradient.
+//
+//                | x           y           z               w
+//  --------------+-----------------------------------------------------
+//  filterParams0 | center.x    center.y    startAngle      -
+//  filterParams1 | uvOrigin.x  uvOrigin.y  -               -
+//  filterParams2 | -           -           -               -
+vec4 filterConicGradient(vec2 colorTexCoord,
+                         sampler2D colorTexture,
+                         vec4 filterParams0,
+                         vec4 filterParams1) {
+    vec2 center = filterParams0.xy, uvOrigin = filterParams1.xy;
+    float startAngle = filterParams0.z;
+
+    vec2 dP = colorTexCoord - center;
+    float t = fract((atan(dP.y, dP.x) - startAngle) * FRAC_1_2_PI);
+    return texture(colorTexture, uvOrigin + vec2(t, 0.0));
+}
+
 //                | x             y             z             w
 //  --------------+----------------------------------------------------
 //  filterParams0 | srcOffset.x   srcOffset.y   support       -
@@ -383,6 +405,11 @@ vec4 filterColor(vec2 colorTexCoord,
                                     framebufferSize,
                                     filterParams0,
                                     filterParams1);
+    case COMBINER_CTRL_FILTER_CONIC_GRADIENT:
+        return filterConicGradient(colorTexCoord,
+                                   colorTexture,
+                                   filterParams0,
+                                   filterParams1);
     case COMBINER_CTRL_FILTER_BLUR:
         return filterBlur(colorTexCoord,
                           colorTexture,
@@ -0,0 +1,203 @@
+// pathfinder/svg/src/clip.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Applies SVG clip paths.
+//!
+//! Clip paths are resolved where they are used, since both their user space and, with
+//! `clipPathUnits="objectBoundingBox"`, their size depend on the clipped element. Intersections,
+//! from `clip-path` attributes on `<clipPath>` elements, become chains of Pathfinder clip paths.
+//! A `<clipPath>` with several children becomes the union of those children, each filled with its
+//! own clip rule and intersected with the clip paths applied to it.
+
+use crate::{FillRuleExt, SVGScene, UsvgPathToSegments, object_bounding_box};
+use crate::{union_node_bounds, usvg_transform_to_transform_2d};
+use pathfinder_content::fill::FillRule;
+use pathfinder_content::outline::Outline;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_renderer::scene::{ClipPath, ClipPathId};
+use usvg::{ClipPath as UsvgClipPath, Node, NodeExt, NodeKind, Units, Visibility};
+
+impl SVGScene {
+    /// Pushes the clip path defined by `clip_node`, as referenced by `element`, and intersects it
+    /// with `parent`.
+    ///
+    /// `transform` maps the user space of `element` to the scene. Returns `None` if the clip path
+    /// hides the element entirely.
+    pub(crate) fn push_svg_clip_path(&mut self,
+                                     clip_node: &Node,
+                                     element: &Node,
+                                     transform: &Transform2F,
+                                     parent: Option<ClipPathId>)
+                                     -> Option<ClipPathId> {
+        let (clip, shapes) = self.clip_shapes(clip_node, element, transform)?;
+
+        // A clip path that is itself clipped is intersected with that clip path, which is
+        // resolved against the same element.
+        let mut parent = parent;
+        if let Some(ref clip_name) = clip.clip_path {
+            let clip_node = self.clip_path_nodes.get(clip_name).cloned()?;
+            parent = Some(self.push_svg_clip_path(&clip_node, element, transform, parent)?);
+        }
+
+        // A single child keeps its own clip rule, and the clip paths applied to it are chained.
+        // Several children are unioned into one outline filled with the nonzero rule.
+        let (outline, fill_rule) = if let [ref shape] = shapes[..] {
+            for &(ref clip_name, ref kid, ref kid_transform) in &shape.clip_paths {
+                let clip_node = self.clip_path_nodes.get(clip_name).cloned()?;
+                parent = Some(self.push_svg_clip_path(&clip_node, kid, kid_transform, parent)?);
+            }
+            (shape.outline.clone(), shape.fill_rule)
+        } else {
+            (self.union_clip_shapes(shapes), FillRule::Winding)
+        };
+        if outline.is_empty() {
+            return None;
+        }
+
+        let mut clip_path = ClipPath::new(outline);
+        clip_path.set_fill_rule(fill_rule);
+        clip_path.set_clip_path(parent);
+        clip_path.set_name(format!("ClipPath({})", clip.id));
+        Some(self.scene.push_clip_path(clip_path))
+    }
+
+    // Returns the `<clipPath>` element and its child shapes in scene coordinates, as resolved
+    // against `element`. Returns `None` if `clip_node` isn't a clip path, or if its units are
+    // relative to an empty bounding box.
+    fn clip_shapes(&self, clip_node: &Node, element: &Node, transform: &Transform2F)
+                   -> Option<(UsvgClipPath, Vec<ClipShape>)> {
+        let clip = match *clip_node.borrow() {
+            NodeKind::ClipPath(ref clip) => clip.clone(),
+            _ => return None,
+        };
+
+        let units_transform = match clip.units {
+            Units::UserSpaceOnUse => Transform2F::default(),
+            Units::ObjectBoundingBox => {
+                let bbox = union_node_bounds(element, &Transform2F::default())?;
+                if !(bbox.width() > 0.0 && bbox.height() > 0.0) {
+                    return None;
+                }
+                object_bounding_box(bbox)
+            }
+        };
+        let transform = *transform *
+            units_transform *
+            usvg_transform_to_transform_2d(&clip.transform);
+
+        let mut shapes = vec![];
+        for kid in clip_node.children() {
+            collect_clip_shapes(&kid, &transform, &[], &mut shapes);
+        }
+        Some((clip, shapes))
+    }
+
+    // Returns the area that the clip path defined by `clip_node` leaves visible on `element`, as
+    // an outline without self-intersections.
+    fn clip_region(&self, clip_node: &Node, element: &Node, transform: &Transform2F) -> Outline {
+        let (clip, shapes) = match self.clip_shapes(clip_node, element, transform) {
+            Some(clip_shapes) => clip_shapes,
+            None => return Outline::new(),
+        };
+        let region = self.union_clip_shapes(shapes);
+        match clip.clip_path {
+            Some(ref clip_name) => {
+                let clip_region = self.clip_region_named(clip_name, element, transform);
+                region.intersection(&clip_region, FillRule::Winding)
+            }
+            None => region,
+        }
+    }
+
+    fn clip_region_named(&self, clip_name: &str, element: &Node, transform: &Transform2F)
+                         -> Outline {
+        match self.clip_path_nodes.get(clip_name) {
+            Some(clip_node) => self.clip_region(clip_node, element, transform),
+            None => Outline::new(),
+        }
+    }
+
+    // Unions the given shapes after intersecting each with the clip paths applied to it.
+    //
+    // Each shape is first simplified under its own clip rule, which leaves contours that fill the
+    // same area under either rule and are wound so that the nonzero rule fills their union.
+    fn union_clip_shapes(&self, shapes: Vec<ClipShape>) -> Outline {
+        let mut union = Outline::new();
+        for shape in shapes {
+            let mut outline = shape.outline.simplify(shape.fill_rule);
+            for &(ref clip_name, ref kid, ref kid_transform) in &shape.clip_paths {
+                let clip_region = self.clip_region_named(clip_name, kid, kid_transform);
+                outline = outline.intersection(&clip_region, FillRule::Winding);
+            }
+            for contour in outline.into_contours() {
+                union.push_contour(contour);
+            }
+        }
+        union
+    }
+}
+
+/// Returns the union of the children of the given `<clipPath>` element, ignoring its units and
+/// any clip paths applied to it or its children.
+pub(crate) fn clip_path_outline(clip_node: &Node, transform: &Transform2F) -> Outline {
+    let mut shapes = vec![];
+    for kid in clip_node.children() {
+        collect_clip_shapes(&kid, transform, &[], &mut shapes);
+    }
+
+    let mut union = Outline::new();
+    for shape in shapes {
+        for contour in shape.outline.simplify(shape.fill_rule).into_contours() {
+            union.push_contour(contour);
+        }
+    }
+    union
+}
+
+// One child shape of a `<clipPath>` element.
+struct ClipShape {
+    outline: Outline,
+    fill_rule: FillRule,
+    // The clip paths applied to this shape, along with the elements that reference them and the
+    // transforms from the user spaces of those elements to the scene.
+    clip_paths: Vec<(String, Node, Transform2F)>,
+}
+
+// Gathers the shapes that make up the content of a `<clipPath>` element. usvg wraps children
+// with `clip-path` attributes and `<use>` elements in groups.
+fn collect_clip_shapes(node: &Node,
+                       transform: &Transform2F,
+                       clip_paths: &[(String, Node, Transform2F)],
+                       shapes: &mut Vec<ClipShape>) {
+    let transform = *transform * usvg_transform_to_transform_2d(&node.transform());
+    match *node.borrow() {
+        NodeKind::Path(ref path) if path.visibility == Visibility::Visible => {
+            let mut outline = Outline::from_segments(UsvgPathToSegments::new(path.data
+                                                                                 .iter()
+                                                                                 .cloned()));
+            outline.transform(&transform);
+            let fill_rule = match path.fill {
+                Some(ref fill) => FillRule::from_usvg_fill_rule(fill.rule),
+                None => FillRule::Winding,
+            };
+            shapes.push(ClipShape { outline, fill_rule, clip_paths: clip_paths.to_vec() });
+        }
+        NodeKind::Group(ref group) => {
+            let mut clip_paths = clip_paths.to_vec();
+            if let Some(ref clip_name) = group.clip_path {
+                clip_paths.push((clip_name.clone(), node.clone(), transform));
+            }
+            for kid in node.children() {
+                collect_clip_shapes(&kid, &transform, &clip_paths, shapes);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,376 @@
+// pathfinder/svg/src/filter.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Evaluates SVG filter effects.
+//!
+//! The filtered content is drawn into a render target covering the filter region, and each
+//! filter primitive then draws its inputs into a fresh render target of the same size, using
+//! pattern filters for blurs and color matrices and blend modes for compositing. The result of
+//! the last primitive is composited into the scene.
+//!
+//! Filters are evaluated in sRGB, regardless of `color-interpolation-filters`.
+
+use crate::{BuildResultFlags, ColorUExt, SVGScene, State, object_bounding_box};
+use crate::{resolve_units_rect, union_node_bounds};
+use hashbrown::HashMap;
+use pathfinder_color::ColorU;
+use pathfinder_color::matrix::ColorMatrix;
+use pathfinder_content::effects::{BlendMode, BlurDirection, PatternFilter};
+use pathfinder_content::outline::Outline;
+use pathfinder_content::pattern::Pattern;
+use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_geometry::rect::{RectF, RectI};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::{DrawPath, RenderTarget};
+use usvg::{FeBlendMode, FeColorMatrixKind, FeCompositeOperator, Filter, FilterInput, FilterKind};
+use usvg::{FilterPrimitive, Node, NodeExt, Units};
+
+impl SVGScene {
+    /// Draws the children of the given group through the given filter.
+    pub(crate) fn process_filtered_group(&mut self, node: &Node, filter: &Filter, state: &State) {
+        let bbox = union_node_bounds(node, &Transform2F::default());
+
+        // Per spec, a filter region relative to an empty bounding box disables rendering of the
+        // element.
+        let region = match resolve_units_rect(filter.units, &filter.rect, bbox) {
+            None => return,
+            Some(region) => region,
+        };
+        let bounds = (state.transform * region).round_out().to_i32();
+        if bounds.width() <= 0 || bounds.height() <= 0 {
+            return;
+        }
+
+        // The same goes for primitive subregions and lengths relative to an empty bounding box.
+        let bbox_is_empty = bbox.map_or(true, |bbox| !(bbox.width() > 0.0 && bbox.height() > 0.0));
+        if filter.primitive_units == Units::ObjectBoundingBox && bbox_is_empty {
+            return;
+        }
+
+        let mut context = FilterContext {
+            bounds,
+            region,
+            bbox: bbox.unwrap_or_default(),
+            transform: Transform2F::from_translation(-bounds.origin().to_f32()) * state.transform,
+            primitive_units: filter.primitive_units,
+            source: None,
+            source_alpha: None,
+            results: HashMap::new(),
+            last_result: None,
+        };
+
+        // Draw the source graphic. Clipping applies to the filter result, not its input.
+        let name = format!("SourceGraphic({})", node.id());
+        let source = self.scene.push_render_target(RenderTarget::new(bounds.size(), name));
+        let mut source_state = state.clone();
+        source_state.transform = context.transform;
+        source_state.clip_path = None;
+        for kid in node.children() {
+            self.process_node(&kid, &source_state);
+        }
+        self.scene.pop_render_target();
+        context.source = Some(source);
+
+        for primitive in &filter.children {
+            let result = self.process_filter_primitive(&mut context, primitive);
+            context.results.insert(primitive.result.clone(), result);
+            context.last_result = Some(result);
+        }
+
+        let result = context.last_result.unwrap_or(source);
+        self.draw_render_target(result, bounds, state.clip_path, format!("Filter({})", filter.id));
+    }
+
+    fn process_filter_primitive(&mut self,
+                                context: &mut FilterContext,
+                                primitive: &FilterPrimitive)
+                                -> RenderTargetId {
+        let subregion = context.subregion(primitive);
+        match primitive.kind {
+            FilterKind::FeFlood(ref flood) => {
+                let mut color = ColorU::from_svg_color(flood.color);
+                color.a = (flood.opacity.value() * 255.0).round() as u8;
+                let result = self.push_filter_render_target(context, &primitive.result);
+                let paint = self.scene.push_paint(&Paint::from_color(color));
+                self.scene.push_draw_path(DrawPath::new(Outline::from_rect(subregion), paint));
+                self.scene.pop_render_target();
+                result
+            }
+            FilterKind::FeGaussianBlur(ref blur) => {
+                let std_dev = vec2f(blur.std_dev_x.value() as f32, blur.std_dev_y.value() as f32);
+                let sigma = context.user_vector_scale(std_dev) * context.transform.extract_scale();
+                let mut result = self.filter_input(context, &blur.input);
+                for &(direction, sigma) in &[(BlurDirection::X, sigma.x()),
+                                             (BlurDirection::Y, sigma.y())] {
+                    if sigma <= 0.0 {
+                        continue;
+                    }
+                    let input = result;
+                    result = self.push_filter_render_target(context, &primitive.result);
+                    self.draw_filter_input(context,
+                                           input,
+                                           subregion,
+                                           Vector2F::zero(),
+                                           Some(PatternFilter::Blur { direction, sigma }),
+                                           BlendMode::SrcOver);
+                    self.scene.pop_render_target();
+                }
+                result
+            }
+            FilterKind::FeOffset(ref fe_offset) => {
+                let offset = vec2f(fe_offset.dx as f32, fe_offset.dy as f32);
+                let offset = context.transform.matrix * context.user_vector_scale(offset);
+                let input = self.filter_input(context, &fe_offset.input);
+                let result = self.push_filter_render_target(context, &primitive.result);
+                self.draw_filter_input(context,
+                                       input,
+                                       subregion,
+                                       offset,
+                                       None,
+                                       BlendMode::SrcOver);
+                self.scene.pop_render_target();
+                result
+            }
+            FilterKind::FeColorMatrix(ref color_matrix) => {
+                let matrix = match color_matrix.kind {
+                    FeColorMatrixKind::Matrix(ref values) => {
+                        // `usvg` guarantees that there are 20 values.
+                        let mut rows = [[0.0; 5]; 4];
+                        for (index, &value) in values.iter().take(20).enumerate() {
+                            rows[index / 5][index % 5] = value as f32;
+                        }
+                        ColorMatrix::from_rows(rows)
+                    }
+                    FeColorMatrixKind::Saturate(saturation) => {
+                        ColorMatrix::saturate(saturation.value() as f32)
+                    }
+                    FeColorMatrixKind::HueRotate(angle) => {
+                        ColorMatrix::hue_rotate((angle as f32).to_radians())
+                    }
+                    FeColorMatrixKind::LuminanceToAlpha => ColorMatrix::luminance_to_alpha(),
+                };
+                let input = self.filter_input(context, &color_matrix.input);
+                let result = self.push_filter_render_target(context, &primitive.result);
+                self.draw_filter_input(context,
+                                       input,
+                                       subregion,
+                                       Vector2F::zero(),
+                                       Some(PatternFilter::ColorMatrix(matrix)),
+                                       BlendMode::SrcOver);
+                self.scene.pop_render_target();
+                result
+            }
+            FilterKind::FeBlend(ref blend) => {
+                let blend_mode = match blend.mode {
+                    FeBlendMode::Normal => BlendMode::SrcOver,
+                    FeBlendMode::Multiply => BlendMode::Multiply,
+                    FeBlendMode::Screen => BlendMode::Screen,
+                    FeBlendMode::Darken => BlendMode::Darken,
+                    FeBlendMode::Lighten => BlendMode::Lighten,
+                };
+                self.composite_filter_inputs(context,
+                                             primitive,
+                                             &blend.input1,
+                                             &blend.input2,
+                                             blend_mode)
+            }
+            FilterKind::FeComposite(ref composite) => {
+                let blend_mode = match composite.operator {
+                    FeCompositeOperator::Over => BlendMode::SrcOver,
+                    FeCompositeOperator::In => BlendMode::SrcIn,
+                    FeCompositeOperator::Out => BlendMode::SrcOut,
+                    FeCompositeOperator::Atop => BlendMode::SrcAtop,
+                    FeCompositeOperator::Xor => BlendMode::Xor,
+                    FeCompositeOperator::Arithmetic { .. } => {
+                        // TODO(pcwalton): Arithmetic compositing needs a dedicated shader.
+                        self.result_flags.insert(BuildResultFlags::UNSUPPORTED_FILTER_NODE);
+                        BlendMode::SrcOver
+                    }
+                };
+                self.composite_filter_inputs(context,
+                                             primitive,
+                                             &composite.input1,
+                                             &composite.input2,
+                                             blend_mode)
+            }
+            FilterKind::FeMerge(ref merge) => {
+                let inputs: Vec<_> = merge.inputs.iter().map(|input| {
+                    self.filter_input(context, input)
+                }).collect();
+                let result = self.push_filter_render_target(context, &primitive.result);
+                for input in inputs {
+                    self.draw_filter_input(context,
+                                           input,
+                                           subregion,
+                                           Vector2F::zero(),
+                                           None,
+                                           BlendMode::SrcOver);
+                }
+                self.scene.pop_render_target();
+                result
+            }
+            _ => {
+                // Unsupported primitives produce transparent black.
+                self.result_flags.insert(BuildResultFlags::UNSUPPORTED_FILTER_NODE);
+                let result = self.push_filter_render_target(context, &primitive.result);
+                self.scene.pop_render_target();
+                result
+            }
+        }
+    }
+
+    // Draws `input2` and then `input1` on top of it with the given blend mode.
+    fn composite_filter_inputs(&mut self,
+                               context: &mut FilterContext,
+                               primitive: &FilterPrimitive,
+                               input1: &FilterInput,
+                               input2: &FilterInput,
+                               blend_mode: BlendMode)
+                               -> RenderTargetId {
+        let subregion = context.subregion(primitive);
+        let (input1, input2) = (self.filter_input(context, input1),
+                                self.filter_input(context, input2));
+        let result = self.push_filter_render_target(context, &primitive.result);
+        self.draw_filter_input(context,
+                               input2,
+                               subregion,
+                               Vector2F::zero(),
+                               None,
+                               BlendMode::SrcOver);
+        self.draw_filter_input(context, input1, subregion, Vector2F::zero(), None, blend_mode);
+        self.scene.pop_render_target();
+        result
+    }
+
+    fn filter_input(&mut self, context: &mut FilterContext, input: &FilterInput)
+                    -> RenderTargetId {
+        let source = context.source.unwrap();
+        match *input {
+            FilterInput::SourceGraphic => source,
+            FilterInput::SourceAlpha => {
+                if let Some(source_alpha) = context.source_alpha {
+                    return source_alpha;
+                }
+                let alpha_matrix = ColorMatrix::from_rows([
+                    [0.0, 0.0, 0.0, 0.0, 0.0],
+                    [0.0, 0.0, 0.0, 0.0, 0.0],
+                    [0.0, 0.0, 0.0, 0.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0, 0.0],
+                ]);
+                let source_alpha = self.push_filter_render_target(context, "SourceAlpha");
+                self.draw_filter_input(context,
+                                       source,
+                                       context.bounds_in_render_target(),
+                                       Vector2F::zero(),
+                                       Some(PatternFilter::ColorMatrix(alpha_matrix)),
+                                       BlendMode::SrcOver);
+                self.scene.pop_render_target();
+                context.source_alpha = Some(source_alpha);
+                source_alpha
+            }
+            FilterInput::Reference(ref name) => {
+                // Unknown references fall back to the default input, per spec.
+                match context.results.get(name) {
+                    Some(&result) => result,
+                    None => context.last_result.unwrap_or(source),
+                }
+            }
+            FilterInput::BackgroundImage |
+            FilterInput::BackgroundAlpha |
+            FilterInput::FillPaint |
+            FilterInput::StrokePaint => {
+                self.result_flags.insert(BuildResultFlags::UNSUPPORTED_FILTER_NODE);
+                let result = self.push_filter_render_target(context, "");
+                self.scene.pop_render_target();
+                result
+            }
+        }
+    }
+
+    fn push_filter_render_target(&mut self, context: &FilterContext, name: &str)
+                                 -> RenderTargetId {
+        let render_target = RenderTarget::new(context.bounds.size(), name.to_owned());
+        self.scene.push_render_target(render_target)
+    }
+
+    // Draws an earlier result into the current render target, clipped to `subregion`.
+    fn draw_filter_input(&mut self,
+                         context: &FilterContext,
+                         input: RenderTargetId,
+                         subregion: RectF,
+                         offset: Vector2F,
+                         filter: Option<PatternFilter>,
+                         blend_mode: BlendMode) {
+        // Patterns that don't repeat are filled with the base color outside their bounds, so
+        // keep offset inputs from drawing there.
+        let input_bounds = context.bounds_in_render_target() + offset;
+        let rect = match subregion.intersection(input_bounds) {
+            Some(rect) => rect,
+            None => return,
+        };
+
+        let mut pattern = Pattern::from_render_target(input, context.bounds.size());
+        pattern.apply_transform(Transform2F::from_translation(offset));
+        pattern.set_filter(filter);
+        let paint = self.scene.push_paint(&Paint::from_pattern(pattern));
+        let mut path = DrawPath::new(Outline::from_rect(rect), paint);
+        path.set_blend_mode(blend_mode);
+        self.scene.push_draw_path(path);
+    }
+}
+
+struct FilterContext {
+    // The filter region in scene space, rounded out to whole pixels.
+    bounds: RectI,
+    // The filter region in user space.
+    region: RectF,
+    // The bounding box of the filtered element in user space.
+    bbox: RectF,
+    // Maps user space to the pixels of the render targets.
+    transform: Transform2F,
+    primitive_units: Units,
+    source: Option<RenderTargetId>,
+    source_alpha: Option<RenderTargetId>,
+    results: HashMap<String, RenderTargetId>,
+    last_result: Option<RenderTargetId>,
+}
+
+impl FilterContext {
+    fn bounds_in_render_target(&self) -> RectF {
+        RectF::new(Vector2F::zero(), self.bounds.size().to_f32())
+    }
+
+    // Returns the filter primitive subregion in render target pixels. Unspecified coordinates
+    // default to those of the filter region.
+    fn subregion(&self, primitive: &FilterPrimitive) -> RectF {
+        let bbox_transform = match self.primitive_units {
+            Units::UserSpaceOnUse => Transform2F::default(),
+            Units::ObjectBoundingBox => object_bounding_box(self.bbox),
+        };
+        let specified = |value: Option<f64>, default: f32| value.map_or(default, |v| v as f32);
+        let default = bbox_transform.inverse() * self.region;
+        let origin = vec2f(specified(primitive.x, default.origin_x()),
+                           specified(primitive.y, default.origin_y()));
+        let size = vec2f(specified(primitive.width, default.width()),
+                         specified(primitive.height, default.height()));
+        let subregion = self.transform * (bbox_transform * RectF::new(origin, size));
+        subregion.intersection(self.bounds_in_render_target()).unwrap_or_default()
+    }
+
+    // Scales a length given in primitive units to user space.
+    fn user_vector_scale(&self, vector: Vector2F) -> Vector2F {
+        match self.primitive_units {
+            Units::UserSpaceOnUse => vector,
+            Units::ObjectBoundingBox => vector * self.bbox.size(),
+        }
+    }
+}
@@ -9,39 +9,100 @@
 // except according to those terms.
 
 //! Converts a subset of SVG to a Pathfinder scene.
+//!
+//! External images are read from the paths that usvg resolved them to, so relative paths are
+//! resolved against the `resources_dir` of the `usvg::Options` that the tree was parsed with.
 
 #[macro_use]
 extern crate bitflags;
 
+use crate::clip::clip_path_outline;
+use crate::pattern::PatternTileKey;
 use hashbrown::HashMap;
+use image::ImageFormat;
 use pathfinder_color::ColorU;
 use pathfinder_content::dash::OutlineDash;
 use pathfinder_content::fill::FillRule;
 use pathfinder_content::gradient::{ColorStop, Gradient, GradientWrap};
 use pathfinder_content::outline::Outline;
+use pathfinder_content::pattern::{Image, Pattern};
+use pathfinder_content::render_target::RenderTargetId;
 use pathfinder_content::segment::{Segment, SegmentFlags};
 use pathfinder_content::stroke::{LineCap, LineJoin, OutlineStrokeToFill, StrokeStyle};
-use pathfinder_content::transform::Transform2FPathIter;
 use pathfinder_geometry::line_segment::LineSegment2F;
-use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::rect::{RectF, RectI};
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::{Vector2F, vec2f};
 use pathfinder_renderer::paint::Paint;
 use pathfinder_renderer::scene::{ClipPath, ClipPathId, DrawPath, Scene};
 use pathfinder_simd::default::F32x2;
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter, Result as FormatResult};
-use usvg::{BaseGradient, Color as SvgColor, FillRule as UsvgFillRule, LineCap as UsvgLineCap};
-use usvg::{LineJoin as UsvgLineJoin, Node, NodeExt, NodeKind, Opacity, Paint as UsvgPaint};
-use usvg::{PathSegment as UsvgPathSegment, Rect as UsvgRect, SpreadMethod, Stop};
-use usvg::{Transform as UsvgTransform, Tree, Visibility};
+use std::fs;
+use usvg::{Align, AspectRatio, BaseGradient, Color as SvgColor, Filter, FillRule as UsvgFillRule};
+use usvg::{Group, Image as UsvgImage, ImageData, ImageFormat as UsvgImageFormat, ImageRendering};
+use usvg::{LineCap as UsvgLineCap, LineJoin as UsvgLineJoin, Node, NodeExt, NodeKind, Opacity};
+use usvg::{Paint as UsvgPaint, PathSegment as UsvgPathSegment, Rect as UsvgRect, SpreadMethod};
+use usvg::{Stop, Svg, Transform as UsvgTransform, Tree, Units, Visibility};
+
+mod clip;
+mod filter;
+mod mask;
+mod pattern;
+
+#[cfg(test)]
+mod tests;
 
 const HAIRLINE_STROKE_WIDTH: f32 = 0.0333;
 
+// The resolution that usvg converts absolute units like `mm` and `pt` to pixels at, by default.
+const CSS_PIXELS_PER_INCH: f32 = 96.0;
+
 pub struct SVGScene {
     pub scene: Scene,
     pub result_flags: BuildResultFlags,
     pub clip_paths: HashMap<String, Outline>,
+    clip_path_nodes: HashMap<String, Node>,
     gradients: HashMap<String, GradientInfo>,
+    filters: HashMap<String, Filter>,
+    masks: HashMap<String, Node>,
+    patterns: HashMap<String, Node>,
+    pattern_tiles: HashMap<PatternTileKey, RenderTargetId>,
+    options: SVGImportOptions,
+}
+
+/// Options that control how an SVG tree is converted to a scene.
+#[derive(Clone, Debug)]
+pub struct SVGImportOptions {
+    /// A transform applied to the whole document after it has been fitted into the viewport.
+    pub transform: Transform2F,
+    /// The size of the viewport that the document's view box is fitted into, in scene units.
+    ///
+    /// If `None` and `dpi` is set, the document's `width` and `height` are used. If both are
+    /// `None`, the view box is used as is, so one user unit becomes one scene unit.
+    pub viewport_size: Option<Vector2F>,
+    /// Overrides the document's `preserveAspectRatio` when fitting it into the viewport.
+    pub preserve_aspect_ratio: Option<AspectRatio>,
+    /// The number of scene units per inch that the document's `width` and `height` are converted
+    /// at. This sizes documents measured in `mm`, `pt`, and so on.
+    ///
+    /// This assumes that the tree was parsed with usvg's default DPI of 96.
+    pub dpi: Option<f32>,
+    /// Whether strokes are widened to a minimum width so that very thin lines remain visible.
+    pub hairline_strokes: bool,
+}
+
+impl Default for SVGImportOptions {
+    #[inline]
+    fn default() -> SVGImportOptions {
+        SVGImportOptions {
+            transform: Transform2F::default(),
+            viewport_size: None,
+            preserve_aspect_ratio: None,
+            dpi: None,
+            hairline_strokes: true,
+        }
+    }
 }
 
 bitflags! {
@@ -60,29 +121,44 @@ bitflags! {
 }
 
 impl SVGScene {
-    // TODO(pcwalton): Allow a global transform to be set.
     #[inline]
     pub fn from_tree(tree: &Tree) -> SVGScene {
         SVGScene::from_tree_and_scene(tree, Scene::new())
     }
 
-    // TODO(pcwalton): Allow a global transform to be set.
+    #[inline]
     pub fn from_tree_and_scene(tree: &Tree, scene: Scene) -> SVGScene {
+        SVGScene::from_tree_with_options(tree, scene, SVGImportOptions::default())
+    }
+
+    /// Converts the tree, appending it to `scene`.
+    ///
+    /// The view box of `scene` is replaced with the bounds of the document's viewport, as
+    /// transformed by `options.transform`. Callers that combine several documents into one scene
+    /// should set the view box afterward.
+    pub fn from_tree_with_options(tree: &Tree, scene: Scene, options: SVGImportOptions)
+                                  -> SVGScene {
         // TODO(pcwalton): Maybe have a `SVGBuilder` type to hold the clip path IDs and other
         // transient data separate from `SVGScene`?
         let mut built_svg = SVGScene {
             scene,
             result_flags: BuildResultFlags::empty(),
             clip_paths: HashMap::new(),
+            clip_path_nodes: HashMap::new(),
             gradients: HashMap::new(),
+            filters: HashMap::new(),
+            masks: HashMap::new(),
+            patterns: HashMap::new(),
+            pattern_tiles: HashMap::new(),
+            options,
         };
 
         let root = &tree.root();
         match *root.borrow() {
             NodeKind::Svg(ref svg) => {
-                built_svg.scene.set_view_box(usvg_rect_to_euclid_rect(&svg.view_box.rect));
+                let state = built_svg.push_root_viewport(svg);
                 for kid in root.children() {
-                    built_svg.process_node(&kid, &State::new(), &mut None);
+                    built_svg.process_node(&kid, &state);
                 }
             }
             _ => unreachable!(),
@@ -91,54 +167,87 @@ impl SVGScene {
         built_svg
     }
 
-    fn process_node(&mut self,
-                    node: &Node,
-                    state: &State,
-                    clip_outline: &mut Option<Outline>) {
+    // Sets the view box of the scene and returns the state that the root's children are drawn
+    // with, which maps the document's view box to the viewport.
+    fn push_root_viewport(&mut self, svg: &Svg) -> State {
+        let view_box = usvg_rect_to_euclid_rect(&svg.view_box.rect);
+        let document_size = vec2f(svg.size.width() as f32, svg.size.height() as f32);
+        let viewport_size = match (self.options.viewport_size, self.options.dpi) {
+            (Some(viewport_size), _) => Some(viewport_size),
+            (None, Some(dpi)) => Some(document_size * (dpi / CSS_PIXELS_PER_INCH)),
+            (None, None) => None,
+        };
+
+        let mut state = State::new();
+        state.transform = self.options.transform;
+        let viewport = match viewport_size {
+            None => view_box,
+            Some(viewport_size) => {
+                let viewport = RectF::new(Vector2F::zero(), viewport_size);
+                let aspect = self.options.preserve_aspect_ratio.unwrap_or(svg.view_box.aspect);
+                if aspect.slice && aspect.align != Align::None {
+                    // The view box overflows the viewport, so clip it.
+                    let mut outline = Outline::from_rect(viewport);
+                    outline.transform(&state.transform);
+                    let mut clip_path = ClipPath::new(outline);
+                    clip_path.set_name("Viewport".to_owned());
+                    state.clip_path = Some(self.scene.push_clip_path(clip_path));
+                }
+                state.transform = state.transform * fit_view_box(view_box, &aspect, viewport);
+                viewport
+            }
+        };
+
+        self.scene.set_view_box(self.options.transform * viewport);
+        state
+    }
+
+    fn process_node(&mut self, node: &Node, state: &State) {
         let mut state = (*state).clone();
         let node_transform = usvg_transform_to_transform_2d(&node.transform());
-        state.transform = node_transform * state.transform;
+        state.transform = state.transform * node_transform;
 
         match *node.borrow() {
             NodeKind::Group(ref group) => {
-                if group.filter.is_some() {
-                    self.result_flags.insert(BuildResultFlags::UNSUPPORTED_FILTER_ATTR);
-                }
-                if group.mask.is_some() {
-                    self.result_flags.insert(BuildResultFlags::UNSUPPORTED_MASK_ATTR);
-                }
-
                 if let Some(ref clip_path_name) = group.clip_path {
-                    if let Some(clip_outline) = self.clip_paths.get(clip_path_name) {
-                        let mut clip_path = ClipPath::new((*clip_outline).clone());
-                        clip_path.set_clip_path(state.clip_path);
-                        clip_path.set_name(format!("ClipPath({})", clip_path_name));
-                        let clip_path_id = self.scene.push_clip_path(clip_path);
-                        state.clip_path = Some(clip_path_id);
+                    if let Some(clip_node) = self.clip_path_nodes.get(clip_path_name).cloned() {
+                        match self.push_svg_clip_path(&clip_node,
+                                                      node,
+                                                      &state.transform,
+                                                      state.clip_path) {
+                            Some(clip_path_id) => state.clip_path = Some(clip_path_id),
+                            None => return,
+                        }
                     }
                 }
 
-                for kid in node.children() {
-                    self.process_node(&kid, &state, clip_outline)
-                }
-            }
-            NodeKind::Path(ref path) if state.path_destination == PathDestination::Clip => {
-                // TODO(pcwalton): Multiple clip paths.
-                let path = UsvgPathToSegments::new(path.data.iter().cloned());
-                let path = Transform2FPathIter::new(path, &state.transform);
-                if clip_outline.is_some() {
-                    self.result_flags.insert(BuildResultFlags::UNSUPPORTED_MULTIPLE_CLIP_PATHS);
+                if let Some(ref mask_name) = group.mask {
+                    match self.masks.get(mask_name).cloned() {
+                        Some(mask_node) => {
+                            self.process_masked_group(node, group, &mask_node, &state);
+                            return;
+                        }
+                        None => self.result_flags.insert(BuildResultFlags::UNSUPPORTED_MASK_ATTR),
+                    }
                 }
-                *clip_outline = Some(Outline::from_segments(path));
+
+                self.process_group_contents(node, group, &state);
             }
             NodeKind::Path(ref path) if state.path_destination == PathDestination::Draw &&
                     path.visibility == Visibility::Visible => {
+                // Paint servers in `objectBoundingBox` units are relative to the bounds of the
+                // fill, even when painting the stroke.
+                let bbox = Outline::from_segments(UsvgPathToSegments::new(path.data
+                                                                              .iter()
+                                                                              .cloned())).bounds();
+
                 if let Some(ref fill) = path.fill {
                     let path = UsvgPathToSegments::new(path.data.iter().cloned());
                     let outline = Outline::from_segments(path);
 
                     let name = format!("Fill({})", node.id());
                     self.push_draw_path(outline,
+                                        bbox,
                                         name,
                                         &state,
                                         &fill.paint,
@@ -148,7 +257,11 @@ impl SVGScene {
 
                 if let Some(ref stroke) = path.stroke {
                     let stroke_style = StrokeStyle {
-                        line_width: f32::max(stroke.width.value() as f32, HAIRLINE_STROKE_WIDTH),
+                        line_width: if self.options.hairline_strokes {
+                            f32::max(stroke.width.value() as f32, HAIRLINE_STROKE_WIDTH)
+                        } else {
+                            stroke.width.value() as f32
+                        },
                         line_cap: LineCap::from_usvg_line_cap(stroke.linecap),
                         line_join: LineJoin::from_usvg_line_join(stroke.linejoin,
                                                                  stroke.miterlimit.value() as f32),
@@ -170,6 +283,7 @@ impl SVGScene {
 
                     let name = format!("Stroke({})", node.id());
                     self.push_draw_path(outline,
+                                        bbox,
                                         name,
                                         &state,
                                         &stroke.paint,
@@ -179,19 +293,16 @@ impl SVGScene {
             }
             NodeKind::Path(..) => {}
             NodeKind::ClipPath(_) => {
-                let mut clip_outline = None;
-                state.path_destination = PathDestination::Clip;
-                for kid in node.children() {
-                    self.process_node(&kid, &state, &mut clip_outline);
-                }
-
-                self.clip_paths.insert(node.id().to_owned(), clip_outline.unwrap());
+                // Clip paths are resolved against the elements that use them.
+                let outline = clip_path_outline(node, &state.transform);
+                self.clip_paths.insert(node.id().to_owned(), outline);
+                self.clip_path_nodes.insert(node.id().to_owned(), node.clone());
             }
             NodeKind::Defs => {
                 // FIXME(pcwalton): This is wrong.
                 state.path_destination = PathDestination::Defs;
                 for kid in node.children() {
-                    self.process_node(&kid, &state, clip_outline);
+                    self.process_node(&kid, &state);
                 }
             }
             NodeKind::LinearGradient(ref svg_linear_gradient) => {
@@ -211,73 +322,158 @@ impl SVGScene {
                                   svg_radial_gradient.id.clone(),
                                   &svg_radial_gradient.base)
             }
-            NodeKind::Filter(..) => {
-                self.result_flags
-                    .insert(BuildResultFlags::UNSUPPORTED_FILTER_NODE);
+            NodeKind::Filter(ref filter) => {
+                self.filters.insert(filter.id.clone(), (*filter).clone());
             }
-            NodeKind::Image(..) => {
-                self.result_flags
-                    .insert(BuildResultFlags::UNSUPPORTED_IMAGE_NODE);
+            NodeKind::Image(ref image) if state.path_destination == PathDestination::Draw &&
+                    image.visibility == Visibility::Visible => {
+                match self.load_image(image) {
+                    Some(pattern_image) => {
+                        let name = format!("Image({})", node.id());
+                        self.push_image(pattern_image, image, name, &state);
+                    }
+                    None => self.result_flags.insert(BuildResultFlags::UNSUPPORTED_IMAGE_NODE),
+                }
             }
-            NodeKind::Mask(..) => {
-                self.result_flags
-                    .insert(BuildResultFlags::UNSUPPORTED_MASK_NODE);
+            NodeKind::Image(..) => {}
+            NodeKind::Mask(ref mask) => {
+                // The mask's content is drawn whenever the mask is used.
+                self.masks.insert(mask.id.clone(), node.clone());
             }
-            NodeKind::Pattern(..) => {
-                self.result_flags
-                    .insert(BuildResultFlags::UNSUPPORTED_PATTERN_NODE);
+            NodeKind::Pattern(ref pattern) => {
+                // The pattern's content is drawn whenever the pattern is used.
+                self.patterns.insert(pattern.id.clone(), node.clone());
             }
             NodeKind::Svg(..) => unreachable!(),
         }
     }
 
-    fn add_gradient(&mut self,
-                    mut gradient: Gradient,
-                    id: String,
-                    usvg_base_gradient: &BaseGradient) {
-        for stop in &usvg_base_gradient.stops {
-            let mut stop = ColorStop::from_usvg_stop(stop);
-            if usvg_base_gradient.spread_method == SpreadMethod::Reflect {
-                stop.offset *= 0.5;
-            }
-            gradient.add(stop);
+    // Decodes the PNG or JPEG data of an image. Nested SVG images aren't supported.
+    fn load_image(&self, image: &UsvgImage) -> Option<Image> {
+        let format = match image.format {
+            UsvgImageFormat::PNG => ImageFormat::Png,
+            UsvgImageFormat::JPEG => ImageFormat::Jpeg,
+            UsvgImageFormat::SVG => return None,
+        };
+        let data = match image.data {
+            ImageData::Raw(ref data) => Cow::Borrowed(&data[..]),
+            // usvg has already resolved the path against its `resources_dir`.
+            ImageData::Path(ref path) => Cow::Owned(fs::read(path).ok()?),
+        };
+        let image_buffer = image::load_from_memory_with_format(&data, format).ok()?;
+        Some(Image::from_image_buffer(image_buffer.to_rgba8()))
+    }
+
+    // Draws an image as a rectangle filled with an image pattern, fitted into the image viewport
+    // according to `preserveAspectRatio`.
+    fn push_image(&mut self, pattern_image: Image, image: &UsvgImage, name: String, state: &State) {
+        let viewport = usvg_rect_to_euclid_rect(&image.view_box.rect);
+        let image_rect = RectF::new(Vector2F::zero(), pattern_image.size().to_f32());
+        let image_transform = fit_view_box(image_rect, &image.view_box.aspect, viewport);
+        let rect = match (image_transform * image_rect).intersection(viewport) {
+            Some(rect) => rect,
+            None => return,
+        };
+
+        let mut pattern = Pattern::from_image(pattern_image);
+        pattern.apply_transform(state.transform * image_transform);
+        if image.rendering_mode == ImageRendering::OptimizeSpeed {
+            pattern.set_smoothing_enabled(false);
         }
+        let paint = self.scene.push_paint(&Paint::from_pattern(pattern));
+
+        let mut outline = Outline::from_rect(rect);
+        outline.transform(&state.transform);
+        let mut path = DrawPath::new(outline, paint);
+        path.set_clip_path(state.clip_path);
+        path.set_name(name);
+        self.scene.push_draw_path(path);
+    }
 
-        // Reflect gradient if necessary.
-        if usvg_base_gradient.spread_method == SpreadMethod::Reflect {
-            for stop in &usvg_base_gradient.stops {
-                let mut stop = ColorStop::from_usvg_stop(stop);
-                if usvg_base_gradient.spread_method == SpreadMethod::Reflect {
-                    stop.offset = 1.0 - stop.offset * 0.5;
+    // Draws the children of a group, applying its filter if it has one. Clip paths and masks
+    // should already have been applied.
+    fn process_group_contents(&mut self, node: &Node, group: &Group, state: &State) {
+        if let Some(ref filter_name) = group.filter {
+            match self.filters.get(filter_name).cloned() {
+                Some(filter) => {
+                    self.process_filtered_group(node, &filter, state);
+                    return;
                 }
-                gradient.add(stop);
+                None => self.result_flags.insert(BuildResultFlags::UNSUPPORTED_FILTER_ATTR),
             }
         }
 
-        match usvg_base_gradient.spread_method {
-            SpreadMethod::Pad => {}
-            SpreadMethod::Reflect | SpreadMethod::Repeat => gradient.wrap = GradientWrap::Repeat,
+        for kid in node.children() {
+            self.process_node(&kid, state)
         }
+    }
 
-        let transform = usvg_transform_to_transform_2d(&usvg_base_gradient.transform);
+    // Composites a render target covering `bounds` in scene space into the scene.
+    fn draw_render_target(&mut self,
+                          render_target: RenderTargetId,
+                          bounds: RectI,
+                          clip_path: Option<ClipPathId>,
+                          name: String) {
+        let mut pattern = Pattern::from_render_target(render_target, bounds.size());
+        pattern.apply_transform(Transform2F::from_translation(bounds.origin().to_f32()));
+        let paint = self.scene.push_paint(&Paint::from_pattern(pattern));
+        let mut path = DrawPath::new(Outline::from_rect(bounds.to_f32()), paint);
+        path.set_clip_path(clip_path);
+        path.set_name(name);
+        self.scene.push_draw_path(path);
+    }
 
-        // TODO(pcwalton): What should we do with `gradientUnits`?
-        self.gradients.insert(id, GradientInfo { gradient, transform });
+    fn add_gradient(&mut self,
+                    mut gradient: Gradient,
+                    id: String,
+                    usvg_base_gradient: &BaseGradient) {
+        for stop in &usvg_base_gradient.stops {
+            gradient.add(ColorStop::from_usvg_stop(stop));
+        }
+
+        gradient.wrap = match usvg_base_gradient.spread_method {
+            SpreadMethod::Pad => GradientWrap::Clamp,
+            SpreadMethod::Reflect => GradientWrap::Reflect,
+            SpreadMethod::Repeat => GradientWrap::Repeat,
+        };
+
+        // Gradients in `objectBoundingBox` units are resolved against each path that uses them.
+        let transform = usvg_transform_to_transform_2d(&usvg_base_gradient.transform);
+        let units = usvg_base_gradient.units;
+        self.gradients.insert(id, GradientInfo { gradient, transform, units });
     }
 
+    // Draws an outline given in user space. `bbox` is the bounding box of the path that the
+    // outline belongs to.
     fn push_draw_path(&mut self,
                       mut outline: Outline,
+                      bbox: RectF,
                       name: String,
                       state: &State,
                       paint: &UsvgPaint,
                       opacity: Opacity,
                       fill_rule: UsvgFillRule) {
         outline.transform(&state.transform);
-        let paint = Paint::from_svg_paint(paint,
-                                          &state.transform,
-                                          opacity,
-                                          &self.gradients,
-                                          &mut self.result_flags);
+        let paint = match *paint {
+            UsvgPaint::Link(ref id) if self.patterns.contains_key(id) => {
+                let pattern_node = self.patterns[id].clone();
+                match self.create_pattern_paint(&pattern_node, bbox, state, opacity) {
+                    Some(paint) => paint,
+                    None => return,
+                }
+            }
+            _ => {
+                match Paint::from_svg_paint(paint,
+                                            &state.transform,
+                                            bbox,
+                                            opacity,
+                                            &self.gradients,
+                                            &mut self.result_flags) {
+                    Some(paint) => paint,
+                    None => return,
+                }
+            }
+        };
         let style = self.scene.push_paint(&paint);
         let fill_rule = FillRule::from_usvg_fill_rule(fill_rule);
         let mut path = DrawPath::new(outline, style);
@@ -323,31 +519,46 @@ impl Display for BuildResultFlags {
     }
 }
 
-trait PaintExt {
+trait PaintExt: Sized {
     fn from_svg_paint(svg_paint: &UsvgPaint,
                       transform: &Transform2F,
+                      bbox: RectF,
                       opacity: Opacity,
                       gradients: &HashMap<String, GradientInfo>,
                       result_flags: &mut BuildResultFlags)
-                      -> Self;
+                      -> Option<Self>;
 }
 
 impl PaintExt for Paint {
+    // `bbox` is the bounding box of the painted path in user space. Returns `None` if nothing
+    // should be drawn, which is the case for gradients relative to an empty bounding box.
     #[inline]
     fn from_svg_paint(svg_paint: &UsvgPaint,
                       transform: &Transform2F,
+                      bbox: RectF,
                       opacity: Opacity,
                       gradients: &HashMap<String, GradientInfo>,
                       result_flags: &mut BuildResultFlags)
-                      -> Paint {
+                      -> Option<Paint> {
         let mut paint;
         match *svg_paint {
             UsvgPaint::Color(color) => paint = Paint::from_color(ColorU::from_svg_color(color)),
             UsvgPaint::Link(ref id) => {
                 match gradients.get(id) {
                     Some(ref gradient_info) => {
+                        let units_transform = match gradient_info.units {
+                            Units::UserSpaceOnUse => Transform2F::default(),
+                            Units::ObjectBoundingBox => {
+                                if !(bbox.width() > 0.0 && bbox.height() > 0.0) {
+                                    return None;
+                                }
+                                object_bounding_box(bbox)
+                            }
+                        };
                         paint = Paint::from_gradient(gradient_info.gradient.clone());
-                        paint.apply_transform(&(*transform * gradient_info.transform));
+                        paint.apply_transform(&(*transform *
+                                                units_transform *
+                                                gradient_info.transform));
                     }
                     None => {
                         // TODO(pcwalton)
@@ -362,7 +573,7 @@ impl PaintExt for Paint {
         base_color.set_a(base_color.a() * opacity.value() as f32);
         paint.set_base_color(base_color.to_u8());
 
-        paint
+        Some(paint)
     }
 }
 
@@ -371,6 +582,85 @@ fn usvg_rect_to_euclid_rect(rect: &UsvgRect) -> RectF {
                vec2f(rect.width() as f32, rect.height() as f32))
 }
 
+// Returns the transform that fits `view_box` into `viewport` according to the given
+// `preserveAspectRatio` value. With `slice`, the content overflows the viewport, and callers must
+// clip it.
+fn fit_view_box(view_box: RectF, aspect: &AspectRatio, viewport: RectF) -> Transform2F {
+    let scale = viewport.size() / view_box.size();
+    let (x_align, y_align) = match aspect.align {
+        Align::None => {
+            return Transform2F::from_translation(viewport.origin()) *
+                Transform2F::from_scale(scale) *
+                Transform2F::from_translation(-view_box.origin());
+        }
+        Align::XMinYMin => (0.0, 0.0),
+        Align::XMidYMin => (0.5, 0.0),
+        Align::XMaxYMin => (1.0, 0.0),
+        Align::XMinYMid => (0.0, 0.5),
+        Align::XMidYMid => (0.5, 0.5),
+        Align::XMaxYMid => (1.0, 0.5),
+        Align::XMinYMax => (0.0, 1.0),
+        Align::XMidYMax => (0.5, 1.0),
+        Align::XMaxYMax => (1.0, 1.0),
+    };
+    let scale = if aspect.slice {
+        f32::max(scale.x(), scale.y())
+    } else {
+        f32::min(scale.x(), scale.y())
+    };
+    let offset = (viewport.size() - view_box.size() * scale) * vec2f(x_align, y_align);
+    Transform2F::from_translation(viewport.origin() + offset) *
+        Transform2F::from_scale(scale) *
+        Transform2F::from_translation(-view_box.origin())
+}
+
+// Resolves the rectangle of a filter, mask, or pattern region against the bounding box of the
+// element that references it. Returns `None` if the rectangle is relative to an empty bounding box.
+fn resolve_units_rect(units: Units, rect: &UsvgRect, bbox: Option<RectF>) -> Option<RectF> {
+    let rect = usvg_rect_to_euclid_rect(rect);
+    match (units, bbox) {
+        (Units::UserSpaceOnUse, _) => Some(rect),
+        (Units::ObjectBoundingBox, Some(bbox)) => Some(object_bounding_box(bbox) * rect),
+        (Units::ObjectBoundingBox, None) => None,
+    }
+}
+
+// Returns the transform that maps `objectBoundingBox` units onto the given bounding box.
+fn object_bounding_box(bbox: RectF) -> Transform2F {
+    Transform2F::from_translation(bbox.origin()) * Transform2F::from_scale(bbox.size())
+}
+
+// Returns the bounding box of the geometry of the node, excluding strokes, in the coordinate
+// system that `transform` maps its parent's user space to.
+fn node_bounds(node: &Node, transform: &Transform2F) -> Option<RectF> {
+    let transform = *transform * usvg_transform_to_transform_2d(&node.transform());
+    match *node.borrow() {
+        NodeKind::Path(ref path) => {
+            let mut outline = Outline::from_segments(UsvgPathToSegments::new(path.data
+                                                                                 .iter()
+                                                                                 .cloned()));
+            if outline.is_empty() {
+                return None;
+            }
+            outline.transform(&transform);
+            Some(outline.bounds())
+        }
+        NodeKind::Group(_) => union_node_bounds(node, &transform),
+        _ => None,
+    }
+}
+
+// Returns the union of the bounds of the children of the given node.
+fn union_node_bounds(node: &Node, transform: &Transform2F) -> Option<RectF> {
+    let mut bounds: Option<RectF> = None;
+    for kid in node.children() {
+        if let Some(kid_bounds) = node_bounds(&kid, transform) {
+            bounds = Some(bounds.map_or(kid_bounds, |bounds| bounds.union_rect(kid_bounds)));
+        }
+    }
+    bounds
+}
+
 fn usvg_transform_to_transform_2d(transform: &UsvgTransform) -> Transform2F {
     Transform2F::row_major(transform.a as f32, transform.c as f32, transform.e as f32,
                            transform.b as f32, transform.d as f32, transform.f as f32)
@@ -550,10 +840,11 @@ impl State {
 enum PathDestination {
     Draw,
     Defs,
-    Clip,
 }
 
 struct GradientInfo {
     gradient: Gradient,
+    // The `gradientTransform`.
     transform: Transform2F,
+    units: Units,
 }
@@ -0,0 +1,150 @@
+// pathfinder/svg/src/mask.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Applies SVG masks.
+//!
+//! The masked group and the content of its mask are drawn into render targets covering the mask
+//! region. The luminance of the mask is then converted to alpha with a color matrix pattern filter
+//! and multiplied into the group with the `DestIn` blend mode before the group is composited into
+//! the scene.
+//!
+//! All masks are luminance masks, as in SVG 1.1, because usvg doesn't expose `mask-type`.
+
+use crate::{BuildResultFlags, PathDestination, SVGScene, State, object_bounding_box};
+use crate::{resolve_units_rect, union_node_bounds};
+use pathfinder_color::matrix::ColorMatrix;
+use pathfinder_content::effects::{BlendMode, PatternFilter};
+use pathfinder_content::outline::Outline;
+use pathfinder_content::pattern::Pattern;
+use pathfinder_content::render_target::RenderTargetId;
+use pathfinder_geometry::rect::{RectF, RectI};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::Vector2F;
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::{ClipPath, DrawPath, RenderTarget};
+use usvg::{Group, Node, NodeExt, NodeKind, Units};
+
+impl SVGScene {
+    /// Draws the given group through the mask defined by `mask_node`.
+    pub(crate) fn process_masked_group(&mut self,
+                                       node: &Node,
+                                       group: &Group,
+                                       mask_node: &Node,
+                                       state: &State) {
+        let bbox = union_node_bounds(node, &Transform2F::default());
+
+        // A mask region relative to an empty bounding box disables rendering of the element.
+        let region = match mask_region(mask_node, bbox) {
+            None => return,
+            Some(region) => region,
+        };
+        let bounds = (state.transform * region).round_out().to_i32();
+        if bounds.width() <= 0 || bounds.height() <= 0 {
+            return;
+        }
+
+        let transform = Transform2F::from_translation(-bounds.origin().to_f32()) * state.transform;
+        let mask = match self.draw_mask(mask_node, bbox, &transform, bounds) {
+            None => return,
+            Some(mask) => mask,
+        };
+
+        // Clipping applies to the masked result, not the content.
+        let name = format!("MaskedContent({})", node.id());
+        let content = self.scene.push_render_target(RenderTarget::new(bounds.size(), name));
+        let mut content_state = state.clone();
+        content_state.transform = transform;
+        content_state.clip_path = None;
+        self.process_group_contents(node, group, &content_state);
+        self.multiply_by_mask(mask, bounds);
+        self.scene.pop_render_target();
+
+        let name = format!("Mask({})", mask_node.id());
+        self.draw_render_target(content, bounds, state.clip_path, name);
+    }
+
+    // Draws the content of a mask, clipped to the mask region, into a new render target. The
+    // render target covers `bounds` in scene space, and `transform` maps the user space of the
+    // masked element to its pixels.
+    fn draw_mask(&mut self,
+                 mask_node: &Node,
+                 bbox: Option<RectF>,
+                 transform: &Transform2F,
+                 bounds: RectI)
+                 -> Option<RenderTargetId> {
+        let mask = match *mask_node.borrow() {
+            NodeKind::Mask(ref mask) => mask.clone(),
+            _ => return None,
+        };
+        let region = mask_region(mask_node, bbox)?;
+
+        // Masks can themselves be masked.
+        let nested_mask = match mask.mask {
+            None => None,
+            Some(ref nested_mask_name) => {
+                match self.masks.get(nested_mask_name).cloned() {
+                    Some(nested_mask_node) => {
+                        Some(self.draw_mask(&nested_mask_node, bbox, transform, bounds)?)
+                    }
+                    None => {
+                        self.result_flags.insert(BuildResultFlags::UNSUPPORTED_MASK_ATTR);
+                        None
+                    }
+                }
+            }
+        };
+
+        let mut region_outline = Outline::from_rect(region);
+        region_outline.transform(transform);
+        let mut clip_path = ClipPath::new(region_outline);
+        clip_path.set_name(format!("MaskRegion({})", mask.id));
+        let clip_path = self.scene.push_clip_path(clip_path);
+
+        let content_transform = match (mask.content_units, bbox) {
+            (Units::UserSpaceOnUse, _) => Transform2F::default(),
+            (Units::ObjectBoundingBox, Some(bbox)) => object_bounding_box(bbox),
+            (Units::ObjectBoundingBox, None) => return None,
+        };
+        let state = State {
+            path_destination: PathDestination::Draw,
+            transform: *transform * content_transform,
+            clip_path: Some(clip_path),
+        };
+
+        let name = format!("Mask({})", mask.id);
+        let render_target = self.scene.push_render_target(RenderTarget::new(bounds.size(), name));
+        for kid in mask_node.children() {
+            self.process_node(&kid, &state);
+        }
+        if let Some(nested_mask) = nested_mask {
+            self.multiply_by_mask(nested_mask, bounds);
+        }
+        self.scene.pop_render_target();
+        Some(render_target)
+    }
+
+    // Multiplies the current render target by the luminance of the given mask.
+    fn multiply_by_mask(&mut self, mask: RenderTargetId, bounds: RectI) {
+        let mut pattern = Pattern::from_render_target(mask, bounds.size());
+        pattern.set_filter(Some(PatternFilter::ColorMatrix(ColorMatrix::luminance_to_alpha())));
+        let paint = self.scene.push_paint(&Paint::from_pattern(pattern));
+        let rect = RectF::new(Vector2F::zero(), bounds.size().to_f32());
+        let mut path = DrawPath::new(Outline::from_rect(rect), paint);
+        path.set_blend_mode(BlendMode::DestIn);
+        self.scene.push_draw_path(path);
+    }
+}
+
+fn mask_region(mask_node: &Node, bbox: Option<RectF>) -> Option<RectF> {
+    match *mask_node.borrow() {
+        NodeKind::Mask(ref mask) => resolve_units_rect(mask.units, &mask.rect, bbox),
+        _ => None,
+    }
+}
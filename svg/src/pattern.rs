@@ -0,0 +1,122 @@
+// pathfinder/svg/src/pattern.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Paints with SVG patterns.
+//!
+//! The content of a single tile is drawn into a render target at roughly the resolution it will be
+//! displayed at, and the render target is then used as a repeating pattern, as in
+//! `CanvasRenderingContext2D::create_pattern_from_canvas()`. Paths that use the same tile at the
+//! same size share its render target.
+
+use crate::{PathDestination, SVGScene, State, fit_view_box, resolve_units_rect};
+use crate::{usvg_rect_to_euclid_rect, usvg_transform_to_transform_2d};
+use pathfinder_color::ColorU;
+use pathfinder_content::pattern::Pattern;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I};
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::RenderTarget;
+use usvg::{Node, NodeKind, Opacity, Units};
+
+impl SVGScene {
+    /// Draws a tile of the pattern defined by `pattern_node` and returns a paint that repeats it.
+    ///
+    /// `bbox` is the bounding box of the painted path in user space. Returns `None` if the pattern
+    /// is empty, in which case nothing should be drawn.
+    pub(crate) fn create_pattern_paint(&mut self,
+                                       pattern_node: &Node,
+                                       bbox: RectF,
+                                       state: &State,
+                                       opacity: Opacity)
+                                       -> Option<Paint> {
+        let pattern = match *pattern_node.borrow() {
+            NodeKind::Pattern(ref pattern) => pattern.clone(),
+            _ => return None,
+        };
+
+        let bbox = if bbox.width() > 0.0 && bbox.height() > 0.0 { Some(bbox) } else { None };
+        let tile = resolve_units_rect(pattern.units, &pattern.rect, bbox)?;
+        if !(tile.width() > 0.0 && tile.height() > 0.0) {
+            return None;
+        }
+
+        // Maps the pattern content to the tile, with the tile's origin at (0, 0).
+        let content_transform = match (pattern.view_box, pattern.content_units, bbox) {
+            (Some(view_box), _, _) => {
+                let tile_rect = RectF::new(Vector2F::zero(), tile.size());
+                fit_view_box(usvg_rect_to_euclid_rect(&view_box.rect), &view_box.aspect, tile_rect)
+            }
+            (None, Units::UserSpaceOnUse, _) => Transform2F::default(),
+            (None, Units::ObjectBoundingBox, Some(bbox)) => Transform2F::from_scale(bbox.size()),
+            (None, Units::ObjectBoundingBox, None) => return None,
+        };
+
+        // Draw the tile at its size on screen, capped at the size of the scene so that huge
+        // tiles don't allocate huge render targets.
+        let tile_transform = state.transform *
+            usvg_transform_to_transform_2d(&pattern.transform) *
+            Transform2F::from_translation(tile.origin());
+        let max_size = self.scene.view_box().size().max(Vector2F::splat(1.0));
+        let size = (tile.size() * tile_transform.extract_scale()).min(max_size).ceil().to_i32();
+        let size = size.max(Vector2I::splat(1));
+        let scale = size.to_f32() / tile.size();
+
+        // The content of a tile depends on the size of the bounding box if anything is in
+        // `objectBoundingBox` units, but never on its position.
+        let uses_bbox = pattern.units == Units::ObjectBoundingBox ||
+            (pattern.view_box.is_none() && pattern.content_units == Units::ObjectBoundingBox);
+        let key = PatternTileKey {
+            id: pattern.id.clone(),
+            size,
+            bbox_size: bbox.filter(|_| uses_bbox).map(|bbox| {
+                [bbox.width().to_bits(), bbox.height().to_bits()]
+            }),
+        };
+        let render_target = match self.pattern_tiles.get(&key) {
+            Some(&render_target) => render_target,
+            None => {
+                let name = format!("Pattern({})", pattern.id);
+                let render_target = self.scene.push_render_target(RenderTarget::new(size, name));
+                let tile_state = State {
+                    path_destination: PathDestination::Draw,
+                    transform: Transform2F::from_scale(scale) * content_transform,
+                    clip_path: None,
+                };
+                for kid in pattern_node.children() {
+                    self.process_node(&kid, &tile_state);
+                }
+                self.scene.pop_render_target();
+                self.pattern_tiles.insert(key, render_target);
+                render_target
+            }
+        };
+
+        let mut pattern = Pattern::from_render_target(render_target, size);
+        let tile_to_pixels = Transform2F::from_scale(scale);
+        pattern.apply_transform(tile_transform * tile_to_pixels.inverse());
+        pattern.set_repeat_x(true);
+        pattern.set_repeat_y(true);
+
+        let mut paint = Paint::from_pattern(pattern);
+        let alpha = (opacity.value() as f32 * 255.0).round() as u8;
+        paint.set_base_color(ColorU::new(255, 255, 255, alpha));
+        Some(paint)
+    }
+}
+
+// Identifies a drawn pattern tile, so that paths that share a pattern can share its render target.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct PatternTileKey {
+    id: String,
+    size: Vector2I,
+    // The bits of the size of the bounding box, if the tile depends on it.
+    bbox_size: Option<[u32; 2]>,
+}
@@ -0,0 +1,652 @@
+// pathfinder/svg/src/tests.rs
+//
+// For this file only, any copyright is dedicated to the Public Domain.
+// https://creativecommons.org/publicdomain/zero/1.0/
+
+use crate::{BuildResultFlags, SVGImportOptions, SVGScene};
+use image::png::PngEncoder;
+use image::ColorType;
+use pathfinder_color::ColorU;
+use pathfinder_content::pattern::Image;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f, vec2i};
+use pathfinder_renderer::options::BuildOptions;
+use pathfinder_renderer::scene::{DisplayItem, Scene};
+use std::env;
+use std::fs;
+use std::process;
+use usvg::{Align, AspectRatio, Options as UsvgOptions, Tree};
+
+const HEADER: &str = "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+                      xmlns:xlink=\"http://www.w3.org/1999/xlink\" width=\"100\" height=\"100\">";
+
+// Renders the given elements in a 100x100 document.
+fn render(body: &str) -> (SVGScene, Image) {
+    let svg = format!("{}{}</svg>", HEADER, body);
+    render_document(&svg, SVGImportOptions::default(), vec2i(100, 100))
+}
+
+fn render_document(svg: &str, options: SVGImportOptions, size: Vector2I) -> (SVGScene, Image) {
+    let tree = Tree::from_str(svg, &UsvgOptions::default()).unwrap();
+    let mut svg_scene = SVGScene::from_tree_with_options(&tree, Scene::new(), options);
+    let image = svg_scene.scene.rasterize(size, BuildOptions::default());
+    (svg_scene, image)
+}
+
+fn pixel(image: &Image, x: i32, y: i32) -> ColorU {
+    image.pixels()[(y * image.size().x() + x) as usize]
+}
+
+// Every render target that content passes through loses a little coverage at tile edges, so
+// colors that went through one are compared with a tolerance.
+fn assert_near(actual: ColorU, expected: ColorU) {
+    let close = |a: u8, b: u8| (a as i32 - b as i32).abs() <= 4;
+    assert!(close(actual.r, expected.r) && close(actual.g, expected.g) &&
+            close(actual.b, expected.b) && close(actual.a, expected.a),
+            "expected {:?}, found {:?}",
+            expected,
+            actual);
+}
+
+#[test]
+fn test_filter_drop_shadow() {
+    let (svg, image) = render(r#"
+        <filter id="f" x="-50%" y="-50%" width="200%" height="200%">
+            <feGaussianBlur in="SourceAlpha" stdDeviation="2"/>
+            <feOffset dx="20" dy="20" result="shadow"/>
+            <feMerge><feMergeNode in="shadow"/><feMergeNode in="SourceGraphic"/></feMerge>
+        </filter>
+        <rect x="10" y="10" width="30" height="30" fill="red" filter="url(#f)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_near(pixel(&image, 20, 20), ColorU::new(255, 0, 0, 255));
+    let shadow = pixel(&image, 50, 50);
+    assert_eq!((shadow.r, shadow.a), (0, 255));
+    let edge = pixel(&image, 45, 30);
+    assert!(edge.a > 40 && edge.a < 220, "{:?}", edge);
+    assert_eq!(pixel(&image, 80, 80).a, 0);
+}
+
+#[test]
+fn test_filter_color_matrix_and_flood_subregion() {
+    // The matrix swaps red and blue. The flood is limited to its primitive subregion.
+    let (svg, image) = render(r#"
+        <filter id="f" filterUnits="userSpaceOnUse" x="0" y="0" width="100" height="100">
+            <feColorMatrix type="matrix" result="m"
+                           values="0 0 1 0 0  0 1 0 0 0  1 0 0 0 0  0 0 0 1 0"/>
+            <feFlood flood-color="lime" x="60" y="60" width="20" height="20"/>
+            <feComposite in2="m" operator="over"/>
+        </filter>
+        <rect x="10" y="10" width="30" height="30" fill="red" filter="url(#f)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_near(pixel(&image, 20, 20), ColorU::new(0, 0, 255, 255));
+    assert_near(pixel(&image, 70, 70), ColorU::new(0, 255, 0, 255));
+    assert_eq!(pixel(&image, 50, 50).a, 0);
+}
+
+#[test]
+fn test_filter_composite_in_and_blend() {
+    let (svg, image) = render(r#"
+        <filter id="f" filterUnits="userSpaceOnUse" x="0" y="0" width="100" height="100">
+            <feFlood flood-color="blue" result="blue"/>
+            <feComposite in="blue" in2="SourceGraphic" operator="in" result="clipped"/>
+            <feBlend in="clipped" in2="SourceGraphic" mode="normal"/>
+        </filter>
+        <g filter="url(#f)" transform="translate(5,5)">
+            <rect x="5" y="5" width="30" height="30" fill="white"/>
+        </g>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_near(pixel(&image, 25, 25), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 60, 60).a, 0);
+    assert_eq!(pixel(&image, 8, 8).a, 0);
+}
+
+#[test]
+fn test_filter_unsupported_primitive() {
+    let (svg, _) = render(r#"
+        <filter id="f"><feTurbulence baseFrequency="0.1"/></filter>
+        <rect x="10" y="10" width="30" height="30" fill="red" filter="url(#f)"/>"#);
+    assert!(svg.result_flags.contains(BuildResultFlags::UNSUPPORTED_FILTER_NODE));
+}
+
+#[test]
+fn test_filter_primitive_units_with_empty_bounding_box() {
+    // A horizontal line has no height, so primitive subregions in its bounding box units are
+    // undefined, and the element isn't rendered.
+    let (_, image) = render(r#"
+        <filter id="f" filterUnits="userSpaceOnUse" x="0" y="0" width="100" height="100"
+                primitiveUnits="objectBoundingBox">
+            <feOffset dx="0.1"/>
+        </filter>
+        <path d="M10 50H90" stroke="blue" stroke-width="10" filter="url(#f)"/>"#);
+    assert!(image.pixels().iter().all(|pixel| pixel.a == 0));
+}
+
+#[test]
+fn test_mask_luminance_and_region() {
+    // The left half of the mask is white and the right half is 50% gray. The bottom is outside
+    // the mask region.
+    let (svg, image) = render(r#"
+        <mask id="m" maskUnits="userSpaceOnUse" x="0" y="0" width="100" height="60">
+            <rect x="0" y="0" width="50" height="100" fill="white"/>
+            <rect x="50" y="0" width="50" height="100" fill="rgb(128,128,128)"/>
+        </mask>
+        <rect x="0" y="0" width="100" height="100" fill="red" mask="url(#m)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_near(pixel(&image, 25, 25), ColorU::new(255, 0, 0, 255));
+    assert_near(pixel(&image, 75, 25), ColorU::new(128, 0, 0, 128));
+    assert_eq!(pixel(&image, 25, 80).a, 0);
+}
+
+#[test]
+fn test_mask_object_bounding_box_and_nested() {
+    // The outer mask keeps the left half of the bounding box, and its own mask keeps the top half.
+    let (svg, image) = render(r#"
+        <mask id="inner" maskContentUnits="objectBoundingBox">
+            <rect x="0" y="0" width="1" height="0.5" fill="white"/>
+        </mask>
+        <mask id="outer" maskContentUnits="objectBoundingBox" mask="url(#inner)">
+            <rect x="0" y="0" width="0.5" height="1" fill="white"/>
+        </mask>
+        <g transform="translate(10,10)">
+            <rect x="10" y="10" width="60" height="60" fill="blue" mask="url(#outer)"/>
+        </g>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_near(pixel(&image, 30, 30), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 70, 30).a, 0);
+    assert_eq!(pixel(&image, 30, 70).a, 0);
+    assert_eq!(pixel(&image, 70, 70).a, 0);
+}
+
+#[test]
+fn test_mask_missing() {
+    // usvg drops references to missing masks, so the element is drawn unmasked.
+    let (svg, image) = render(r#"
+        <rect x="0" y="0" width="100" height="100" fill="red" mask="url(#missing)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_eq!(pixel(&image, 50, 50), ColorU::new(255, 0, 0, 255));
+}
+
+// Returns a 2x2 PNG image with red and green pixels on top and blue and white ones below.
+fn quad_image_png() -> Vec<u8> {
+    let pixels = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255];
+    let mut png = vec![];
+    PngEncoder::new(&mut png).encode(&pixels, 2, 2, ColorType::Rgba8).unwrap();
+    png
+}
+
+// Returns a data URL for the image from `quad_image_png()`.
+fn quad_image_url() -> String {
+    const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut url = "data:image/png;base64,".to_owned();
+    for chunk in quad_image_png().chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                url.push(BASE64[(bits >> (18 - 6 * index) & 63) as usize] as char);
+            } else {
+                url.push('=');
+            }
+        }
+    }
+    url
+}
+
+#[test]
+fn test_image_meet() {
+    // The image is scaled to 40x40 and centered horizontally.
+    let (svg, image) = render(&format!(r#"
+        <image x="10" y="10" width="80" height="40" image-rendering="optimizeSpeed"
+               xlink:href="{}"/>"#,
+        quad_image_url()));
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_eq!(pixel(&image, 20, 20).a, 0);
+    assert_eq!(pixel(&image, 35, 15), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 65, 15), ColorU::new(0, 255, 0, 255));
+    assert_eq!(pixel(&image, 35, 45), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 80, 20).a, 0);
+}
+
+#[test]
+fn test_image_slice_and_none() {
+    let (svg, image) = render(&format!(r#"
+        <image x="10" y="10" width="80" height="40" preserveAspectRatio="xMinYMin slice"
+               image-rendering="optimizeSpeed" xlink:href="{0}"/>
+        <image x="10" y="60" width="80" height="20" preserveAspectRatio="none"
+               image-rendering="optimizeSpeed" xlink:href="{0}"/>"#,
+        quad_image_url()));
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+
+    // Slicing scales the image to 80x80 and clips it to the top half.
+    assert_eq!(pixel(&image, 20, 20), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 80, 45), ColorU::new(0, 255, 0, 255));
+    assert_eq!(pixel(&image, 50, 52).a, 0);
+
+    // `none` stretches it.
+    assert_eq!(pixel(&image, 20, 65), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 80, 75), ColorU::white());
+}
+
+#[test]
+fn test_image_transform_and_clip() {
+    let (svg, image) = render(&format!(r#"
+        <clipPath id="c"><rect x="0" y="0" width="30" height="100"/></clipPath>
+        <g clip-path="url(#c)">
+            <image transform="translate(10,10)" width="40" height="40"
+                   image-rendering="optimizeSpeed" xlink:href="{}"/>
+        </g>"#,
+        quad_image_url()));
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_eq!(pixel(&image, 12, 12), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 12, 45), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 40, 15).a, 0);
+}
+
+#[test]
+fn test_image_external_file() {
+    // Relative paths are resolved against usvg's resources directory, not the current directory.
+    let dir = env::temp_dir().join(format!("pathfinder-svg-image-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("quad.png"), quad_image_png()).unwrap();
+
+    let svg = format!(r#"{}
+        <image x="10" y="10" width="40" height="40" image-rendering="optimizeSpeed"
+               xlink:href="quad.png"/>
+        </svg>"#,
+        HEADER);
+    let usvg_options = UsvgOptions { resources_dir: Some(dir.clone()), ..UsvgOptions::default() };
+    let tree = Tree::from_str(&svg, &usvg_options).unwrap();
+    let mut svg = SVGScene::from_tree(&tree);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let image = svg.scene.rasterize(vec2i(100, 100), BuildOptions::default());
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_eq!(pixel(&image, 20, 20), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 40, 40), ColorU::white());
+}
+
+#[test]
+fn test_pattern_user_space() {
+    // 20x20 tiles with a red left half and a blue right half.
+    let (svg, image) = render(r#"
+        <pattern id="p" patternUnits="userSpaceOnUse" x="0" y="0" width="20" height="20">
+            <rect x="0" y="0" width="10" height="20" fill="red"/>
+            <rect x="10" y="0" width="10" height="20" fill="blue"/>
+        </pattern>
+        <rect x="0" y="0" width="100" height="60" fill="url(#p)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    for &(x, y) in &[(5, 5), (25, 35), (85, 50)] {
+        assert_near(pixel(&image, x, y), ColorU::new(255, 0, 0, 255));
+    }
+    for &(x, y) in &[(13, 5), (53, 35), (93, 50)] {
+        assert_near(pixel(&image, x, y), ColorU::new(0, 0, 255, 255));
+    }
+    assert_eq!(pixel(&image, 50, 80).a, 0);
+}
+
+#[test]
+fn test_pattern_object_bounding_box_and_opacity() {
+    // The tiles are a quarter of the bounding box, and so is their content.
+    let (svg, image) = render(r#"
+        <pattern id="p" width="0.5" height="0.5" patternContentUnits="objectBoundingBox">
+            <rect x="0" y="0" width="0.25" height="0.25" fill="lime"/>
+        </pattern>
+        <rect x="20" y="20" width="80" height="80" fill="url(#p)" fill-opacity="0.5"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    for &(x, y) in &[(25, 25), (65, 25), (65, 65)] {
+        assert_near(pixel(&image, x, y), ColorU::new(0, 255, 0, 128));
+    }
+    assert_eq!(pixel(&image, 45, 25).a, 0);
+    assert_eq!(pixel(&image, 25, 45).a, 0);
+}
+
+#[test]
+fn test_pattern_view_box_and_transform() {
+    // A 10x10 view box is scaled into 20x20 tiles, which are then moved 5 units to the right.
+    let (svg, image) = render(r#"
+        <pattern id="p" patternUnits="userSpaceOnUse" width="20" height="20" viewBox="0 0 10 10"
+                 patternTransform="translate(5,0)">
+            <rect x="0" y="0" width="5" height="10" fill="red"/>
+        </pattern>
+        <g transform="translate(0,10)"><rect width="100" height="40" fill="url(#p)"/></g>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_near(pixel(&image, 8, 20), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 2, 20).a, 0);
+    assert_eq!(pixel(&image, 20, 20).a, 0);
+    assert_near(pixel(&image, 28, 45), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 28, 5).a, 0);
+}
+
+#[test]
+fn test_pattern_stroke_and_mask() {
+    let (svg, image) = render(r#"
+        <pattern id="p" patternUnits="userSpaceOnUse" width="10" height="10">
+            <rect width="10" height="10" fill="blue"/>
+        </pattern>
+        <mask id="m" maskUnits="userSpaceOnUse" x="0" y="0" width="100" height="100">
+            <rect x="0" y="0" width="50" height="100" fill="white"/>
+        </mask>
+        <rect x="10" y="10" width="80" height="30" fill="url(#p)" mask="url(#m)"/>
+        <rect x="20" y="60" width="60" height="20" fill="none" stroke="url(#p)"
+              stroke-width="6"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_near(pixel(&image, 30, 20), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 70, 20).a, 0);
+    assert_near(pixel(&image, 50, 61), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 50, 70).a, 0);
+}
+
+#[test]
+fn test_pattern_tiles_are_shared() {
+    // Paths of different sizes share a user space tile, but not a bounding box one.
+    let (svg, image) = render(r#"
+        <pattern id="p" patternUnits="userSpaceOnUse" width="10" height="10">
+            <rect width="5" height="10" fill="red"/>
+        </pattern>
+        <pattern id="q" width="0.5" height="1">
+            <rect width="5" height="10" fill="blue"/>
+        </pattern>
+        <rect width="100" height="20" fill="url(#p)"/>
+        <rect y="20" width="50" height="20" fill="url(#p)"/>
+        <rect y="40" width="100" height="20" stroke="black" fill="url(#p)"/>
+        <rect y="60" width="20" height="10" fill="url(#q)"/>
+        <rect y="80" width="20" height="10" fill="url(#q)"/>
+        <rect x="50" y="60" width="40" height="20" fill="url(#q)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    let render_target_count = svg.scene.display_list().iter().filter(|item| {
+        match **item {
+            DisplayItem::PushRenderTarget(_) => true,
+            _ => false,
+        }
+    }).count();
+    assert_eq!(render_target_count, 3);
+    assert_near(pixel(&image, 2, 25), ColorU::new(255, 0, 0, 255));
+    assert_near(pixel(&image, 2, 85), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 7, 85).a, 0);
+    assert_near(pixel(&image, 52, 65), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 57, 65).a, 0);
+}
+
+#[test]
+fn test_options_viewport_meet_slice_and_none() {
+    let document = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="20"
+                           viewBox="0 0 10 20">
+        <rect width="10" height="20" fill="red"/>
+        <rect width="10" height="5" fill="blue"/>
+    </svg>"#;
+
+    // By default, the document is centered in the viewport.
+    let options = SVGImportOptions {
+        viewport_size: Some(vec2f(40.0, 40.0)),
+        ..SVGImportOptions::default()
+    };
+    let (svg, image) = render_document(document, options, vec2i(40, 40));
+    assert_eq!(svg.scene.view_box(), RectF::new(Vector2F::zero(), vec2f(40.0, 40.0)));
+    assert_eq!(pixel(&image, 5, 20).a, 0);
+    assert_eq!(pixel(&image, 20, 20), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 20, 5), ColorU::new(0, 0, 255, 255));
+
+    // With `slice`, the document covers the viewport and is clipped to it.
+    let mut aspect = AspectRatio { defer: false, align: Align::XMidYMid, slice: true };
+    let options = SVGImportOptions {
+        viewport_size: Some(vec2f(40.0, 40.0)),
+        preserve_aspect_ratio: Some(aspect),
+        ..SVGImportOptions::default()
+    };
+    let (_, image) = render_document(document, options, vec2i(80, 80));
+    assert_eq!(pixel(&image, 5, 20), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 5, 39), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 5, 41).a, 0);
+    assert_eq!(pixel(&image, 45, 5).a, 0);
+    assert_ne!(pixel(&image, 20, 2), ColorU::new(0, 0, 255, 255));
+
+    // With `none`, the document is stretched to fill the viewport.
+    aspect.align = Align::None;
+    let options = SVGImportOptions {
+        viewport_size: Some(vec2f(40.0, 40.0)),
+        preserve_aspect_ratio: Some(aspect),
+        ..SVGImportOptions::default()
+    };
+    let (_, image) = render_document(document, options, vec2i(40, 40));
+    assert_eq!(pixel(&image, 2, 20), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 20, 8), ColorU::new(0, 0, 255, 255));
+}
+
+#[test]
+fn test_options_dpi_and_transform() {
+    let document = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10mm" height="5mm"
+                           viewBox="0 0 10 5">
+        <rect width="5" height="5" fill="red"/>
+    </svg>"#;
+
+    // At 254 DPI, a millimeter is ten pixels.
+    let options = SVGImportOptions { dpi: Some(254.0), ..SVGImportOptions::default() };
+    let (svg, image) = render_document(document, options, vec2i(100, 100));
+    let size = svg.scene.view_box().size();
+    assert!((size.x() - 100.0).abs() < 0.01 && (size.y() - 50.0).abs() < 0.01, "{:?}", size);
+    assert_eq!(pixel(&image, 45, 45), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 55, 45).a, 0);
+
+    // The transform applies on top of the viewport, and moves the view box with it.
+    let options = SVGImportOptions {
+        viewport_size: Some(vec2f(20.0, 10.0)),
+        transform: Transform2F::from_translation(vec2f(30.0, 40.0)),
+        ..SVGImportOptions::default()
+    };
+    let (svg, image) = render_document(document, options, vec2i(100, 100));
+    assert_eq!(svg.scene.view_box(), RectF::new(vec2f(30.0, 40.0), vec2f(20.0, 10.0)));
+    assert_eq!(pixel(&image, 35, 45), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 45, 45).a, 0);
+    assert_eq!(pixel(&image, 5, 5).a, 0);
+
+    // Without options, the view box is the document's size in user units.
+    let (svg, _) = render_document(document, SVGImportOptions::default(), vec2i(10, 10));
+    assert_eq!(svg.scene.view_box(), RectF::new(Vector2F::zero(), vec2f(10.0, 5.0)));
+}
+
+#[test]
+fn test_options_hairline_strokes() {
+    let document = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+        <path d="M0 5.5 L10 5.5" stroke="black" stroke-width="0.01"/>
+    </svg>"#;
+    let (_, widened) = render_document(document, SVGImportOptions::default(), vec2i(10, 10));
+    let options = SVGImportOptions { hairline_strokes: false, ..SVGImportOptions::default() };
+    let (_, exact) = render_document(document, options, vec2i(10, 10));
+    let (widened, exact) = (pixel(&widened, 5, 5).a, pixel(&exact, 5, 5).a);
+    assert!(widened > exact && exact <= 4, "{} {}", widened, exact);
+}
+
+#[test]
+fn test_gradient_object_bounding_box() {
+    let (svg, image) = render(r#"
+        <linearGradient id="g">
+            <stop offset="0" stop-color="red"/><stop offset="1" stop-color="blue"/>
+        </linearGradient>
+        <linearGradient id="d" x2="1" y2="1">
+            <stop offset="0" stop-color="red"/><stop offset="1" stop-color="blue"/>
+        </linearGradient>
+        <rect x="10" y="10" width="80" height="20" fill="url(#g)"/>
+        <rect x="0" y="50" width="100" height="20" fill="url(#d)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    let (left, middle) = (pixel(&image, 11, 20), pixel(&image, 50, 20));
+    let right = pixel(&image, 88, 20);
+    assert!(left.r > 240 && left.b < 15, "{:?}", left);
+    assert!(right.b > 240 && right.r < 15, "{:?}", right);
+    assert!((middle.r as i32 - middle.b as i32).abs() < 10, "{:?}", middle);
+
+    // The stops stay parallel to the box's diagonals.
+    let (top_right, bottom_left) = (pixel(&image, 90, 50), pixel(&image, 0, 68));
+    assert_near(top_right, bottom_left);
+    assert!((top_right.r as i32 - top_right.b as i32).abs() < 20, "{:?}", top_right);
+}
+
+#[test]
+fn test_gradient_transform_and_radial_focus() {
+    let (svg, image) = render(r#"
+        <linearGradient id="g" gradientUnits="userSpaceOnUse" x2="100"
+                        gradientTransform="rotate(90)">
+            <stop offset="0" stop-color="red"/><stop offset="1" stop-color="blue"/>
+        </linearGradient>
+        <radialGradient id="r" fx="0.25">
+            <stop offset="0" stop-color="red"/><stop offset="1" stop-color="blue"/>
+        </radialGradient>
+        <rect width="50" height="100" fill="url(#g)"/>
+        <rect x="50" y="40" width="50" height="20" fill="url(#r)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    let (top, bottom) = (pixel(&image, 25, 1), pixel(&image, 25, 98));
+    assert!(top.r > 240 && bottom.b > 240, "{:?} {:?}", top, bottom);
+    assert_near(pixel(&image, 5, 50), pixel(&image, 45, 50));
+
+    // The focal point is at a quarter of the box's width.
+    let focus = pixel(&image, 62, 50);
+    assert!(focus.r > 230, "{:?}", focus);
+    let (left, right) = (pixel(&image, 52, 50), pixel(&image, 98, 50));
+    assert!(left.b > 100 && right.b > 200 && right.b > left.b, "{:?} {:?}", left, right);
+    assert_near(pixel(&image, 75, 43), pixel(&image, 75, 56));
+    assert_eq!(pixel(&image, 75, 35).a, 0);
+}
+
+#[test]
+fn test_gradient_object_bounding_box_empty() {
+    // A horizontal line has no height, so a bounding box gradient can't paint it.
+    let (_, image) = render(r#"
+        <linearGradient id="g">
+            <stop offset="0" stop-color="red"/><stop offset="1" stop-color="blue"/>
+        </linearGradient>
+        <path d="M10 50 L90 50" stroke="url(#g)" stroke-width="10"/>"#);
+    assert_eq!(pixel(&image, 50, 50).a, 0);
+}
+
+#[test]
+fn test_clip_multiple_children_with_transforms() {
+    let (svg, image) = render(r#"
+        <clipPath id="c">
+            <rect x="0" y="0" width="20" height="20"/>
+            <rect x="0" y="0" width="20" height="20" transform="translate(50, 50)"/>
+        </clipPath>
+        <g transform="translate(10, 10)" clip-path="url(#c)">
+            <rect x="0" y="0" width="80" height="80" fill="red"/>
+        </g>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_eq!(pixel(&image, 20, 20), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 70, 70), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 45, 45).a, 0);
+    assert_eq!(pixel(&image, 5, 5).a, 0);
+}
+
+#[test]
+fn test_clip_overlapping_children_are_unioned() {
+    // The second square is wound the other way, and the last two use the even-odd rule. Their
+    // overlaps must stay visible.
+    let (svg, image) = render(r#"
+        <clipPath id="c">
+            <path d="M0 0H30V30H0Z"/>
+            <path d="M20 20V50H50V20Z"/>
+            <path clip-rule="evenodd" d="M60 0H90V30H60Z"/>
+            <path clip-rule="evenodd" d="M70 10H100V40H70Z"/>
+        </clipPath>
+        <rect width="100" height="100" fill="blue" clip-path="url(#c)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    for &(x, y) in &[(10, 10), (25, 25), (40, 40), (65, 5), (80, 20), (95, 35)] {
+        assert_eq!(pixel(&image, x, y), ColorU::new(0, 0, 255, 255), "at {}, {}", x, y);
+    }
+    assert_eq!(pixel(&image, 40, 10).a, 0);
+    assert_eq!(pixel(&image, 65, 35).a, 0);
+}
+
+#[test]
+fn test_clip_rule_even_odd() {
+    let (svg, image) = render(r#"
+        <clipPath id="c">
+            <path clip-rule="evenodd" d="M10 10H90V90H10Z M30 30H70V70H30Z"/>
+        </clipPath>
+        <rect width="100" height="100" fill="blue" clip-path="url(#c)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_eq!(pixel(&image, 20, 20), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 50, 50).a, 0);
+}
+
+#[test]
+fn test_clip_mixed_rules() {
+    // The even-odd child keeps its hole, except where the nonzero child, which would have a hole
+    // of its own under the even-odd rule, covers it.
+    let (svg, image) = render(r#"
+        <clipPath id="c">
+            <path clip-rule="evenodd" d="M10 10H90V90H10Z M30 30H70V70H30Z"/>
+            <path d="M40 40H60V60H40Z M45 45H55V55H45Z"/>
+        </clipPath>
+        <rect width="100" height="100" fill="blue" clip-path="url(#c)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_eq!(pixel(&image, 20, 20), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 35, 35).a, 0);
+    assert_eq!(pixel(&image, 50, 50), ColorU::new(0, 0, 255, 255));
+}
+
+#[test]
+fn test_clip_nested_and_object_bounding_box() {
+    // The outer clip keeps the left half of the bounding box, and its own clip keeps the top half.
+    let (svg, image) = render(r#"
+        <clipPath id="top" clipPathUnits="objectBoundingBox">
+            <rect x="0" y="0" width="1" height="0.5"/>
+        </clipPath>
+        <clipPath id="left" clipPathUnits="objectBoundingBox" clip-path="url(#top)">
+            <rect x="0" y="0" width="0.5" height="1"/>
+        </clipPath>
+        <rect x="20" y="20" width="60" height="60" fill="lime" clip-path="url(#left)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_eq!(pixel(&image, 30, 30), ColorU::new(0, 255, 0, 255));
+    assert_eq!(pixel(&image, 60, 30).a, 0);
+    assert_eq!(pixel(&image, 30, 60).a, 0);
+}
+
+#[test]
+fn test_clip_children_with_clip_paths() {
+    // The first child is clipped to the left half. The second one isn't clipped.
+    let (svg, image) = render(r#"
+        <clipPath id="left"><rect x="0" y="0" width="50" height="100"/></clipPath>
+        <clipPath id="c">
+            <rect x="0" y="0" width="100" height="50" clip-path="url(#left)"/>
+            <rect x="60" y="60" width="30" height="30"/>
+        </clipPath>
+        <rect width="100" height="50" fill="red" clip-path="url(#c)"/>
+        <rect y="50" width="100" height="50" fill="red" clip-path="url(#c)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_eq!(pixel(&image, 20, 20), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 70, 20).a, 0);
+    assert_eq!(pixel(&image, 75, 75), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 20, 70).a, 0);
+}
+
+#[test]
+fn test_clip_child_with_clip_path() {
+    let (svg, image) = render(r#"
+        <clipPath id="left"><rect x="0" y="0" width="50" height="100"/></clipPath>
+        <clipPath id="c">
+            <rect x="0" y="0" width="100" height="50" clip-path="url(#left)"/>
+        </clipPath>
+        <rect width="100" height="100" fill="red" clip-path="url(#c)"/>"#);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_eq!(pixel(&image, 20, 20), ColorU::new(255, 0, 0, 255));
+    assert_eq!(pixel(&image, 70, 20).a, 0);
+    assert_eq!(pixel(&image, 20, 70).a, 0);
+}
+
+#[test]
+fn test_clip_use_in_transformed_group() {
+    let (svg, image) = render(r##"
+        <defs><circle id="dot" cx="10" cy="10" r="8"/></defs>
+        <clipPath id="c">
+            <use xlink:href="#dot"/>
+            <use xlink:href="#dot" x="20"/>
+        </clipPath>
+        <g transform="scale(2)">
+            <rect width="50" height="50" fill="blue" clip-path="url(#c)"/>
+        </g>"##);
+    assert!(svg.result_flags.is_empty(), "{}", svg.result_flags);
+    assert_eq!(pixel(&image, 20, 20), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 60, 20), ColorU::new(0, 0, 255, 255));
+    assert_eq!(pixel(&image, 40, 3).a, 0);
+    assert_eq!(pixel(&image, 20, 60).a, 0);
+}
@@ -1,27 +1,317 @@
-use std::fs::File;
-use std::io::{Read, BufWriter};
-use std::error::Error;
-use std::path::PathBuf;
-use pathfinder_svg::SVGScene;
+// pathfinder/utils/convert/src/main.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Converts SVG, SWF, and Lottie files to SVG, PDF, PostScript, or PNG.
+//!
+//! Vector output is written with `pathfinder_export`. PNG output is rendered offscreen with the
+//! CPU renderer, so no GPU is needed.
+
+use clap::{App, Arg, ArgMatches};
+use pathfinder_color::ColorU;
+use pathfinder_content::outline::Outline;
 use pathfinder_export::{Export, FileFormat};
-use usvg::{Tree, Options};
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f, vec2i};
+use pathfinder_lottie::Lottie;
+use pathfinder_renderer::options::{BuildOptions, RenderTransform};
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::{DrawPath, Scene};
+use pathfinder_svg::SVGScene;
+use pathfinder_swf::{draw_paths_into_scene, process_swf_tags};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use usvg::{Options as UsvgOptions, Tree};
+
+// The resolution at which one scene unit maps to one pixel, as in CSS.
+const DEFAULT_DPI: f32 = 96.0;
+
+enum OutputFormat {
+    Vector(FileFormat),
+    PNG,
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut args = std::env::args_os().skip(1);
-    let input = PathBuf::from(args.next().expect("no input given"));
-    let output = PathBuf::from(args.next().expect("no output given"));
-
-    let mut data = Vec::new();
-    File::open(input)?.read_to_end(&mut data)?;
-    let svg = SVGScene::from_tree(&Tree::from_data(&data, &Options::default()).unwrap());
-
-    let scene = &svg.scene;
-    let mut writer = BufWriter::new(File::create(&output)?);
-    let format = match output.extension().and_then(|s| s.to_str()) {
-        Some("pdf") => FileFormat::PDF,
-        Some("ps") => FileFormat::PS,
-        _ => return Err("output filename must have .ps or .pdf extension".into())
-    };
-    scene.export(&mut writer, format).unwrap();
+    let matches = App::new("Pathfinder Converter")
+        .version("0.1")
+        .author("The Pathfinder Project Developers")
+        .about("Converts vector graphics between formats using Pathfinder")
+        .arg(Arg::with_name("INPUT").help("The `.svg`, `.swf`, or Lottie `.json` file to read")
+                                    .required(true)
+                                    .index(1))
+        .arg(Arg::with_name("OUTPUT").help("The `.svg`, `.pdf`, `.ps`, `.eps`, or `.png` file to \
+                                            write")
+                                     .required(true)
+                                     .index(2))
+        .arg(Arg::with_name("size").long("size")
+                                   .short("s")
+                                   .value_name("WIDTHxHEIGHT")
+                                   .help("The size of the PNG output in pixels; without --dpi, \
+                                          the view box is scaled uniformly to fit and centered \
+                                          [default: the view box scaled by the DPI]"))
+        .arg(Arg::with_name("dpi").long("dpi")
+                                  .short("d")
+                                  .value_name("DPI")
+                                  .help("The resolution of the PNG output; at 96 DPI, one unit \
+                                         is one pixel [default: 96, or the DPI that fits the \
+                                         view box to --size]"))
+        .arg(Arg::with_name("background").long("background")
+                                         .short("b")
+                                         .value_name("#RRGGBB[AA]")
+                                         .help("Fills the view box with a color behind the \
+                                                content [default: transparent, or the SWF \
+                                                stage color]"))
+        .arg(Arg::with_name("dilation").long("dilation")
+                                       .value_name("X[,Y]")
+                                       .help("Expands outlines by the given number of pixels \
+                                              when rendering PNG output"))
+        .arg(Arg::with_name("subpixel-aa").long("subpixel-aa")
+                                          .help("Renders PNG output with subpixel \
+                                                 antialiasing"))
+        .arg(Arg::with_name("frame").long("frame")
+                                    .short("f")
+                                    .value_name("FRAME")
+                                    .help("The frame of a Lottie animation to draw [default: \
+                                           the first frame]"))
+        .get_matches();
+
+    let input = Path::new(matches.value_of("INPUT").unwrap());
+    let output = Path::new(matches.value_of("OUTPUT").unwrap());
+
+    let output_format = match extension(output).as_ref().map(|ext| &ext[..]) {
+        Some("svg") => OutputFormat::Vector(FileFormat::SVG),
+        Some("pdf") => OutputFormat::Vector(FileFormat::PDF),
+        Some("ps") | Some("eps") => OutputFormat::Vector(FileFormat::PS),
+        Some("png") => OutputFormat::PNG,
+        _ => return Err("unsupported output extension; use .svg, .pdf, .ps, .eps, or .png".into()),
+    };
+    if let OutputFormat::Vector(_) = output_format {
+        for option in &["size", "dpi", "dilation", "subpixel-aa"] {
+            if matches.is_present(option) {
+                return Err(format!("--{} only applies to PNG output", option).into());
+            }
+        }
+    }
+
+    let data = fs::read(input)?;
+    let (scene, default_background) = match extension(input).as_ref().map(|ext| &ext[..]) {
+        Some("svg") => (load_svg(&data, input)?, None),
+        Some("swf") => load_swf(&data)?,
+        Some("json") => (load_lottie(&data, &matches)?, None),
+        _ => return Err("unsupported input extension; use .svg, .swf, or .json".into()),
+    };
+
+    let background = match matches.value_of("background") {
+        Some(color) => Some(parse_color(color)?),
+        None => default_background,
+    };
+    let mut scene = match background {
+        Some(color) => add_background(scene, color),
+        None => scene,
+    };
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    match output_format {
+        OutputFormat::Vector(format) => scene.export(&mut writer, format)?,
+        OutputFormat::PNG => {
+            let view_box = scene.view_box();
+            // Render the view box with its origin moved to the top left of the image.
+            let (size, transform) = match (matches.value_of("size"), matches.value_of("dpi")) {
+                (Some(size), None) => {
+                    let size = parse_size(size)?;
+                    (size, fit_transform(view_box, size))
+                }
+                (size, dpi) => {
+                    let scale = Vector2F::splat(parse_dpi(dpi)? / DEFAULT_DPI);
+                    let size = match size {
+                        Some(size) => parse_size(size)?,
+                        None => (view_box.size() * scale).ceil().to_i32(),
+                    };
+                    (size, Transform2F::from_scale(scale) *
+                           Transform2F::from_translation(-view_box.origin()))
+                }
+            };
+
+            scene.set_view_box(RectF::new(Vector2F::zero(), size.to_f32()));
+            let build_options = BuildOptions {
+                transform: RenderTransform::Transform2D(transform),
+                dilation: match matches.value_of("dilation") {
+                    Some(dilation) => parse_dilation(dilation)?,
+                    None => Vector2F::zero(),
+                },
+                subpixel_aa_enabled: matches.is_present("subpixel-aa"),
+            };
+            scene.rasterize_to_png(size, build_options, &mut writer)?;
+        }
+    }
     Ok(())
 }
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase())
+}
+
+fn load_svg(data: &[u8], path: &Path) -> Result<Scene, Box<dyn Error>> {
+    // Resolve external images relative to the SVG file.
+    let options = UsvgOptions {
+        resources_dir: path.parent().map(Path::to_owned),
+        ..UsvgOptions::default()
+    };
+    let tree = Tree::from_data(data, &options)?;
+    Ok(SVGScene::from_tree(&tree).scene)
+}
+
+// Returns the scene along with the stage background color, which is drawn unless the user asks
+// for a different background.
+fn load_swf(data: &[u8]) -> Result<(Scene, Option<ColorU>), Box<dyn Error>> {
+    let (_, movie): (_, swf_types::Movie) = swf_parser::streaming::movie::parse_movie(data)
+        .map_err(|_| "failed to parse SWF file")?;
+    let (library, stage) = process_swf_tags(&movie);
+
+    let mut scene = Scene::new();
+    let stage_size = vec2f(stage.width() as f32, stage.height() as f32);
+    scene.set_view_box(RectF::new(Vector2F::zero(), stage_size));
+    draw_paths_into_scene(&library, &mut scene);
+    Ok((scene, Some(stage.background_color().to_u8())))
+}
+
+fn load_lottie(data: &[u8], matches: &ArgMatches) -> Result<Scene, Box<dyn Error>> {
+    let lottie = Lottie::from_reader(data)?;
+    let frame = match matches.value_of("frame") {
+        Some(frame) => frame.parse().map_err(|_| format!("invalid frame `{}`", frame))?,
+        None => lottie.in_point as f32,
+    };
+
+    let mut scene = Scene::new();
+    let size = vec2f(lottie.width as f32, lottie.height as f32);
+    scene.set_view_box(RectF::new(Vector2F::zero(), size));
+    lottie.draw_frame_into_scene(frame, &mut scene);
+    Ok(scene)
+}
+
+fn add_background(scene: Scene, color: ColorU) -> Scene {
+    let view_box = scene.view_box();
+    let mut background = Scene::new();
+    background.set_view_box(view_box);
+    let paint = background.push_paint(&Paint::from_color(color));
+    background.push_draw_path(DrawPath::new(Outline::from_rect(view_box), paint));
+    background.append_scene(scene);
+    background
+}
+
+// Scales the view box uniformly to fit an image of the given size, centering it.
+fn fit_transform(view_box: RectF, size: Vector2I) -> Transform2F {
+    let size = size.to_f32();
+    let scale = if view_box.width() > 0.0 && view_box.height() > 0.0 {
+        f32::min(size.x() / view_box.width(), size.y() / view_box.height())
+    } else {
+        1.0
+    };
+    let offset = (size - view_box.size() * scale) * 0.5;
+    Transform2F::from_translation(offset) *
+        Transform2F::from_scale(Vector2F::splat(scale)) *
+        Transform2F::from_translation(-view_box.origin())
+}
+
+fn parse_size(size: &str) -> Result<Vector2I, Box<dyn Error>> {
+    let error = || format!("invalid size `{}`; expected WIDTHxHEIGHT", size);
+    let mut dimensions = size.splitn(2, 'x');
+    let mut dimension = || -> Result<i32, Box<dyn Error>> {
+        match dimensions.next().and_then(|dimension| dimension.trim().parse().ok()) {
+            Some(dimension) if dimension > 0 => Ok(dimension),
+            _ => Err(error().into()),
+        }
+    };
+    let (width, height) = (dimension()?, dimension()?);
+    Ok(vec2i(width, height))
+}
+
+fn parse_dpi(dpi: Option<&str>) -> Result<f32, Box<dyn Error>> {
+    match dpi {
+        None => Ok(DEFAULT_DPI),
+        Some(dpi) => match dpi.parse::<f32>() {
+            Ok(dpi) if dpi > 0.0 => Ok(dpi),
+            _ => Err(format!("invalid DPI `{}`", dpi).into()),
+        },
+    }
+}
+
+fn parse_dilation(dilation: &str) -> Result<Vector2F, Box<dyn Error>> {
+    let error = || format!("invalid dilation `{}`; expected X[,Y]", dilation);
+    let values = dilation.split(',')
+                         .map(|value| value.trim().parse::<f32>())
+                         .collect::<Result<Vec<_>, _>>()
+                         .map_err(|_| error())?;
+    match values[..] {
+        [amount] => Ok(Vector2F::splat(amount)),
+        [x, y] => Ok(vec2f(x, y)),
+        _ => Err(error().into()),
+    }
+}
+
+fn parse_color(color: &str) -> Result<ColorU, Box<dyn Error>> {
+    let error = || format!("invalid color `{}`; expected #RRGGBB or #RRGGBBAA", color);
+    let hex = color.trim_start_matches('#');
+    if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(error().into());
+    }
+    let channel = |index: usize| {
+        hex.get(index * 2..index * 2 + 2).map_or(255, |digits| {
+            u8::from_str_radix(digits, 16).unwrap()
+        })
+    };
+    Ok(ColorU { r: channel(0), g: channel(1), b: channel(2), a: channel(3) })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{fit_transform, parse_color, parse_dilation, parse_size};
+    use pathfinder_color::ColorU;
+    use pathfinder_geometry::rect::RectF;
+    use pathfinder_geometry::vector::{Vector2F, vec2f, vec2i};
+
+    #[test]
+    fn size() {
+        assert_eq!(parse_size("640x480").unwrap(), vec2i(640, 480));
+        assert_eq!(parse_size(" 64 x 48 ").unwrap(), vec2i(64, 48));
+        for size in &["640", "640x", "x480", "0x480", "640x-1", "64x48x1", "axb", ""] {
+            assert!(parse_size(size).is_err(), "{}", size);
+        }
+    }
+
+    #[test]
+    fn color() {
+        assert_eq!(parse_color("#ff8000").unwrap(), ColorU::new(255, 128, 0, 255));
+        assert_eq!(parse_color("FF800040").unwrap(), ColorU::new(255, 128, 0, 64));
+        for color in &["#fff", "#ff80001", "#gg8000", "#ff8000ff00", "", "#+f8000"] {
+            assert!(parse_color(color).is_err(), "{}", color);
+        }
+    }
+
+    #[test]
+    fn dilation() {
+        assert_eq!(parse_dilation("0.5").unwrap(), Vector2F::splat(0.5));
+        assert_eq!(parse_dilation("1, 2").unwrap(), vec2f(1.0, 2.0));
+        for dilation in &["", "a", "1,", "1,2,3"] {
+            assert!(parse_dilation(dilation).is_err(), "{}", dilation);
+        }
+    }
+
+    #[test]
+    fn fit_size() {
+        // A 200x100 view box fits a 100x100 image at half scale, centered vertically.
+        let view_box = RectF::new(vec2f(10.0, 20.0), vec2f(200.0, 100.0));
+        let transform = fit_transform(view_box, vec2i(100, 100));
+        assert_eq!(transform * view_box.origin(), vec2f(0.0, 25.0));
+        assert_eq!(transform * view_box.lower_right(), vec2f(100.0, 75.0));
+    }
+}
@@ -0,0 +1,207 @@
+// pathfinder/utils/reftests/src/lib.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reference image comparison for Pathfinder's regression tests.
+//!
+//! The tests in this crate render each file in `resources/svg` and `resources/swf` and compare
+//! the result against a reference PNG in `resources/reftests`. A test without a reference image
+//! fails. The following environment variables control their behavior:
+//!
+//! * `PATHFINDER_REFTEST_TOLERANCE`: The maximum difference allowed in any color channel of a
+//!   pixel before that pixel is considered to differ. Defaults to 2.
+//!
+//! * `PATHFINDER_REFTEST_BLESS`: If set, the rendered images are written out as the new reference
+//!   images instead of being compared.
+//!
+//! * `PATHFINDER_REFTEST_OUTPUT`: The directory that diff images are written to when a test fails.
+//!   Defaults to `target/reftests`.
+
+use image::{ImageResult, Rgba, RgbaImage};
+use pathfinder_content::pattern::Image;
+use std::env;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_TOLERANCE: u8 = 2;
+
+/// Settings that control how rendered images are compared to their references.
+#[derive(Clone, Debug)]
+pub struct ReftestOptions {
+    /// The directory containing the reference images.
+    pub reference_dir: PathBuf,
+    /// The directory that the rendered and diff images of failing tests are written to.
+    pub output_dir: PathBuf,
+    /// The maximum per-channel difference at which two pixels are still considered equal.
+    pub tolerance: u8,
+    /// If true, the rendered images replace the reference images.
+    pub bless: bool,
+}
+
+/// The result of comparing two images.
+#[derive(Clone)]
+pub struct Comparison {
+    /// The number of pixels that differ by more than the tolerance.
+    pub failing_pixels: usize,
+    /// The largest per-channel difference found between the two images.
+    pub max_difference: u8,
+    /// An image highlighting the differing pixels in red over a faded copy of the reference.
+    pub diff: RgbaImage,
+}
+
+/// The outcome of a single reference test.
+#[derive(Clone, Debug)]
+pub enum ReftestOutcome {
+    /// The rendered image matched the reference.
+    Passed,
+    /// The rendered image was written out as the new reference.
+    Blessed,
+    /// There is no reference image for this test.
+    NoReference,
+    /// The rendered image differed from the reference.
+    Failed {
+        failing_pixels: usize,
+        max_difference: u8,
+        /// The path that the diff image was written to.
+        diff_path: PathBuf,
+    },
+}
+
+impl ReftestOptions {
+    /// Creates options that read reference images from the given subdirectory of
+    /// `resources/reftests`, honoring the environment variables listed in the crate
+    /// documentation.
+    pub fn from_env(suite: &str) -> ReftestOptions {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        let tolerance = match env::var("PATHFINDER_REFTEST_TOLERANCE") {
+            Ok(tolerance) => {
+                tolerance.parse().expect("PATHFINDER_REFTEST_TOLERANCE must be a number from 0-255")
+            }
+            Err(_) => DEFAULT_TOLERANCE,
+        };
+        let output_dir = match env::var_os("PATHFINDER_REFTEST_OUTPUT") {
+            Some(output_dir) => PathBuf::from(output_dir),
+            None => root.join("target/reftests"),
+        };
+        ReftestOptions {
+            reference_dir: root.join("resources/reftests").join(suite),
+            output_dir: output_dir.join(suite),
+            tolerance,
+            bless: env::var_os("PATHFINDER_REFTEST_BLESS").is_some(),
+        }
+    }
+
+    /// Checks the rendered image for the test with the given name against its reference.
+    pub fn check(&self, name: &str, image: &RgbaImage) -> ImageResult<ReftestOutcome> {
+        let reference_path = self.reference_dir.join(format!("{}.png", name));
+        if self.bless {
+            fs::create_dir_all(&self.reference_dir)?;
+            image.save(&reference_path)?;
+            return Ok(ReftestOutcome::Blessed);
+        }
+        if !reference_path.exists() {
+            return Ok(ReftestOutcome::NoReference);
+        }
+
+        let reference = image::open(&reference_path)?.to_rgba8();
+        let comparison = compare(image, &reference, self.tolerance);
+        if comparison.failing_pixels == 0 {
+            return Ok(ReftestOutcome::Passed);
+        }
+
+        fs::create_dir_all(&self.output_dir)?;
+        image.save(self.output_dir.join(format!("{}.png", name)))?;
+        let diff_path = self.output_dir.join(format!("{}-diff.png", name));
+        comparison.diff.save(&diff_path)?;
+        Ok(ReftestOutcome::Failed {
+            failing_pixels: comparison.failing_pixels,
+            max_difference: comparison.max_difference,
+            diff_path,
+        })
+    }
+}
+
+/// Compares two images pixel by pixel.
+///
+/// If the images differ in size, every pixel outside their intersection counts as failing.
+pub fn compare(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> Comparison {
+    let width = actual.width().max(expected.width());
+    let height = actual.height().max(expected.height());
+    let mut diff = RgbaImage::new(width, height);
+    let (mut failing_pixels, mut max_difference) = (0, 0);
+
+    for (x, y, diff_pixel) in diff.enumerate_pixels_mut() {
+        let (actual_pixel, expected_pixel) = match (get_pixel(actual, x, y),
+                                                    get_pixel(expected, x, y)) {
+            (Some(actual_pixel), Some(expected_pixel)) => (actual_pixel, expected_pixel),
+            _ => {
+                failing_pixels += 1;
+                max_difference = 255;
+                *diff_pixel = Rgba([255, 0, 0, 255]);
+                continue;
+            }
+        };
+
+        let difference = actual_pixel.0
+                                     .iter()
+                                     .zip(expected_pixel.0.iter())
+                                     .map(|(&a, &b)| a.max(b) - a.min(b))
+                                     .max()
+                                     .unwrap();
+        max_difference = max_difference.max(difference);
+        if difference > tolerance {
+            failing_pixels += 1;
+            *diff_pixel = Rgba([255, 0, 0, 255]);
+        } else {
+            let [r, g, b, a] = expected_pixel.0;
+            let luma = ((r as u32 * 3 + g as u32 * 6 + b as u32) / 10) * a as u32 / 255;
+            let faded = (255 - (255 - luma) / 4) as u8;
+            *diff_pixel = Rgba([faded, faded, faded, 255]);
+        }
+    }
+
+    Comparison { failing_pixels, max_difference, diff }
+}
+
+/// Converts an image rendered by Pathfinder to an `RgbaImage`.
+pub fn to_rgba_image(image: &Image) -> RgbaImage {
+    let size = image.size();
+    let pixels = pathfinder_color::color_slice_to_u8_slice(image.pixels()).to_vec();
+    RgbaImage::from_raw(size.x() as u32, size.y() as u32, pixels).unwrap()
+}
+
+#[inline]
+fn get_pixel(image: &RgbaImage, x: u32, y: u32) -> Option<Rgba<u8>> {
+    if x < image.width() && y < image.height() {
+        Some(*image.get_pixel(x, y))
+    } else {
+        None
+    }
+}
+
+impl Display for ReftestOutcome {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match *self {
+            ReftestOutcome::Passed => write!(formatter, "passed"),
+            ReftestOutcome::Blessed => write!(formatter, "blessed"),
+            ReftestOutcome::NoReference => {
+                write!(formatter,
+                       "no reference image; run with PATHFINDER_REFTEST_BLESS=1 to create one")
+            }
+            ReftestOutcome::Failed { failing_pixels, max_difference, ref diff_path } => {
+                write!(formatter,
+                       "{} pixels differ (max difference {}); see {}",
+                       failing_pixels,
+                       max_difference,
+                       diff_path.display())
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// pathfinder/utils/reftests/tests/reftests.rs
+//
+// For this file only, any copyright is dedicated to the Public Domain.
+// https://creativecommons.org/publicdomain/zero/1.0/
+
+use pathfinder_content::outline::Outline;
+use pathfinder_geometry::rect::RectF;
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, vec2f};
+use pathfinder_renderer::options::{BuildOptions, RenderTransform};
+use pathfinder_renderer::paint::Paint;
+use pathfinder_renderer::scene::{DrawPath, Scene};
+use pathfinder_svg::SVGScene;
+use pathfinder_swf::{draw_paths_into_scene, process_swf_tags};
+use reftests::{ReftestOptions, ReftestOutcome};
+use std::fs;
+use std::path::{Path, PathBuf};
+use usvg::{Options as UsvgOptions, Tree};
+
+fn resource_files(dir: &str, extension: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../resources").join(dir);
+    let mut paths: Vec<_> = fs::read_dir(dir).unwrap()
+                                             .map(|entry| entry.unwrap().path())
+                                             .filter(|path| {
+                                                 path.extension().map_or(false, |ext| {
+                                                     ext == extension
+                                                 })
+                                             })
+                                             .collect();
+    paths.sort();
+    paths
+}
+
+// Renders the scene's view box, with its origin moved to the top left of the image.
+fn rasterize(scene: &mut Scene) -> image::RgbaImage {
+    let view_box = scene.view_box();
+    let size = view_box.size().ceil().to_i32();
+    scene.set_view_box(RectF::new(Vector2F::zero(), view_box.size()));
+    let build_options = BuildOptions {
+        transform: RenderTransform::Transform2D(Transform2F::from_translation(-view_box.origin())),
+        ..BuildOptions::default()
+    };
+    reftests::to_rgba_image(&scene.rasterize(size, build_options))
+}
+
+fn run_suite<F>(suite: &str, paths: &[PathBuf], mut build_scene: F)
+                where F: FnMut(&[u8]) -> Scene {
+    let options = ReftestOptions::from_env(suite);
+    let mut failures = vec![];
+    for path in paths {
+        let name = path.file_stem().unwrap().to_string_lossy();
+        let mut scene = build_scene(&fs::read(path).unwrap());
+        let outcome = options.check(&name, &rasterize(&mut scene)).unwrap();
+        match outcome {
+            ReftestOutcome::Passed | ReftestOutcome::Blessed => {}
+            ReftestOutcome::NoReference | ReftestOutcome::Failed { .. } => {
+                failures.push(format!("{}/{}: {}", suite, name, outcome))
+            }
+        }
+    }
+    assert!(failures.is_empty(), "reference tests failed:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_svg_reftests() {
+    run_suite("svg", &resource_files("svg", "svg"), |data| {
+        SVGScene::from_tree(&Tree::from_data(data, &UsvgOptions::default()).unwrap()).scene
+    });
+}
+
+#[test]
+fn test_swf_reftests() {
+    run_suite("swf", &resource_files("swf", "swf"), |data| {
+        let (_, movie): (_, swf_types::Movie) =
+            swf_parser::streaming::movie::parse_movie(data).unwrap();
+        let (library, stage) = process_swf_tags(&movie);
+
+        let mut scene = Scene::new();
+        let stage_size = vec2f(stage.width() as f32, stage.height() as f32);
+        scene.set_view_box(RectF::new(Vector2F::zero(), stage_size));
+        let background = scene.push_paint(&Paint::from_color(stage.background_color().to_u8()));
+        let outline = Outline::from_rect(RectF::new(Vector2F::zero(), stage_size));
+        scene.push_draw_path(DrawPath::new(outline, background));
+        draw_paths_into_scene(&library, &mut scene);
+        scene
+    });
+}
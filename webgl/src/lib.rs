@@ -813,7 +813,9 @@ impl Device for WebGlDevice {
         self.context
             .tex_parameteri(WebGl::TEXTURE_2D,
                             WebGl::TEXTURE_WRAP_S,
-                            if flags.contains(TextureSamplingFlags::REPEAT_U) {
+                            if flags.contains(TextureSamplingFlags::MIRROR_U) {
+                                WebGl::MIRRORED_REPEAT as i32
+                            } else if flags.contains(TextureSamplingFlags::REPEAT_U) {
                                 WebGl::REPEAT as i32
                             } else {
                                 WebGl::CLAMP_TO_EDGE as i32
@@ -821,7 +823,9 @@ impl Device for WebGlDevice {
         self.context
             .tex_parameteri(WebGl::TEXTURE_2D,
                             WebGl::TEXTURE_WRAP_T,
-                            if flags.contains(TextureSamplingFlags::REPEAT_V) {
+                            if flags.contains(TextureSamplingFlags::MIRROR_V) {
+                                WebGl::MIRRORED_REPEAT as i32
+                            } else if flags.contains(TextureSamplingFlags::REPEAT_V) {
                                 WebGl::REPEAT as i32
                             } else {
                                 WebGl::CLAMP_TO_EDGE as i32